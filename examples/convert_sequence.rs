@@ -0,0 +1,68 @@
+//! Example showing how to convert a folder of per-frame images into a 6D file
+
+use clap::Parser;
+use pyama_rust::io::{save_array, ImageSequence};
+
+#[derive(Parser)]
+#[command(name = "convert_sequence")]
+#[command(about = "Convert a folder of per-frame TIFF/PNG images into a 6D file")]
+struct Args {
+    /// Folder holding the images
+    #[arg(short, long)]
+    input: String,
+
+    /// File name pattern with named captures t, p, z and c
+    #[arg(long, default_value = r"pos(?P<p>\d+)_t(?P<t>\d+)_c(?P<c>\d+)\.tif")]
+    pattern: String,
+
+    /// Output file path (.meta, .ome.tif, .zarr, .npy or .npz)
+    #[arg(short, long, default_value = "converted.meta")]
+    output: String,
+
+    /// Pixel size in micrometers
+    #[arg(long, default_value = "1.0")]
+    pixel_size: f64,
+
+    /// Time interval in seconds
+    #[arg(long, default_value = "1.0")]
+    time_interval: f64,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    println!("=== Converting Image Sequence: {} ===\n", args.input);
+
+    // Match file names against the pattern
+    println!("1. Scanning folder...");
+    let sequence = ImageSequence::scan(&args.input, &args.pattern)?
+        .pixel_size(args.pixel_size)
+        .time_interval(args.time_interval);
+    let [t, p, z, c] = sequence.axis_values().clone().map(|values| values.len());
+    println!("Found {} files: {} time points, {} positions, {} z slices, {} channels",
+             sequence.file_count(), t, p, z, c);
+
+    // Report gaps before reading any pixels
+    let missing = sequence.missing();
+    if !missing.is_empty() {
+        println!("✗ {} frames are missing:", missing.len());
+        for [t, p, z, c] in missing {
+            println!("  t={} p={} z={} c={}", t, p, z, c);
+        }
+        std::process::exit(1);
+    }
+    println!();
+
+    // Read all frames in their native data type and save
+    println!("2. Loading images...");
+    let array = sequence.load::<f32>()?;
+    println!("Loaded {} array with dimensions {:?}", array.data_type(), array.dimensions().shape());
+
+    println!("Saving to: {}", args.output);
+    save_array(&array, &args.output)?;
+
+    println!("✓ Converted: {}", args.output);
+    println!("\nTo inspect this file, run: cargo run --example load_6d -- --input {}", args.output);
+
+    Ok(())
+}
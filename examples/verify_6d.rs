@@ -0,0 +1,42 @@
+//! Example showing how to check split files for corrupt frames
+
+use clap::Parser;
+use pyama_rust::io::verify_file;
+
+#[derive(Parser)]
+#[command(name = "verify_6d")]
+#[command(about = "Check split files (.meta + .data) for corrupt frames")]
+struct Args {
+    /// `.meta` files to check
+    #[arg(required = true)]
+    inputs: Vec<String>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let mut failed = 0;
+    for input in &args.inputs {
+        println!("=== Verifying {} ===", input);
+        match verify_file(input) {
+            Ok(report) => {
+                print!("{}", report);
+                if !report.is_ok() {
+                    failed += 1;
+                }
+            }
+            Err(e) => {
+                println!("✗ Cannot read file: {}", e);
+                failed += 1;
+            }
+        }
+        println!();
+    }
+
+    if failed > 0 {
+        println!("✗ {} of {} files failed verification", failed, args.inputs.len());
+        std::process::exit(1);
+    }
+    println!("✓ All {} files verified", args.inputs.len());
+    Ok(())
+}
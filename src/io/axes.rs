@@ -0,0 +1,279 @@
+//! Axis specifications of foreign N-D arrays
+//!
+//! Arrays read from NumPy files or headerless binary dumps come with their
+//! axes in whatever order the producer chose. An [`AxisSpec`] such as
+//! `"CTYX"` names each axis with a letter of TPZCYX so the array can be
+//! brought into TPZCYX order; axes missing from the spec get length 1.
+//! Split files record the order of their `.data` file the same way and are
+//! read through the [strides](AxisSpec::strides) of that order.
+
+use super::array_6d::Dimensions;
+use anyhow::{Result, anyhow};
+use ndarray::{Array6, ArrayD, Axis, Ix6};
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Axis letters of `Array6D` in storage order
+pub const TPZCYX: [char; 6] = ['T', 'P', 'Z', 'C', 'Y', 'X'];
+
+/// Meaning of each axis of an N-D array, e.g. `TCYX` for a 4D array
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct AxisSpec {
+    /// Axis letters in array order
+    axes: Vec<char>,
+}
+
+impl AxisSpec {
+    /// Get the spec assumed when none is given: `YX`, `TYX`, `TCYX`, `TZCYX` or `TPZCYX`
+    pub fn default_for(ndim: usize) -> Result<Self> {
+        match ndim {
+            2 => "YX".parse(),
+            3 => "TYX".parse(),
+            4 => "TCYX".parse(),
+            5 => "TZCYX".parse(),
+            6 => "TPZCYX".parse(),
+            _ => Err(anyhow!("Expected a 2D to 6D array, got {} dimensions", ndim)),
+        }
+    }
+
+    /// Get number of axes
+    pub fn ndim(&self) -> usize {
+        self.axes.len()
+    }
+
+    /// Get axis letters in array order
+    pub fn letters(&self) -> &[char] {
+        &self.axes
+    }
+
+    /// Get the position of TPZCYX axis `letter` in the array, if present
+    pub fn position(&self, letter: char) -> Option<usize> {
+        self.axes.iter().position(|&a| a == letter)
+    }
+
+    /// Check whether the axes already appear in TPZCYX order
+    ///
+    /// A C-order array with such a spec has the byte layout of a TPZCYX
+    /// array with the missing axes inserted.
+    pub fn is_tpzcyx_order(&self) -> bool {
+        let ranks: Vec<usize> = self.axes.iter().map(|&a| rank(a)).collect();
+        ranks.windows(2).all(|w| w[0] < w[1])
+    }
+
+    /// Get the spec of arrays in `Array6D` order
+    pub fn tpzcyx() -> Self {
+        Self { axes: TPZCYX.to_vec() }
+    }
+
+    /// Check whether every frame is a contiguous YX plane, as needed for chunked files
+    pub fn has_contiguous_frames(&self) -> bool {
+        self.axes.ends_with(&['Y', 'X'])
+    }
+
+    /// Get the shape of a C-order array with these axes and the given dimensions
+    pub fn shape(&self, dimensions: &Dimensions) -> Vec<usize> {
+        let extents = dimensions.shape();
+        self.axes.iter().map(|&a| extents[rank(a)]).collect()
+    }
+
+    /// Check that the axes missing from the spec have length 1 in `dimensions`
+    pub fn check_dimensions(&self, dimensions: &Dimensions) -> Result<()> {
+        let extents = dimensions.shape();
+        if let Some(k) = (0..TPZCYX.len()).find(|&k| extents[k] > 1 && self.position(TPZCYX[k]).is_none()) {
+            return Err(anyhow!(
+                "Axis order {} has no {} axis, but the array has {} of them",
+                self, TPZCYX[k], extents[k]
+            ));
+        }
+        Ok(())
+    }
+
+    /// Get the element strides of the T, P, Z, C, Y and X axes of a C-order array with these axes
+    ///
+    /// Axes missing from the spec have length 1 and stride 0.
+    pub fn strides(&self, dimensions: &Dimensions) -> [usize; 6] {
+        let shape = self.shape(dimensions);
+        let mut strides = [0; 6];
+        let mut stride = 1;
+        for (i, &letter) in self.axes.iter().enumerate().rev() {
+            strides[rank(letter)] = stride;
+            stride *= shape[i];
+        }
+        strides
+    }
+
+    /// Get the TPZCYX axis index of every axis in order, missing axes first
+    ///
+    /// Permuting a TPZCYX array by it gives a view whose logical order is the
+    /// order of these axes.
+    pub fn permutation(&self) -> [usize; 6] {
+        let mut permutation = [0; 6];
+        let missing = (0..TPZCYX.len()).filter(|&k| self.position(TPZCYX[k]).is_none());
+        for (slot, k) in missing.chain(self.axes.iter().map(|&a| rank(a))).enumerate() {
+            permutation[slot] = k;
+        }
+        permutation
+    }
+
+    /// Get the (t, p, z, c) coordinates of the first sample of stored frame `index`
+    ///
+    /// Stored frames are consecutive runs of one frame's worth of samples; with
+    /// [contiguous frames](Self::has_contiguous_frames) each is exactly one frame.
+    pub fn stored_frame_coords(&self, dimensions: &Dimensions, index: usize) -> (usize, usize, usize, usize) {
+        let shape = self.shape(dimensions);
+        let mut offset = index * dimensions.frame_elements();
+        let mut coords = [0; 6];
+        for (i, &letter) in self.axes.iter().enumerate().rev() {
+            coords[rank(letter)] = offset % shape[i];
+            offset /= shape[i];
+        }
+        (coords[0], coords[1], coords[2], coords[3])
+    }
+
+    /// Check that `shape` has one extent per axis
+    pub fn check_shape(&self, shape: &[usize]) -> Result<()> {
+        if shape.len() != self.ndim() {
+            return Err(anyhow!(
+                "Axis spec '{}' has {} axes but the array has shape {:?}",
+                self, self.ndim(), shape
+            ));
+        }
+        Ok(())
+    }
+
+    /// Get TPZCYX dimensions of an array of the given shape
+    pub fn dimensions(&self, shape: &[usize]) -> Result<Dimensions> {
+        self.check_shape(shape)?;
+        let size = |letter: char| self.position(letter).map_or(1, |i| shape[i]);
+        let dimensions = Dimensions {
+            time: size('T'),
+            position: size('P'),
+            z: size('Z'),
+            channel: size('C'),
+            height: size('Y'),
+            width: size('X'),
+        };
+        dimensions.validate_extents()?;
+        Ok(dimensions)
+    }
+
+    /// Bring an array with these axes into standard-layout TPZCYX order
+    pub fn to_tpzcyx<T: Clone>(&self, array: ArrayD<T>) -> Result<Array6<T>> {
+        self.check_shape(array.shape())?;
+
+        // Permute the present axes into TPZCYX order, then add the missing ones
+        let mut order: Vec<usize> = (0..self.ndim()).collect();
+        order.sort_by_key(|&i| rank(self.axes[i]));
+        let mut array = array.permuted_axes(order);
+        for (k, letter) in TPZCYX.iter().enumerate() {
+            if self.position(*letter).is_none() {
+                array.insert_axis_inplace(Axis(k));
+            }
+        }
+        Ok(array.into_dimensionality::<Ix6>()?.as_standard_layout().into_owned())
+    }
+}
+
+/// Get the TPZCYX position of an axis letter
+fn rank(letter: char) -> usize {
+    TPZCYX.iter().position(|&l| l == letter).unwrap_or(TPZCYX.len())
+}
+
+impl FromStr for AxisSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let axes: Vec<char> = spec.trim().chars().map(|c| c.to_ascii_uppercase()).collect();
+        if let Some(bad) = axes.iter().find(|a| !TPZCYX.contains(a)) {
+            return Err(anyhow!("Unknown axis '{}' in '{}' (expected letters of TPZCYX)", bad, spec));
+        }
+        if let Some(dup) = TPZCYX.iter().find(|l| axes.iter().filter(|a| a == l).count() > 1) {
+            return Err(anyhow!("Axis '{}' appears more than once in '{}'", dup, spec));
+        }
+        if !axes.contains(&'Y') || !axes.contains(&'X') {
+            return Err(anyhow!("Axis spec '{}' must contain Y and X", spec));
+        }
+        Ok(Self { axes })
+    }
+}
+
+impl TryFrom<String> for AxisSpec {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<AxisSpec> for String {
+    fn from(spec: AxisSpec) -> Self {
+        spec.to_string()
+    }
+}
+
+impl fmt::Display for AxisSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.axes.iter().try_for_each(|a| write!(f, "{}", a))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::IxDyn;
+
+    #[test]
+    fn test_axis_spec() {
+        assert_eq!("tcyx".parse::<AxisSpec>().unwrap().to_string(), "TCYX");
+        assert!("TCY".parse::<AxisSpec>().is_err());
+        assert!("TTYX".parse::<AxisSpec>().is_err());
+        assert!("QYX".parse::<AxisSpec>().is_err());
+        assert_eq!(AxisSpec::default_for(5).unwrap().to_string(), "TZCYX");
+        assert!(AxisSpec::default_for(7).is_err());
+
+        assert!("TCYX".parse::<AxisSpec>().unwrap().is_tpzcyx_order());
+        assert!(!"CTYX".parse::<AxisSpec>().unwrap().is_tpzcyx_order());
+        assert!(!"TCXY".parse::<AxisSpec>().unwrap().is_tpzcyx_order());
+    }
+
+    #[test]
+    fn test_to_tpzcyx() {
+        // C=2, T=3, X=4, Y=1 with value 100c + 10t + x
+        let values: Vec<u16> = (0..2).flat_map(|c| (0..3).flat_map(move |t| (0..4).map(move |x| 100 * c + 10 * t + x))).collect();
+        let array = ArrayD::from_shape_vec(IxDyn(&[2, 3, 4, 1]), values).unwrap();
+        let axes: AxisSpec = "CTXY".parse().unwrap();
+
+        assert_eq!(axes.dimensions(&[2, 3, 4, 1]).unwrap(), Dimensions::new(3, 1, 1, 2, 1, 4));
+        let data = axes.to_tpzcyx(array).unwrap();
+        assert_eq!(data.shape(), [3, 1, 1, 2, 1, 4]);
+        assert_eq!(data[[2, 0, 0, 1, 0, 3]], 123);
+        assert!(axes.dimensions(&[2, 3, 4]).is_err());
+    }
+
+    #[test]
+    fn test_strides() {
+        let dims = Dimensions::new(3, 2, 1, 4, 5, 6);
+        assert_eq!(AxisSpec::tpzcyx().strides(&dims), [240, 120, 120, 30, 6, 1]);
+        assert_eq!(AxisSpec::tpzcyx().permutation(), [0, 1, 2, 3, 4, 5]);
+
+        let order: AxisSpec = "PTCYX".parse().unwrap();
+        assert!(order.check_dimensions(&dims).is_ok());
+        assert_eq!(order.shape(&dims), [2, 3, 4, 5, 6]);
+        assert_eq!(order.strides(&dims), [120, 360, 0, 30, 6, 1]);
+        assert_eq!(order.permutation(), [2, 1, 0, 3, 4, 5]);
+        assert!(order.has_contiguous_frames());
+        // Stored frame 5 is p=0, t=1, c=1
+        assert_eq!(order.stored_frame_coords(&dims, 5), (1, 0, 0, 1));
+
+        let interleaved: AxisSpec = "TPYXC".parse().unwrap();
+        assert!(!interleaved.has_contiguous_frames());
+        assert_eq!(interleaved.strides(&dims), [240, 120, 0, 1, 24, 4]);
+        assert!("TCYX".parse::<AxisSpec>().unwrap().check_dimensions(&dims).is_err());
+
+        assert_eq!(serde_json::to_string(&order).unwrap(), r#""PTCYX""#);
+        assert_eq!(serde_json::from_str::<AxisSpec>(r#""ptcyx""#).unwrap(), order);
+        assert!(serde_json::from_str::<AxisSpec>(r#""PTCY""#).is_err());
+    }
+}
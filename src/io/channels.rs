@@ -0,0 +1,211 @@
+//! Per-channel acquisition settings
+//!
+//! Each channel of an array can carry the settings it was acquired with:
+//! excitation and emission wavelengths, exposure, camera gain and offset,
+//! filter, the colour it is displayed in and what it shows. Every field is
+//! optional, since imported files rarely record all of them.
+
+use serde::{Serialize, Deserialize};
+use anyhow::{Result, anyhow};
+use std::fmt;
+use std::str::FromStr;
+
+/// What a channel shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelRole {
+    /// Phase contrast or brightfield transmitted light
+    Phase,
+    /// Fluorescence emission
+    Fluorescence,
+    /// Label image, e.g. a segmentation
+    Label,
+}
+
+impl ChannelRole {
+    /// Get the name used in `.meta` files
+    pub fn name(self) -> &'static str {
+        match self {
+            ChannelRole::Phase => "phase",
+            ChannelRole::Fluorescence => "fluorescence",
+            ChannelRole::Label => "label",
+        }
+    }
+}
+
+impl fmt::Display for ChannelRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Display colour of a channel, stored as `#RRGGBB`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Convert from an OME-XML colour, a signed 32-bit RGBA integer
+    pub fn from_ome(value: i32) -> Self {
+        let [r, g, b, _alpha] = value.to_be_bytes();
+        Self { r, g, b }
+    }
+
+    /// Convert to an opaque OME-XML colour
+    pub fn to_ome(self) -> i32 {
+        i32::from_be_bytes([self.r, self.g, self.b, 255])
+    }
+}
+
+impl FromStr for Color {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(anyhow!("Invalid colour '{}', expected #RRGGBB", s));
+        }
+        let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+        Ok(Self { r: byte(0)?, g: byte(2)?, b: byte(4)? })
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> Self {
+        color.to_string()
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+    }
+}
+
+/// Acquisition settings of one channel
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChannelMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub excitation_nm: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emission_nm: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exposure_ms: Option<f64>,
+    /// Camera gain (multiplier)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gain: Option<f64>,
+    /// Camera offset in counts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera_offset: Option<f64>,
+    /// Filter set or cube, e.g. `FITC`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<Color>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<ChannelRole>,
+}
+
+impl ChannelMetadata {
+    /// Check that no setting was recorded
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check that numeric settings are finite and wavelengths and exposure positive
+    pub fn validate(&self) -> Result<()> {
+        let positive = [("excitation wavelength", self.excitation_nm), ("emission wavelength", self.emission_nm), ("exposure", self.exposure_ms)];
+        for (name, value) in positive {
+            if value.is_some_and(|v| !(v.is_finite() && v > 0.0)) {
+                return Err(anyhow!("Channel {} must be positive, got {}", name, value.unwrap_or_default()));
+            }
+        }
+        for (name, value) in [("gain", self.gain), ("camera offset", self.camera_offset)] {
+            if value.is_some_and(|v| !v.is_finite()) {
+                return Err(anyhow!("Channel {} must be finite", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ChannelMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(role) = self.role {
+            parts.push(role.to_string());
+        }
+        if let Some(nm) = self.excitation_nm {
+            parts.push(format!("ex {} nm", nm));
+        }
+        if let Some(nm) = self.emission_nm {
+            parts.push(format!("em {} nm", nm));
+        }
+        if let Some(ms) = self.exposure_ms {
+            parts.push(format!("{} ms", ms));
+        }
+        if let Some(gain) = self.gain {
+            parts.push(format!("gain {}", gain));
+        }
+        if let Some(offset) = self.camera_offset {
+            parts.push(format!("offset {}", offset));
+        }
+        parts.extend(self.filter.clone());
+        parts.extend(self.color.map(|color| color.to_string()));
+        f.write_str(&parts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color() {
+        let color: Color = "#00ff7F".parse().unwrap();
+        assert_eq!(color, Color::new(0, 255, 127));
+        assert_eq!(color.to_string(), "#00FF7F");
+        assert_eq!(Color::from_ome(color.to_ome()), color);
+        assert_eq!(Color::from_ome(-16776961), Color::new(255, 0, 0));
+        assert!("#12345".parse::<Color>().is_err());
+        assert!("#GG0000".parse::<Color>().is_err());
+    }
+
+    #[test]
+    fn test_channel_metadata_serialization() {
+        let channel = ChannelMetadata {
+            excitation_nm: Some(488.0),
+            exposure_ms: Some(100.0),
+            filter: Some("FITC".to_string()),
+            color: Some(Color::new(0, 255, 0)),
+            role: Some(ChannelRole::Fluorescence),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&channel).unwrap();
+        assert_eq!(json, r##"{"excitation_nm":488.0,"exposure_ms":100.0,"filter":"FITC","color":"#00FF00","role":"fluorescence"}"##);
+        assert_eq!(serde_json::from_str::<ChannelMetadata>(&json).unwrap(), channel);
+        assert!(serde_json::from_str::<ChannelMetadata>(r#"{"color":"green"}"#).is_err());
+        assert!(ChannelMetadata::default().is_empty());
+        assert_eq!(channel.to_string(), "fluorescence, ex 488 nm, 100 ms, FITC, #00FF00");
+
+        assert!(channel.validate().is_ok());
+        assert!(ChannelMetadata { exposure_ms: Some(0.0), ..Default::default() }.validate().is_err());
+        assert!(ChannelMetadata { gain: Some(f64::NAN), ..Default::default() }.validate().is_err());
+    }
+}
@@ -0,0 +1,172 @@
+//! Compressed, chunked layout of the split format (version 2.0)
+//!
+//! Every (t, p, z, c) frame is encoded exactly like a raw split frame and
+//! then compressed on its own. The `.meta` JSON stores the byte offset of
+//! each chunk in the `.data` file, so any frame can be located in O(1) and
+//! decoded without touching its neighbours.
+
+use super::array_6d::FrameSource;
+use anyhow::{Result, anyhow};
+use memmap2::Mmap;
+use serde::{Serialize, Deserialize};
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Split format version of files with compressed chunks
+pub const CHUNKED_FORMAT_VERSION: &str = "2.0";
+
+/// zstd level used for new chunks (favours speed over ratio)
+const ZSTD_LEVEL: i32 = 3;
+
+/// Compression codec applied to each frame chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    /// Zstandard, better ratio
+    Zstd,
+    /// LZ4 block format, faster decoding
+    Lz4,
+}
+
+impl Compression {
+    /// Get the name used in `.meta` files
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::Zstd => "zstd",
+            Compression::Lz4 => "lz4",
+        }
+    }
+
+    /// Compress one encoded frame into a chunk
+    pub fn compress(self, frame: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Compression::Zstd => Ok(zstd::bulk::compress(frame, ZSTD_LEVEL)?),
+            Compression::Lz4 => Ok(lz4_flex::block::compress(frame)),
+        }
+    }
+
+    /// Decompress a chunk back into an encoded frame of `frame_size` bytes
+    pub fn decompress(self, chunk: &[u8], frame_size: usize) -> Result<Vec<u8>> {
+        let frame = match self {
+            Compression::Zstd => zstd::bulk::decompress(chunk, frame_size)?,
+            Compression::Lz4 => lz4_flex::block::decompress(chunk, frame_size)
+                .map_err(|e| anyhow!("LZ4 decompression failed: {}", e))?,
+        };
+
+        if frame.len() != frame_size {
+            return Err(anyhow!(
+                "Chunk decompressed to {} bytes, expected {}",
+                frame.len(), frame_size
+            ));
+        }
+        Ok(frame)
+    }
+}
+
+/// Location of every compressed frame chunk in a `.data` file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkIndex {
+    pub compression: Compression,
+    /// Chunk start offsets in stored frame order followed by the file size;
+    /// chunk `i` spans `offsets[i]..offsets[i + 1]`
+    pub offsets: Vec<u64>,
+}
+
+impl ChunkIndex {
+    /// Get the byte range of chunk `index` in the `.data` file
+    pub fn chunk_range(&self, index: usize) -> Range<usize> {
+        self.offsets[index] as usize..self.offsets[index + 1] as usize
+    }
+
+    /// Check that the index describes `frame_count` chunks filling a file of `data_size` bytes
+    pub fn validate(&self, frame_count: usize, data_size: u64) -> Result<()> {
+        self.check_offsets(frame_count)?;
+        let end = self.offsets[frame_count];
+        if end != data_size {
+            return Err(anyhow!(
+                "Data file size mismatch: chunk index ends at {} bytes, got {}",
+                end, data_size
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check that the index has ascending offsets for `frame_count` chunks, whatever the file size
+    pub fn check_offsets(&self, frame_count: usize) -> Result<()> {
+        if self.offsets.len() != frame_count + 1 {
+            return Err(anyhow!(
+                "Chunk index has {} offsets, expected {} for {} frames",
+                self.offsets.len(), frame_count + 1, frame_count
+            ));
+        }
+        if self.offsets[0] != 0 {
+            return Err(anyhow!("Chunk index must start at offset 0, got {}", self.offsets[0]));
+        }
+        if let Some(i) = self.offsets.windows(2).position(|w| w[1] < w[0]) {
+            return Err(anyhow!(
+                "Chunk index is not monotonic: offset {} ({}) is before offset {} ({})",
+                i + 1, self.offsets[i + 1], i, self.offsets[i]
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Memory-mapped `.data` file of compressed chunks
+#[derive(Debug)]
+pub(crate) struct ChunkedFrames {
+    mmap: Mmap,
+    index: ChunkIndex,
+    /// Size of one decompressed frame in bytes
+    frame_size: usize,
+}
+
+impl ChunkedFrames {
+    /// Wrap a mapped `.data` file after checking it against the index
+    pub fn new(mmap: Mmap, index: ChunkIndex, frame_count: usize, frame_size: usize) -> Result<Self> {
+        index.validate(frame_count, mmap.len() as u64)?;
+        Ok(Self { mmap, index, frame_size })
+    }
+}
+
+impl FrameSource for ChunkedFrames {
+    fn frame_bytes(&self, index: usize) -> Result<Cow<'_, [u8]>> {
+        let chunk = &self.mmap[self.index.chunk_range(index)];
+        Ok(Cow::Owned(self.index.compression.decompress(chunk, self.frame_size)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compression_roundtrip() {
+        let frame: Vec<u8> = (0..4096u32).flat_map(|i| ((i / 64) as u16).to_le_bytes()).collect();
+
+        for compression in [Compression::Zstd, Compression::Lz4] {
+            let chunk = compression.compress(&frame).unwrap();
+            assert!(chunk.len() < frame.len());
+            assert_eq!(compression.decompress(&chunk, frame.len()).unwrap(), frame);
+
+            // A chunk that does not expand to a full frame is rejected
+            assert!(compression.decompress(&chunk, frame.len() + 2).is_err());
+        }
+    }
+
+    #[test]
+    fn test_index_validation() {
+        let index = ChunkIndex { compression: Compression::Zstd, offsets: vec![0, 10, 25, 40] };
+        assert!(index.validate(3, 40).is_ok());
+        assert_eq!(index.chunk_range(1), 10..25);
+
+        assert!(index.validate(2, 40).is_err());
+        assert!(index.validate(3, 41).is_err());
+
+        let shifted = ChunkIndex { offsets: vec![5, 10, 25, 40], ..index.clone() };
+        assert!(shifted.validate(3, 40).is_err());
+
+        let unordered = ChunkIndex { offsets: vec![0, 25, 10, 40], ..index };
+        assert!(unordered.validate(3, 40).is_err());
+    }
+}
@@ -0,0 +1,423 @@
+//! Concatenating datasets along T, P or C
+//!
+//! Acquisitions split across restarts (more time points) or across runs
+//! (more positions or channels) are merged into one array. All other
+//! extents, the voxel size and the data type must match. Channels of
+//! datasets merged along T or P are matched by name, so runs that list
+//! the same channels in another order line up. Merged arrays read their
+//! frames from the parts on demand, so files are merged frame by frame.
+
+use super::array_6d::{Array6D, Dimensions, FrameSource, VoxelSize};
+use super::dtype::{ByteOrder, Pixel, SampleEncoding};
+use super::formats::{open_array_as, save_array};
+use super::provenance::ProvenanceEntry;
+use super::subset::Selection;
+use anyhow::{Result, anyhow};
+use ndarray::{Array6, Axis};
+use std::borrow::Cow;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Axis to concatenate datasets along
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConcatAxis {
+    Time,
+    Position,
+    Channel,
+}
+
+impl ConcatAxis {
+    pub const ALL: [ConcatAxis; 3] = [ConcatAxis::Time, ConcatAxis::Position, ConcatAxis::Channel];
+
+    /// Get the name used in provenance logs
+    pub fn name(self) -> &'static str {
+        match self {
+            ConcatAxis::Time => "time",
+            ConcatAxis::Position => "position",
+            ConcatAxis::Channel => "channel",
+        }
+    }
+
+    /// Get the index of the axis in TPZCYX order
+    fn index(self) -> usize {
+        match self {
+            ConcatAxis::Time => 0,
+            ConcatAxis::Position => 1,
+            ConcatAxis::Channel => 3,
+        }
+    }
+}
+
+impl fmt::Display for ConcatAxis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ConcatAxis {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "t" | "time" => Ok(ConcatAxis::Time),
+            "p" | "position" => Ok(ConcatAxis::Position),
+            "c" | "channel" => Ok(ConcatAxis::Channel),
+            _ => Err(anyhow!("Invalid concatenation axis '{}', expected time, position or channel", s)),
+        }
+    }
+}
+
+/// Concatenate arrays along `axis`
+///
+/// Along T, each part starts one time interval after the last time point
+/// of the previous part; use [`concatenate_time_at`] when the real start
+/// times are known. Along P and C the time axis of the first part is kept.
+pub fn concatenate<T: Pixel>(arrays: &[Array6D<T>], axis: ConcatAxis) -> Result<Array6D<T>> {
+    concatenate_parts(arrays, axis, None, concatenate_entry(axis, arrays.len()))
+}
+
+/// Concatenate arrays along T, with each part starting `start_times_s` seconds after the first
+pub fn concatenate_time_at<T: Pixel>(arrays: &[Array6D<T>], start_times_s: &[f64]) -> Result<Array6D<T>> {
+    if start_times_s.len() != arrays.len() {
+        return Err(anyhow!("Expected {} start times, one per part, got {}", arrays.len(), start_times_s.len()));
+    }
+    let entry = concatenate_entry(ConcatAxis::Time, arrays.len()).parameter("start_times_s", format!("{:?}", start_times_s));
+    concatenate_parts(arrays, ConcatAxis::Time, Some(start_times_s), entry)
+}
+
+/// Concatenate files along `axis` into a new file
+///
+/// The inputs are opened without loading them and frames are copied one
+/// at a time, so datasets larger than memory can be merged. Samples keep
+/// their data type.
+pub fn concatenate_files<P: AsRef<Path>, Q: AsRef<Path>>(inputs: &[P], output: Q, axis: ConcatAxis) -> Result<()> {
+    let arrays = inputs.iter()
+        .map(|path| open_array_as::<f64, _>(path)
+            .map_err(|e| anyhow!("Failed to open {}: {}", path.as_ref().display(), e)))
+        .collect::<Result<Vec<_>>>()?;
+    let sources: Vec<String> = inputs.iter()
+        .map(|path| path.as_ref().file_name().unwrap_or_default().to_string_lossy().into_owned())
+        .collect();
+    let entry = concatenate_entry(axis, arrays.len()).parameter("sources", sources.join(", "));
+    save_array(&concatenate_parts(&arrays, axis, None, entry)?, output)
+}
+
+fn concatenate_entry(axis: ConcatAxis, parts: usize) -> ProvenanceEntry {
+    ProvenanceEntry::new("concatenate").parameter("axis", axis).parameter("parts", parts)
+}
+
+pub(crate) fn concatenate_parts<T: Pixel>(
+    arrays: &[Array6D<T>],
+    axis: ConcatAxis,
+    start_times_s: Option<&[f64]>,
+    entry: ProvenanceEntry,
+) -> Result<Array6D<T>> {
+    let first = arrays.first().ok_or_else(|| anyhow!("Nothing to concatenate"))?;
+    check_compatible(arrays, axis)?;
+
+    // Along T and P, every part must hold the channels of the first
+    let parts: Vec<Array6D<T>> = match axis {
+        ConcatAxis::Channel => arrays.to_vec(),
+        _ => arrays.iter().map(|array| match_channels(array, first.channel_names())).collect::<Result<_>>()?,
+    };
+
+    let mut shape = first.dimensions().shape();
+    shape[axis.index()] = parts.iter().map(|part| part.dimensions().shape()[axis.index()]).sum();
+    let dims = Dimensions::new(shape[0], shape[1], shape[2], shape[3], shape[4], shape[5]);
+
+    let channel_names = match axis {
+        ConcatAxis::Channel => unique_names(parts.iter().flat_map(|part| part.channel_names().iter().cloned())),
+        _ => first.channel_names().to_vec(),
+    };
+    let data_type = first.data_type().to_string();
+    let array = if parts.iter().all(|part| part.data().is_some()) {
+        let views: Vec<_> = parts.iter().filter_map(|part| part.data()).map(|data| data.view()).collect();
+        let data: Array6<T> = ndarray::concatenate(Axis(axis.index()), &views)?;
+        Array6D::new(data, dims, first.pixel_size_um(), first.time_interval_s(), channel_names, data_type)?
+    } else {
+        let encoding = SampleEncoding::new(T::DATA_TYPE, ByteOrder::Little);
+        let source = ConcatenatedFrames { parts: parts.clone(), axis, dimensions: dims, encoding };
+        Array6D::from_source(Arc::new(source), encoding, dims, first.pixel_size_um(), first.time_interval_s(), channel_names, data_type)?
+    };
+
+    let channels = match axis {
+        ConcatAxis::Channel => merge_metadata(&parts, |part| part.channels(), |part| part.dimensions().channel),
+        _ => first_known(&parts, |part| part.channels()),
+    };
+    let positions = match axis {
+        ConcatAxis::Position => merge_metadata(&parts, |part| part.positions(), |part| part.dimensions().position),
+        _ => first_known(&parts, |part| part.positions()),
+    };
+    let mut array = array
+        .with_voxel_size(first.voxel_size())?
+        .with_channels(channels)?
+        .with_positions(positions)?
+        .with_provenance(first.provenance().to_vec());
+    if let Some(timestamps) = merged_timestamps(&parts, axis, start_times_s)? {
+        array = array.with_timestamps(timestamps)?;
+    }
+    array.record(entry);
+    Ok(array)
+}
+
+/// Check that the parts differ only in their extent along `axis`
+fn check_compatible<T: Pixel>(arrays: &[Array6D<T>], axis: ConcatAxis) -> Result<()> {
+    let first = PartLayout::of(&arrays[0]);
+    arrays.iter().enumerate().skip(1).try_for_each(|(i, array)| PartLayout::of(array).check(i, &first, axis))
+}
+
+/// What a part must share with the first part, outside the concatenated axis
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PartLayout<'a> {
+    pub dimensions: Dimensions,
+    pub data_type: &'a str,
+    pub voxel_size: VoxelSize,
+}
+
+impl<'a> PartLayout<'a> {
+    fn of<T: Pixel>(array: &'a Array6D<T>) -> Self {
+        Self { dimensions: *array.dimensions(), data_type: array.data_type(), voxel_size: array.voxel_size() }
+    }
+
+    /// Check that part `index` (0-based) can be joined to `first` along `axis`
+    pub fn check(&self, index: usize, first: &PartLayout, axis: ConcatAxis) -> Result<()> {
+        let (mut shape, mut expected) = (self.dimensions.shape(), first.dimensions.shape());
+        shape[axis.index()] = 0;
+        expected[axis.index()] = 0;
+        if shape != expected {
+            return Err(anyhow!(
+                "Part {} has dimensions {:?}, which do not match {:?} outside the {} axis",
+                index + 1, self.dimensions.shape(), first.dimensions.shape(), axis
+            ));
+        }
+        if self.data_type != first.data_type {
+            return Err(anyhow!("Part {} has data type {}, expected {}", index + 1, self.data_type, first.data_type));
+        }
+        let (a, b) = (self.voxel_size, first.voxel_size);
+        let close = |x: f64, y: f64| (x - y).abs() <= 1e-6 * x.abs().max(y.abs());
+        if !(close(a.x_um, b.x_um) && close(a.y_um, b.y_um) && close(a.z_um, b.z_um)) {
+            return Err(anyhow!("Part {} has voxel size {}, expected {}", index + 1, a, b));
+        }
+        Ok(())
+    }
+}
+
+/// Get the index in `part_names` of every channel of `names`, matching names case-insensitively
+pub(crate) fn channel_order(part_names: &[String], names: &[String]) -> Result<Vec<usize>> {
+    let key = |name: &String| name.trim().to_lowercase();
+    names.iter()
+        .map(|name| part_names.iter().position(|other| key(other) == key(name)))
+        .collect::<Option<Vec<usize>>>()
+        .filter(|order| order.len() == part_names.len() && (0..order.len()).all(|c| order.contains(&c)))
+        .ok_or_else(|| anyhow!("Channels {:?} do not match {:?}", part_names, names))
+}
+
+/// Reorder the channels of `array` to `names`, matching names case-insensitively
+fn match_channels<T: Pixel>(array: &Array6D<T>, names: &[String]) -> Result<Array6D<T>> {
+    if array.channel_names() == names {
+        return Ok(array.clone());
+    }
+    let order = channel_order(array.channel_names(), names)?;
+    array.select(&Selection::all().channels(order))
+}
+
+/// Make repeated channel names unique by numbering later occurrences
+fn unique_names(names: impl Iterator<Item = String>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::new();
+    for name in names {
+        let mut candidate = name.clone();
+        let mut n = 2;
+        while unique.contains(&candidate) {
+            candidate = format!("{} ({})", name, n);
+            n += 1;
+        }
+        unique.push(candidate);
+    }
+    unique
+}
+
+/// Join per-index metadata of all parts; parts without any get empty records
+fn merge_metadata<T: Pixel, V: Clone + Default>(
+    parts: &[Array6D<T>],
+    values: impl Fn(&Array6D<T>) -> &[V],
+    len: impl Fn(&Array6D<T>) -> usize,
+) -> Vec<V> {
+    if parts.iter().all(|part| values(part).is_empty()) {
+        return Vec::new();
+    }
+    parts.iter()
+        .flat_map(|part| match values(part) {
+            [] => vec![V::default(); len(part)],
+            known => known.to_vec(),
+        })
+        .collect()
+}
+
+/// Get the metadata of the first part that has any
+fn first_known<T: Pixel, V: Clone>(parts: &[Array6D<T>], values: impl Fn(&Array6D<T>) -> &[V]) -> Vec<V> {
+    parts.iter().map(values).find(|known| !known.is_empty()).unwrap_or_default().to_vec()
+}
+
+/// Get the timestamps of the merged array, or `None` if its time points stay regular
+fn merged_timestamps<T: Pixel>(parts: &[Array6D<T>], axis: ConcatAxis, start_times_s: Option<&[f64]>) -> Result<Option<Vec<f64>>> {
+    let first = &parts[0];
+    if axis != ConcatAxis::Time {
+        return Ok(first.timestamps_s().map(<[f64]>::to_vec));
+    }
+    let regular = parts.iter().all(|part| part.timestamps_s().is_none() && part.time_interval_s() == first.time_interval_s());
+    if regular && start_times_s.is_none() {
+        return Ok(None);
+    }
+
+    let mut timestamps: Vec<f64> = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        let start = match (start_times_s, timestamps.last()) {
+            (Some(starts), last) => {
+                if last.is_some_and(|&last| starts[i] <= last) {
+                    return Err(anyhow!("Part {} starts at {} s, before the previous part ends", i + 1, starts[i]));
+                }
+                starts[i]
+            }
+            (None, Some(&last)) => last + parts[i - 1].time_interval_s(),
+            (None, None) => 0.0,
+        };
+        timestamps.extend(part.time_axis_s().iter().map(|t| start + t));
+    }
+    Ok(Some(timestamps))
+}
+
+/// Frames of the parts of a concatenation, read from the parts on demand
+#[derive(Debug)]
+struct ConcatenatedFrames<T: Pixel> {
+    parts: Vec<Array6D<T>>,
+    axis: ConcatAxis,
+    dimensions: Dimensions,
+    /// Encoding of the pixel type, so frames pass through unchanged
+    encoding: SampleEncoding,
+}
+
+impl<T: Pixel> FrameSource for ConcatenatedFrames<T> {
+    fn frame_bytes(&self, index: usize) -> Result<Cow<'_, [u8]>> {
+        let (t, p, z, c) = self.dimensions.frame_coords(index);
+        let mut coords = [t, p, z, c];
+        let mut part_index = 0;
+        for part in &self.parts {
+            let extent = part.dimensions().shape()[self.axis.index()];
+            if coords[self.axis.index()] < extent {
+                break;
+            }
+            coords[self.axis.index()] -= extent;
+            part_index += 1;
+        }
+
+        let [t, p, z, c] = coords;
+        let frame = self.parts[part_index].get_frame(t, p, z, c)?;
+        let mut bytes = Vec::with_capacity(self.dimensions.frame_elements() * self.encoding.size());
+        self.encoding.encode(frame.iter(), &mut bytes);
+        Ok(Cow::Owned(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::array_6d::VoxelSize;
+    use crate::io::channels::ChannelMetadata;
+    use crate::io::formats::{load_array, open_array};
+    use crate::io::generators::ArrayGenerator;
+    use crate::io::positions::PositionMetadata;
+
+    #[test]
+    fn test_concatenate_time() {
+        let a = ArrayGenerator::generate_test_array(3, 2, 1, 2, 6, 4).unwrap();
+        let b = ArrayGenerator::generate_test_array(2, 2, 1, 2, 6, 4).unwrap();
+        let merged = concatenate(&[a.clone(), b.clone()], ConcatAxis::Time).unwrap();
+        assert_eq!(*merged.dimensions(), Dimensions::new(5, 2, 1, 2, 6, 4));
+        assert_eq!(merged.get_frame(3, 1, 0, 1).unwrap(), b.get_frame(0, 1, 0, 1).unwrap());
+        assert_eq!(merged.timestamps_s(), None);
+        assert_eq!(merged.provenance().last().unwrap().parameters["axis"], "time");
+
+        // Known start times become timestamps
+        let dt = a.time_interval_s();
+        let merged = concatenate_time_at(&[a.clone(), b.clone()], &[0.0, 10.0 * dt]).unwrap();
+        let expected: Vec<f64> = [0.0, 1.0, 2.0, 10.0, 11.0].iter().map(|t| t * dt).collect();
+        assert_eq!(merged.timestamps_s(), Some(&expected[..]));
+        assert!(concatenate_time_at(&[a.clone(), b.clone()], &[0.0, dt]).is_err());
+
+        // Irregular parts continue one interval after the previous part
+        let c = b.clone().with_timestamps(vec![0.0, 0.5 * dt]).unwrap();
+        let merged = concatenate(&[a, c], ConcatAxis::Time).unwrap();
+        assert_eq!(merged.timestamps_s().unwrap()[3..], [3.0 * dt, 3.5 * dt]);
+    }
+
+    #[test]
+    fn test_concatenate_positions_and_channels() {
+        let array = ArrayGenerator::generate_test_array(2, 2, 1, 2, 6, 4).unwrap();
+        let names = array.channel_names().to_vec();
+
+        // Channels listed in another order are matched by name
+        let swapped = array.select(&Selection::all().channels([1, 0])).unwrap();
+        let merged = concatenate(&[array.clone(), swapped], ConcatAxis::Position).unwrap();
+        assert_eq!(*merged.dimensions(), Dimensions::new(2, 4, 1, 2, 6, 4));
+        assert_eq!(merged.get_frame(1, 3, 0, 0).unwrap(), array.get_frame(1, 1, 0, 0).unwrap());
+
+        let renamed = Array6D::new(
+            array.data().unwrap().clone(), *array.dimensions(), array.pixel_size_um(), array.time_interval_s(),
+            vec!["Phase".to_string(), "GFP".to_string()], array.data_type().to_string(),
+        ).unwrap();
+        assert!(concatenate(&[array.clone(), renamed.clone()], ConcatAxis::Position).is_err());
+
+        let merged = concatenate(&[array.clone(), array.clone()], ConcatAxis::Channel).unwrap();
+        assert_eq!(merged.channel_names(), [names[0].clone(), names[1].clone(), format!("{} (2)", names[0]), format!("{} (2)", names[1])]);
+        let settings = ChannelMetadata { exposure_ms: Some(50.0), ..Default::default() };
+        let with_settings = renamed.with_channels(vec![settings.clone(), settings.clone()]).unwrap();
+        let merged = concatenate(&[array.clone(), with_settings], ConcatAxis::Channel).unwrap();
+        assert_eq!(merged.channels().len(), 4);
+        assert!(merged.channels()[0].is_empty());
+        assert_eq!(merged.channels()[3], settings);
+
+        let positions = vec![PositionMetadata::in_well("B02", 1), PositionMetadata::in_well("B02", 2)];
+        let with_wells = array.clone().with_positions(positions).unwrap();
+        let merged = concatenate(&[array.clone(), with_wells], ConcatAxis::Position).unwrap();
+        assert_eq!(merged.wells()["B02"], [2, 3]);
+
+        // Extents, pixel sizes and data types must match
+        let smaller = array.select(&Selection::all().time(0)).unwrap();
+        assert!(concatenate(&[array.clone(), smaller.clone()], ConcatAxis::Position).is_err());
+        assert!(concatenate(&[array.clone(), smaller], ConcatAxis::Time).is_ok());
+        let finer = array.clone().with_voxel_size(VoxelSize::isotropic(0.1)).unwrap();
+        assert!(concatenate(&[array, finer], ConcatAxis::Time).is_err());
+        assert!(concatenate::<f32>(&[], ConcatAxis::Time).is_err());
+    }
+
+    #[test]
+    fn test_concatenate_files() {
+        let a = ArrayGenerator::generate_test_array(2, 1, 2, 2, 8, 6).unwrap();
+        let b = ArrayGenerator::generate_test_array(2, 2, 2, 2, 8, 6).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let paths = [temp_dir.path().join("run1.meta"), temp_dir.path().join("run2.meta")];
+        save_array(&a, &paths[0]).unwrap();
+        save_array(&b, &paths[1]).unwrap();
+
+        let opened = [open_array(&paths[0]).unwrap(), open_array(&paths[1]).unwrap()];
+        let lazy = concatenate(&opened, ConcatAxis::Position).unwrap();
+        assert!(lazy.is_lazy());
+        let expected = concatenate(&[a, b], ConcatAxis::Position).unwrap();
+        assert_eq!(lazy.materialize().unwrap().data(), expected.data());
+
+        let output = temp_dir.path().join("merged.meta");
+        concatenate_files(&paths, &output, ConcatAxis::Position).unwrap();
+        let merged = load_array(&output).unwrap();
+        assert_eq!(merged.data(), expected.data());
+        assert_eq!(merged.data_type(), expected.data_type());
+        let entry = &merged.provenance()[merged.provenance().len() - 2];
+        assert_eq!(entry.operation, "concatenate");
+        assert_eq!(entry.parameters["sources"], "run1.meta, run2.meta");
+
+        assert!(concatenate_files(&paths, &output, ConcatAxis::Time).is_err());
+        assert_eq!("P".parse::<ConcatAxis>().unwrap(), ConcatAxis::Position);
+        assert!("z".parse::<ConcatAxis>().is_err());
+    }
+}
@@ -0,0 +1,397 @@
+//! Sample data types for on-disk storage and in-memory pixels
+//!
+//! The split format stores samples at their native width. Values are
+//! converted to the array's pixel type when loaded and quantized back to
+//! the storage type when saved.
+
+use anyhow::{Result, anyhow};
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Byte order of multi-byte samples in a `.data` file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ByteOrder {
+    #[default]
+    Little,
+    Big,
+}
+
+/// Encoding of the samples in a `.data` file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SampleEncoding {
+    pub data_type: DataType,
+    pub byte_order: ByteOrder,
+}
+
+impl SampleEncoding {
+    /// Create encoding for the given type and byte order
+    pub fn new(data_type: DataType, byte_order: ByteOrder) -> Self {
+        Self { data_type, byte_order }
+    }
+
+    /// Get size of one element in bytes
+    pub fn size(self) -> usize {
+        self.data_type.size()
+    }
+
+    /// Append the encoding of `values` to `out`
+    pub fn encode<'a, T: Pixel>(self, values: impl IntoIterator<Item = &'a T>, out: &mut Vec<u8>) {
+        self.data_type.encode(values, self.byte_order, out)
+    }
+
+    /// Decode samples into pixels of type `T`
+    pub fn decode<T: Pixel>(self, bytes: &[u8]) -> Vec<T> {
+        self.data_type.decode(bytes, self.byte_order)
+    }
+
+    /// Parse a NumPy dtype string such as `<u2` or `>f4`
+    ///
+    /// `=` is the byte order of this machine; `|` (not applicable) is only
+    /// valid for single-byte types.
+    pub fn from_numpy(descr: &str) -> Result<Self> {
+        let native = if cfg!(target_endian = "big") { ByteOrder::Big } else { ByteOrder::Little };
+        let (byte_order, kind) = match descr.split_at_checked(1) {
+            Some(("<", kind)) => (ByteOrder::Little, kind),
+            Some((">", kind)) => (ByteOrder::Big, kind),
+            Some(("=", kind)) => (native, kind),
+            Some(("|", kind @ "u1")) => (ByteOrder::Little, kind),
+            Some(("|", _)) => return Err(anyhow!("NumPy dtype '{}' needs a byte order, not '|'", descr)),
+            _ => return Err(anyhow!("Invalid NumPy dtype '{}'", descr)),
+        };
+        let data_type = match kind {
+            "u1" => DataType::UInt8,
+            "u2" => DataType::UInt16,
+            "u4" => DataType::UInt32,
+            "f4" => DataType::Float32,
+            "f8" => DataType::Float64,
+            _ => return Err(anyhow!(
+                "Unsupported NumPy dtype '{}' (expected u1, u2, u4, f4 or f8)",
+                descr
+            )),
+        };
+        Ok(Self::new(data_type, byte_order))
+    }
+
+    /// Get the NumPy dtype string, e.g. `|u1`, `<u2` or `>f8`
+    pub fn numpy_descr(self) -> String {
+        let order = match (self.data_type, self.byte_order) {
+            (DataType::UInt8, _) => '|',
+            (_, ByteOrder::Little) => '<',
+            (_, ByteOrder::Big) => '>',
+        };
+        let kind = match self.data_type {
+            DataType::UInt8 => "u1",
+            DataType::UInt16 => "u2",
+            DataType::UInt32 => "u4",
+            DataType::Float32 => "f4",
+            DataType::Float64 => "f8",
+        };
+        format!("{}{}", order, kind)
+    }
+}
+
+/// Element type of the samples in a `.data` file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataType {
+    UInt8,
+    UInt16,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+/// Pixel element type that an `Array6D` can hold
+pub trait Pixel: Copy + Default + PartialOrd + fmt::Debug + Send + Sync + 'static {
+    /// Data type matching this pixel type
+    const DATA_TYPE: DataType;
+
+    /// Convert to f64 (exact for every supported type)
+    fn to_f64(self) -> f64;
+
+    /// Convert from f64, rounding and saturating for integer types
+    fn from_f64(value: f64) -> Self;
+}
+
+macro_rules! impl_integer_pixel {
+    ($($ty:ty => $dtype:ident),*) => {$(
+        impl Pixel for $ty {
+            const DATA_TYPE: DataType = DataType::$dtype;
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn from_f64(value: f64) -> Self {
+                value.round() as $ty
+            }
+        }
+    )*};
+}
+
+impl_integer_pixel!(u8 => UInt8, u16 => UInt16, u32 => UInt32);
+
+/// Append one value converted to `$ty` in the given byte order
+macro_rules! encode_as {
+    ($ty:ty, $value:expr, $order:expr, $out:expr) => {{
+        let value = <$ty>::from_f64($value);
+        match $order {
+            ByteOrder::Little => $out.extend_from_slice(&value.to_le_bytes()),
+            ByteOrder::Big => $out.extend_from_slice(&value.to_be_bytes()),
+        }
+    }};
+}
+
+/// Decode `$ty` samples in the given byte order into pixels of type `$pixel`
+///
+/// Each element is assembled from its bytes, so the input buffer needs no
+/// particular alignment.
+macro_rules! decode_as {
+    ($ty:ty, $pixel:ty, $bytes:expr, $order:expr) => {
+        $bytes
+            .chunks_exact(std::mem::size_of::<$ty>())
+            .map(|chunk| {
+                let chunk = chunk.try_into().expect("chunk length equals element size");
+                let value = match $order {
+                    ByteOrder::Little => <$ty>::from_le_bytes(chunk),
+                    ByteOrder::Big => <$ty>::from_be_bytes(chunk),
+                };
+                <$pixel>::from_f64(value as f64)
+            })
+            .collect()
+    };
+}
+
+impl Pixel for f32 {
+    const DATA_TYPE: DataType = DataType::Float32;
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+}
+
+impl Pixel for f64 {
+    const DATA_TYPE: DataType = DataType::Float64;
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+}
+
+impl DataType {
+    /// Every supported data type, narrowest first
+    pub const ALL: [DataType; 5] = [DataType::UInt8, DataType::UInt16, DataType::UInt32, DataType::Float32, DataType::Float64];
+
+    /// Get the name used in metadata files
+    pub fn name(self) -> &'static str {
+        match self {
+            DataType::UInt8 => "uint8",
+            DataType::UInt16 => "uint16",
+            DataType::UInt32 => "uint32",
+            DataType::Float32 => "float32",
+            DataType::Float64 => "float64",
+        }
+    }
+
+    /// Get size of one element in bytes
+    pub fn size(self) -> usize {
+        match self {
+            DataType::UInt8 => 1,
+            DataType::UInt16 => 2,
+            DataType::UInt32 => 4,
+            DataType::Float32 => 4,
+            DataType::Float64 => 8,
+        }
+    }
+
+    /// Get the largest representable value (used as saturation level)
+    pub fn max_value(self) -> f64 {
+        match self {
+            DataType::UInt8 => u8::MAX as f64,
+            DataType::UInt16 => u16::MAX as f64,
+            DataType::UInt32 => u32::MAX as f64,
+            DataType::Float32 => f32::MAX as f64,
+            DataType::Float64 => f64::MAX,
+        }
+    }
+
+    /// Round and clamp a value to what this type can store
+    pub fn quantize(self, value: f32) -> f32 {
+        match self {
+            DataType::UInt8 => (value.round() as u8) as f32,
+            DataType::UInt16 => (value.round() as u16) as f32,
+            DataType::UInt32 => (value.round() as u32) as f32,
+            DataType::Float32 | DataType::Float64 => value,
+        }
+    }
+
+    /// Append the encoding of `values` in `byte_order` to `out`
+    pub fn encode<'a, T: Pixel>(
+        self,
+        values: impl IntoIterator<Item = &'a T>,
+        byte_order: ByteOrder,
+        out: &mut Vec<u8>,
+    ) {
+        for value in values {
+            let value = value.to_f64();
+            match self {
+                DataType::UInt8 => encode_as!(u8, value, byte_order, out),
+                DataType::UInt16 => encode_as!(u16, value, byte_order, out),
+                DataType::UInt32 => encode_as!(u32, value, byte_order, out),
+                DataType::Float32 => encode_as!(f32, value, byte_order, out),
+                DataType::Float64 => encode_as!(f64, value, byte_order, out),
+            }
+        }
+    }
+
+    /// Decode samples in `byte_order` into pixels of type `T`
+    pub fn decode<T: Pixel>(self, bytes: &[u8], byte_order: ByteOrder) -> Vec<T> {
+        match self {
+            DataType::UInt8 => decode_as!(u8, T, bytes, byte_order),
+            DataType::UInt16 => decode_as!(u16, T, bytes, byte_order),
+            DataType::UInt32 => decode_as!(u32, T, bytes, byte_order),
+            DataType::Float32 => decode_as!(f32, T, bytes, byte_order),
+            DataType::Float64 => decode_as!(f64, T, bytes, byte_order),
+        }
+    }
+}
+
+impl FromStr for DataType {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "uint8" => Ok(DataType::UInt8),
+            "uint16" => Ok(DataType::UInt16),
+            "uint32" => Ok(DataType::UInt32),
+            "float32" => Ok(DataType::Float32),
+            "float64" => Ok(DataType::Float64),
+            _ => Err(anyhow!(
+                "Unsupported data type '{}' (expected uint8, uint16, uint32, float32 or float64)",
+                name
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_names() {
+        for dtype in [DataType::UInt8, DataType::UInt16, DataType::UInt32, DataType::Float32, DataType::Float64] {
+            assert_eq!(dtype.name().parse::<DataType>().unwrap(), dtype);
+        }
+        assert!("int7".parse::<DataType>().is_err());
+    }
+
+    #[test]
+    fn test_encode_decode_roundtrip() {
+        let values = [0.0, 1.4, 254.6, 300.0, -5.0];
+
+        let mut bytes = Vec::new();
+        DataType::UInt8.encode(&values, ByteOrder::Little, &mut bytes);
+        assert_eq!(bytes.len(), 5);
+        assert_eq!(DataType::UInt8.decode::<f32>(&bytes, ByteOrder::Little), vec![0.0, 1.0, 255.0, 255.0, 0.0]);
+
+        let mut bytes = Vec::new();
+        DataType::UInt16.encode(&values, ByteOrder::Little, &mut bytes);
+        assert_eq!(bytes.len(), 10);
+        assert_eq!(DataType::UInt16.decode::<f32>(&bytes, ByteOrder::Little), vec![0.0, 1.0, 255.0, 300.0, 0.0]);
+
+        for dtype in [DataType::Float32, DataType::Float64] {
+            let mut bytes = Vec::new();
+            dtype.encode(&values, ByteOrder::Little, &mut bytes);
+            assert_eq!(bytes.len(), values.len() * dtype.size());
+            assert_eq!(dtype.decode::<f32>(&bytes, ByteOrder::Little), values.to_vec());
+        }
+    }
+
+    #[test]
+    fn test_integer_pixels() {
+        let labels: Vec<u32> = vec![0, 1, 70_000, u32::MAX];
+
+        let mut bytes = Vec::new();
+        DataType::UInt32.encode(&labels, ByteOrder::Little, &mut bytes);
+        assert_eq!(DataType::UInt32.decode::<u32>(&bytes, ByteOrder::Little), labels);
+
+        // Narrower storage saturates
+        let mut bytes = Vec::new();
+        DataType::UInt16.encode(&labels, ByteOrder::Little, &mut bytes);
+        assert_eq!(DataType::UInt16.decode::<u32>(&bytes, ByteOrder::Little), vec![0, 1, 65535, 65535]);
+
+        assert_eq!(u8::from_f64(-1.0), 0);
+        assert_eq!(u8::from_f64(1.6), 2);
+        assert_eq!(<u16 as Pixel>::DATA_TYPE, DataType::UInt16);
+    }
+
+    #[test]
+    fn test_byte_order() {
+        let values: Vec<u16> = vec![0x0102, 0xA0B0];
+
+        let mut little = Vec::new();
+        DataType::UInt16.encode(&values, ByteOrder::Little, &mut little);
+        assert_eq!(little, vec![0x02, 0x01, 0xB0, 0xA0]);
+
+        let mut big = Vec::new();
+        DataType::UInt16.encode(&values, ByteOrder::Big, &mut big);
+        assert_eq!(big, vec![0x01, 0x02, 0xA0, 0xB0]);
+        assert_eq!(DataType::UInt16.decode::<u16>(&big, ByteOrder::Big), values);
+
+        let mut big = Vec::new();
+        DataType::Float64.encode(&[1.5f32, -2.25], ByteOrder::Big, &mut big);
+        assert_eq!(DataType::Float64.decode::<f32>(&big, ByteOrder::Big), vec![1.5, -2.25]);
+    }
+
+    #[test]
+    fn test_decode_unaligned() {
+        let mut bytes = vec![0xFF];
+        DataType::Float32.encode(&[3.5f32, 7.0], ByteOrder::Little, &mut bytes);
+
+        // Start one byte into the buffer so the f32 samples are misaligned
+        assert_eq!(DataType::Float32.decode::<f32>(&bytes[1..], ByteOrder::Little), vec![3.5, 7.0]);
+    }
+
+    #[test]
+    fn test_numpy_descr() {
+        for dtype in [DataType::UInt8, DataType::UInt16, DataType::UInt32, DataType::Float32, DataType::Float64] {
+            for order in [ByteOrder::Little, ByteOrder::Big] {
+                let encoding = SampleEncoding::new(dtype, order);
+                let parsed = SampleEncoding::from_numpy(&encoding.numpy_descr()).unwrap();
+                assert_eq!(parsed.data_type, dtype);
+            }
+        }
+        assert_eq!(SampleEncoding::new(DataType::UInt16, ByteOrder::Big).numpy_descr(), ">u2");
+        assert_eq!(SampleEncoding::from_numpy(">f8").unwrap(), SampleEncoding::new(DataType::Float64, ByteOrder::Big));
+        assert!(SampleEncoding::from_numpy("<i2").is_err());
+        assert!(SampleEncoding::from_numpy("u2").is_err());
+        assert!(SampleEncoding::from_numpy("|u2").is_err());
+        assert_eq!(SampleEncoding::from_numpy("|u1").unwrap().data_type, DataType::UInt8);
+        let native = if cfg!(target_endian = "big") { ByteOrder::Big } else { ByteOrder::Little };
+        assert_eq!(SampleEncoding::from_numpy("=u4").unwrap(), SampleEncoding::new(DataType::UInt32, native));
+    }
+
+    #[test]
+    fn test_quantize() {
+        assert_eq!(DataType::UInt16.quantize(103.7), 104.0);
+        assert_eq!(DataType::UInt16.quantize(-3.0), 0.0);
+        assert_eq!(DataType::UInt8.quantize(1000.0), 255.0);
+        assert_eq!(DataType::Float32.quantize(103.7), 103.7);
+    }
+}
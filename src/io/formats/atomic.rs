@@ -0,0 +1,285 @@
+//! Crash-safe writes of the `.meta`/`.data` pair
+//!
+//! Saves write both files under a `.tmp` suffix, sync them and then rename
+//! them into place, `.data` first. The `.meta` carries a [`Commit`] with
+//! the size and CRC32 of the data it was saved with, so a `.meta` paired
+//! with the wrong or a truncated `.data` is detected, and the temporary
+//! files of an interrupted save can be completed or discarded by
+//! [`recover_file`].
+
+use super::{read_metadata, write_metadata, ArrayMetadata};
+use anyhow::{Result, anyhow};
+use serde::{Serialize, Deserialize};
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Size and checksum of the data a `.meta` was committed with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Commit {
+    /// Bytes of sample data after `data_offset`
+    pub data_size: u64,
+    /// CRC32 of those bytes
+    pub crc32: u32,
+}
+
+/// Outcome of [`recover_file`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// No interrupted save was found
+    Clean,
+    /// An interrupted save had written everything and was moved into place
+    Completed,
+    /// The temporary files of an incomplete save were removed
+    Discarded,
+}
+
+/// Get the temporary path a file is written to before being renamed into place
+pub fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Data file writer that tracks the commit of everything written so far
+#[derive(Debug)]
+pub(crate) struct CommitWriter {
+    file: BufWriter<File>,
+    hasher: crc32fast::Hasher,
+    size: u64,
+}
+
+impl CommitWriter {
+    /// Create (or truncate) the file at `path`
+    pub fn create(path: &Path) -> Result<Self> {
+        Ok(Self {
+            file: BufWriter::new(File::create(path)?),
+            hasher: crc32fast::Hasher::new(),
+            size: 0,
+        })
+    }
+
+    /// Sync everything written to disk and get its commit
+    pub fn sync(&mut self) -> Result<Commit> {
+        self.file.flush()?;
+        self.file.get_ref().sync_data()?;
+        Ok(Commit { data_size: self.size, crc32: self.hasher.clone().finalize() })
+    }
+}
+
+impl Write for CommitWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.file.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Replace the `.meta` at `path` so readers see either the old or the new one
+pub(crate) fn replace_metadata(path: &Path, metadata: &ArrayMetadata) -> Result<()> {
+    let temp = temp_path(path);
+    write_metadata(&temp, metadata)?;
+    std::fs::rename(&temp, path)?;
+    Ok(())
+}
+
+/// Remove a file if it exists
+pub(crate) fn remove_if_exists(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+impl ArrayMetadata {
+    /// Check that the file at `data_path` holds the data this `.meta` was committed with
+    ///
+    /// Reads the whole data file. Metadata without a commit (older files and
+    /// in-place raw imports) only gets the size check.
+    pub fn check_commit(&self, data_path: &Path) -> Result<()> {
+        let file_size = std::fs::metadata(data_path)?.len();
+        self.check_commit_size(data_path, file_size)?;
+        let Some(commit) = self.commit else {
+            return Ok(());
+        };
+
+        let mut file = BufReader::new(File::open(data_path)?);
+        file.seek(SeekFrom::Start(self.data_offset))?;
+        let mut reader = file.take(commit.data_size);
+        let mut hasher = crc32fast::Hasher::new();
+        let mut buffer = vec![0u8; 1 << 20];
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+
+        let crc32 = hasher.finalize();
+        if crc32 != commit.crc32 {
+            return Err(anyhow!(
+                "{} does not belong to this .meta: checksum {:08x} differs from the committed {:08x} (stale or corrupted data)",
+                data_path.display(), crc32, commit.crc32
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check the size of the data file against the commit and the dimensions
+    pub(crate) fn check_commit_size(&self, data_path: &Path, file_size: u64) -> Result<()> {
+        if let Some(commit) = self.commit {
+            let expected_size = self.data_offset + commit.data_size;
+            if file_size < expected_size || (file_size > expected_size && !self.appending) {
+                return Err(anyhow!(
+                    "{} does not belong to this .meta: the last save committed {} bytes but the file has {} (stale or partially written data)",
+                    data_path.display(), expected_size, file_size
+                ));
+            }
+        }
+        self.check_data_size(file_size)
+    }
+}
+
+/// Check that a split file's `.data` is the one its `.meta` was saved with
+///
+/// Unlike `validate_file`, this reads the whole data file to compare its
+/// checksum with the commit in the `.meta`.
+pub fn verify_commit<P: AsRef<Path>>(path: P) -> Result<ArrayMetadata> {
+    let path = path.as_ref();
+    let metadata = read_metadata(path)?;
+    metadata.check_commit(&metadata.data_path(path))?;
+    Ok(metadata)
+}
+
+/// Complete or discard a save of the split file at `path` that was interrupted
+///
+/// A save whose temporary `.meta` was fully written and matches its data is
+/// moved into place; anything less is removed, leaving the previous files.
+pub fn recover_file<P: AsRef<Path>>(path: P) -> Result<Recovery> {
+    let path = path.as_ref();
+    let meta_temp = temp_path(path);
+    let pending = read_metadata(&meta_temp).ok();
+
+    let data_path = match &pending {
+        Some(metadata) => metadata.data_path(path),
+        None => read_metadata(path)
+            .map_or_else(|_| path.with_extension("data"), |metadata| metadata.data_path(path)),
+    };
+    let data_temp = temp_path(&data_path);
+    if !meta_temp.exists() && !data_temp.exists() {
+        return Ok(Recovery::Clean);
+    }
+
+    if let Some(metadata) = pending.filter(|m| m.commit.is_some()) {
+        // The data is renamed before the .meta, so it may already be in place
+        let data_source = if data_temp.exists() { &data_temp } else { &data_path };
+        if metadata.check_commit(data_source).is_ok() {
+            if data_temp.exists() {
+                std::fs::rename(&data_temp, &data_path)?;
+            }
+            std::fs::rename(&meta_temp, path)?;
+            return Ok(Recovery::Completed);
+        }
+    }
+
+    remove_if_exists(&meta_temp)?;
+    remove_if_exists(&data_temp)?;
+    Ok(Recovery::Discarded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::formats::{load_array, save_array, validate_file};
+    use crate::io::generators::ArrayGenerator;
+
+    #[test]
+    fn test_temp_path() {
+        assert_eq!(temp_path(Path::new("/data/run.meta")), Path::new("/data/run.meta.tmp"));
+        assert_eq!(temp_path(Path::new("run")), Path::new("run.tmp"));
+    }
+
+    #[test]
+    fn test_stale_data_detected() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("a.meta");
+        let other = temp_dir.path().join("b.meta");
+        let array = ArrayGenerator::generate_test_array(2, 1, 1, 2, 4, 4).unwrap();
+        let shifted = crate::io::Array6D::new(
+            array.data().unwrap().mapv(|v| v + 1.0),
+            *array.dimensions(),
+            1.0,
+            1.0,
+            array.channel_names().to_vec(),
+            array.data_type().to_string(),
+        ).unwrap();
+        save_array(&array, &path).unwrap();
+        save_array(&shifted, &other).unwrap();
+        assert!(!temp_path(&path).exists());
+        assert!(verify_commit(&path).is_ok());
+
+        // Same size, different save: only the checksum tells them apart
+        std::fs::copy(other.with_extension("data"), path.with_extension("data")).unwrap();
+        assert!(validate_file(&path).is_ok());
+        let error = verify_commit(&path).unwrap_err().to_string();
+        assert!(error.contains("checksum"), "{}", error);
+
+        // A truncated file names the committed size
+        let data = std::fs::read(other.with_extension("data")).unwrap();
+        std::fs::write(path.with_extension("data"), &data[..10]).unwrap();
+        let error = validate_file(&path).unwrap_err().to_string();
+        assert!(error.contains("committed"), "{}", error);
+    }
+
+    #[test]
+    fn test_recover_interrupted_save() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("run.meta");
+        let old = ArrayGenerator::generate_test_array(1, 1, 1, 1, 4, 4).unwrap();
+        let new = ArrayGenerator::generate_test_array(2, 1, 1, 2, 4, 4).unwrap();
+        save_array(&old, &path).unwrap();
+        assert_eq!(recover_file(&path).unwrap(), Recovery::Clean);
+
+        // Crash after both temporary files were written: the save is completed
+        let staging = temp_dir.path().join("staging.meta");
+        save_array(&new, &staging).unwrap();
+        std::fs::copy(staging.with_extension("data"), temp_path(&path.with_extension("data"))).unwrap();
+        std::fs::copy(&staging, temp_path(&path)).unwrap();
+        assert_eq!(load_array(&path).unwrap().data(), old.data());
+        assert_eq!(recover_file(&path).unwrap(), Recovery::Completed);
+        assert_eq!(load_array(&path).unwrap().data(), new.data());
+
+        // Crash after the data was renamed but before the .meta
+        save_array(&old, &path).unwrap();
+        std::fs::copy(staging.with_extension("data"), path.with_extension("data")).unwrap();
+        std::fs::copy(&staging, temp_path(&path)).unwrap();
+        let error = validate_file(&path).unwrap_err().to_string();
+        assert!(error.contains("recover_file"), "{}", error);
+        assert_eq!(recover_file(&path).unwrap(), Recovery::Completed);
+        assert_eq!(load_array(&path).unwrap().data(), new.data());
+
+        // Crash while writing the data: the previous save survives
+        save_array(&old, &path).unwrap();
+        std::fs::write(temp_path(&path.with_extension("data")), [0u8; 7]).unwrap();
+        assert_eq!(recover_file(&path).unwrap(), Recovery::Discarded);
+        assert!(!temp_path(&path.with_extension("data")).exists());
+        assert_eq!(load_array(&path).unwrap().data(), old.data());
+
+        // A .meta that cannot be renamed into place is kept for recovery
+        std::fs::remove_file(&path).unwrap();
+        std::fs::create_dir_all(path.join("blocker")).unwrap();
+        let error = save_array(&new, &path).unwrap_err();
+        assert!(error.to_string().contains("recover_file"), "{}", error);
+        assert!(temp_path(&path).is_file());
+        std::fs::remove_dir_all(&path).unwrap();
+        assert_eq!(recover_file(&path).unwrap(), Recovery::Completed);
+        assert_eq!(load_array(&path).unwrap().data(), new.data());
+    }
+}
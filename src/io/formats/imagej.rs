@@ -0,0 +1,253 @@
+//! ImageJ hyperstack metadata
+//!
+//! ImageJ keeps the stack layout as `key=value` lines in the ImageDescription
+//! of the first IFD and stores planes in CZT order (channel fastest). Slice
+//! labels, which carry the channel names, live in the binary IJMetadata tag.
+
+use super::ome_tiff::OmeMetadata;
+use crate::io::array_6d::{Dimensions, VoxelSize};
+use crate::io::dtype::DataType;
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+use tiff::decoder::ifd::Value;
+use tiff::decoder::Decoder;
+use tiff::tags::Tag;
+use tiff::ColorType;
+
+/// Private tag holding the byte count of each IJMetadata block
+pub const IJ_METADATA_BYTE_COUNTS: u16 = 50838;
+
+/// Private tag holding the IJMetadata blocks
+pub const IJ_METADATA: u16 = 50839;
+
+/// IJMetadata header magic ("IJIJ")
+const MAGIC: u32 = 0x494a_494a;
+
+/// IJMetadata type of slice labels ("labl")
+const LABELS: u32 = 0x6c61_626c;
+
+/// Check whether an ImageDescription was written by ImageJ
+pub fn is_imagej_description(description: &str) -> bool {
+    description.starts_with("ImageJ=")
+}
+
+/// Build the ImageDescription of a hyperstack with a single position
+pub fn description(dimensions: &Dimensions, z_step_um: f64, time_interval_s: f64) -> String {
+    let images = dimensions.time * dimensions.z * dimensions.channel;
+    let mut lines = vec![
+        "ImageJ=1.11a".to_string(),
+        format!("images={}", images),
+        format!("channels={}", dimensions.channel),
+        format!("slices={}", dimensions.z),
+        format!("frames={}", dimensions.time),
+        "hyperstack=true".to_string(),
+    ];
+    if dimensions.channel > 1 {
+        lines.push("mode=grayscale".to_string());
+    }
+    lines.push("unit=micron".to_string());
+    lines.push(format!("spacing={}", z_step_um));
+    lines.push(format!("finterval={}", time_interval_s));
+    lines.push("loop=false".to_string());
+
+    lines.join("\n") + "\n"
+}
+
+/// Encode slice labels as little-endian IJMetadata (byte counts, data)
+pub fn encode_labels(labels: &[String]) -> (Vec<u32>, Vec<u8>) {
+    let mut data = Vec::new();
+    data.extend_from_slice(&MAGIC.to_le_bytes());
+    data.extend_from_slice(&LABELS.to_le_bytes());
+    data.extend_from_slice(&(labels.len() as u32).to_le_bytes());
+    let mut counts = vec![data.len() as u32];
+
+    for label in labels {
+        let start = data.len();
+        for unit in label.encode_utf16() {
+            data.extend_from_slice(&unit.to_le_bytes());
+        }
+        counts.push((data.len() - start) as u32);
+    }
+
+    (counts, data)
+}
+
+/// Decode the slice labels from IJMetadata in the file's byte order
+pub fn decode_labels(counts: &[u32], data: &[u8], big_endian: bool) -> Result<Vec<String>> {
+    let read_u32 = |offset: usize| -> Result<u32> {
+        let bytes: [u8; 4] = data.get(offset..offset + 4)
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| anyhow!("IJMetadata is truncated"))?;
+        Ok(if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    };
+
+    if read_u32(0)? != MAGIC {
+        return Err(anyhow!("IJMetadata has an invalid header"));
+    }
+    let header_len = *counts.first().ok_or_else(|| anyhow!("IJMetadata has no byte counts"))? as usize;
+
+    let mut labels = Vec::new();
+    let mut offset = header_len;
+    let mut blocks = counts[1..].iter();
+    for entry in (4..header_len).step_by(8) {
+        let kind = read_u32(entry)?;
+        for _ in 0..read_u32(entry + 4)? {
+            let len = *blocks.next().ok_or_else(|| anyhow!("IJMetadata is missing byte counts"))? as usize;
+            let block = data.get(offset..offset + len)
+                .ok_or_else(|| anyhow!("IJMetadata is truncated"))?;
+            offset += len;
+
+            if kind == LABELS {
+                let units: Vec<u16> = block.chunks_exact(2)
+                    .map(|b| if big_endian { u16::from_be_bytes([b[0], b[1]]) } else { u16::from_le_bytes([b[0], b[1]]) })
+                    .collect();
+                labels.push(String::from_utf16_lossy(&units));
+            }
+        }
+    }
+
+    Ok(labels)
+}
+
+/// Read the layout of an ImageJ hyperstack from its first IFD
+pub fn read_metadata<R: Read + Seek>(decoder: &mut Decoder<R>, description: &str, big_endian: bool) -> Result<OmeMetadata> {
+    let entries: HashMap<&str, &str> = description.lines()
+        .filter_map(|line| line.split_once('='))
+        .collect();
+    let count = |key: &str| -> Result<usize> {
+        entries.get(key)
+            .map(|v| v.parse().map_err(|_| anyhow!("Invalid ImageJ {} '{}'", key, v)))
+            .unwrap_or(Ok(1))
+    };
+
+    let images = count("images")?;
+    let channels = count("channels")?;
+    let mut slices = count("slices")?;
+    let frames = count("frames")?;
+    // Plain stacks only give the image count; ImageJ treats them as slices
+    if channels * slices * frames == 1 {
+        slices = images;
+    }
+    if channels * slices * frames != images {
+        return Err(anyhow!(
+            "ImageJ metadata lists {} images but {} channels × {} slices × {} frames",
+            images, channels, slices, frames
+        ));
+    }
+
+    let (width, height) = decoder.dimensions()?;
+    let dimensions = Dimensions {
+        time: frames,
+        position: 1,
+        z: slices,
+        channel: channels,
+        height: height as usize,
+        width: width as usize,
+    };
+    dimensions.validate_extents()?;
+
+    let float = decoder.find_tag_unsigned::<u16>(Tag::SampleFormat)? == Some(3);
+    let data_type = match (decoder.colortype()?, float) {
+        (ColorType::Gray(8), false) => DataType::UInt8,
+        (ColorType::Gray(16), false) => DataType::UInt16,
+        (ColorType::Gray(32), false) => DataType::UInt32,
+        (ColorType::Gray(32), true) => DataType::Float32,
+        (ColorType::Gray(64), true) => DataType::Float64,
+        (colortype, _) => return Err(anyhow!("Unsupported ImageJ pixel format {:?}", colortype)),
+    };
+
+    // X/YResolution are pixels per unit, spacing is the z-step in units
+    let micron = matches!(entries.get("unit").copied(), Some("micron" | "um" | "µm" | "\\u00B5m"));
+    let x_um = match decoder.find_tag(Tag::XResolution)? {
+        Some(Value::Rational(n, d)) if micron && n > 0 => d as f64 / n as f64,
+        _ => 1.0,
+    };
+    let y_um = match decoder.find_tag(Tag::YResolution)? {
+        Some(Value::Rational(n, d)) if micron && n > 0 => d as f64 / n as f64,
+        _ => x_um,
+    };
+    let z_um = match entries.get("spacing") {
+        Some(v) if micron => v.parse().map_err(|_| anyhow!("Invalid ImageJ spacing '{}'", v))?,
+        _ => x_um,
+    };
+    let time_interval_s = match entries.get("finterval") {
+        Some(v) => v.parse().map_err(|_| anyhow!("Invalid ImageJ finterval '{}'", v))?,
+        None => 1.0,
+    };
+
+    let labels = match (
+        decoder.find_tag(Tag::Unknown(IJ_METADATA_BYTE_COUNTS))?,
+        decoder.find_tag(Tag::Unknown(IJ_METADATA))?,
+    ) {
+        (Some(counts), Some(data)) => decode_labels(&counts.into_u32_vec()?, &tag_bytes(data)?, big_endian)?,
+        _ => Vec::new(),
+    };
+    // Planes are in CZT order, so the first C labels name the channels
+    let channel_names = (0..channels)
+        .map(|c| match labels.get(c) {
+            Some(label) if !label.is_empty() => label.clone(),
+            _ => format!("Channel {}", c),
+        })
+        .collect();
+
+    Ok(OmeMetadata {
+        dimensions,
+        voxel_size: VoxelSize::new(x_um, y_um, z_um),
+        time_interval_s,
+        channel_names,
+        channels: Vec::new(),
+        positions: Vec::new(),
+        data_type,
+        plane_ifds: (0..images).collect(),
+    })
+}
+
+/// Get the bytes of a BYTE or UNDEFINED tag value
+fn tag_bytes(value: Value) -> Result<Vec<u8>> {
+    let values = match value {
+        Value::List(values) => values,
+        value => vec![value],
+    };
+    values.into_iter()
+        .map(|value| match value {
+            Value::Byte(b) => Ok(b),
+            Value::Unsigned(b) if b <= u8::MAX as u32 => Ok(b as u8),
+            Value::UnsignedBig(b) if b <= u8::MAX as u64 => Ok(b as u8),
+            value => Err(anyhow!("IJMetadata holds {:?} instead of bytes", value)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_roundtrip() {
+        let labels = vec!["Phase".to_string(), "GFP µ".to_string(), String::new()];
+        let (counts, data) = encode_labels(&labels);
+        assert_eq!(counts.len(), labels.len() + 1);
+        assert_eq!(counts.iter().sum::<u32>() as usize, data.len());
+        assert_eq!(decode_labels(&counts, &data, false).unwrap(), labels);
+
+        // Big-endian files (as written by ImageJ itself) swap every field
+        let mut big = Vec::new();
+        big.extend_from_slice(&MAGIC.to_be_bytes());
+        big.extend_from_slice(&LABELS.to_be_bytes());
+        big.extend_from_slice(&1u32.to_be_bytes());
+        big.extend_from_slice(&[0, b'D', 0, b'A']);
+        assert_eq!(decode_labels(&[12, 4], &big, true).unwrap(), ["DA"]);
+
+        assert!(decode_labels(&counts, &data[..data.len() - 1], false).is_err());
+    }
+
+    #[test]
+    fn test_description() {
+        let description = description(&Dimensions::new_2d(3, 1, 2, 4, 5), 0.5, 2.5);
+        assert!(is_imagej_description(&description));
+        assert!(description.contains("images=6\n"));
+        assert!(description.contains("channels=2\nslices=1\nframes=3\n"));
+        assert!(description.contains("spacing=0.5\nfinterval=2.5\n"));
+    }
+}
@@ -0,0 +1,1221 @@
+//! File format support for 6D arrays
+//! 
+//! Supports loading and saving 6D arrays in Split format (.meta + .data files).
+//! Split files can either be loaded into memory or opened lazily through a
+//! memory mapping of the `.data` file. Samples are stored at the native width
+//! of the array's data type and converted to the requested pixel type on load.
+//! The `.data` file holds either raw frames or independently compressed
+//! frame chunks (see [`super::chunked`]); readers detect the layout from the
+//! metadata.
+//!
+//! OME-TIFF files (`.ome.tif`) are read through [`ome_tiff`] and OME-Zarr
+//! stores (`.zarr`) through [`ome_zarr`], NumPy `.npy`/`.npz` files through
+//! [`npy`]; the functions below pick the backend from the path through the
+//! [`registry`].
+
+pub mod atomic;
+pub mod imagej;
+pub mod multi_position;
+pub mod npy;
+pub mod ome_tiff;
+pub mod ome_zarr;
+pub mod raw;
+pub mod registry;
+pub mod sequence;
+pub mod writer;
+
+use super::array_6d::{frame_from_bytes, frame_start, Array6D, FrameSource, MappedFrames, ReorderedFrames, VoxelSize};
+use super::axes::AxisSpec;
+use atomic::{remove_if_exists, temp_path, Commit, CommitWriter};
+use super::chunked::{ChunkIndex, ChunkedFrames, Compression, CHUNKED_FORMAT_VERSION};
+use super::channels::ChannelMetadata;
+use super::dtype::{ByteOrder, DataType, Pixel, SampleEncoding};
+use super::positions::PositionMetadata;
+use super::verify::{ChecksumKind, FrameChecksums};
+use super::provenance::{now_rfc3339, ProvenanceEntry};
+use registry::FormatRegistry;
+use anyhow::{Result, anyhow};
+use memmap2::MmapOptions;
+use ndarray::Array2;
+use std::path::{Path, PathBuf};
+use std::fs::File;
+use std::sync::Arc;
+use std::io::{Read, Seek, SeekFrom, Write};
+use serde::{Serialize, Deserialize};
+
+/// Split format version written by this build
+pub const FORMAT_VERSION: &str = "1.1";
+
+/// Format version of legacy files that always store f32 samples
+const LEGACY_F32_VERSION: &str = "1.0";
+
+/// Metadata that gets saved separately from array data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArrayMetadata {
+    pub dimensions: super::array_6d::Dimensions,
+    /// Pixel width in µm; also the height and z-step of files without them
+    pub pixel_size_um: f64,
+    /// Pixel height in µm
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pixel_size_y_um: Option<f64>,
+    /// Step between z-planes in µm
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub z_step_um: Option<f64>,
+    pub time_interval_s: f64,
+    pub channel_names: Vec<String>,
+    pub data_type: String,
+    /// Byte order of the samples; absent in 1.0 files, which are little-endian
+    #[serde(default)]
+    pub byte_order: ByteOrder,
+    /// Order of the axes in the `.data` file, outermost first; absent for TPZCYX
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub axis_order: Option<AxisSpec>,
+    pub format_version: String,
+    /// When the file was written (RFC 3339, UTC)
+    pub created_at: String,
+    /// Seconds since the first time point, for irregular acquisitions;
+    /// absent when time points are `time_interval_s` apart
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamps_s: Option<Vec<f64>>,
+    /// Operations that produced the file, oldest first; only ever appended to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provenance: Vec<ProvenanceEntry>,
+    /// Acquisition settings of every channel; absent if unknown
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub channels: Vec<ChannelMetadata>,
+    /// Stage coordinates and wells of every position; absent if unknown
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub positions: Vec<PositionMetadata>,
+    /// Chunk offsets of compressed (2.0) files; absent for raw files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunks: Option<ChunkIndex>,
+    /// Sample file relative to the `.meta` file; absent for `<name>.data`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_file: Option<String>,
+    /// Bytes before the first sample, e.g. the header of a foreign raw file
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub data_offset: u64,
+    /// Set while a [`writer::SplitWriter`] appends time points; the `.data`
+    /// file may then hold frames beyond `dimensions.time`, which readers ignore
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub appending: bool,
+    /// Size and checksum of the data at the last save; absent in older files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<Commit>,
+    /// Checksum of every frame, if the file was saved with them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame_checksums: Option<FrameChecksums>,
+    /// Downsampling factors of the pyramid levels in `<name>.pyramid/`; absent without a pyramid
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pyramid: Vec<usize>,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// Layout of the `.data` file written by [`save_array_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplitFormat {
+    /// Uncompressed samples (format 1.1)
+    #[default]
+    Raw,
+    /// One compressed chunk per frame with an offset index (format 2.0)
+    Chunked(Compression),
+}
+
+impl<T: Pixel> From<&Array6D<T>> for ArrayMetadata {
+    fn from(array: &Array6D<T>) -> Self {
+        let mut metadata = Self::new(
+            *array.dimensions(),
+            array.pixel_size_um(),
+            array.time_interval_s(),
+            array.channel_names().to_vec(),
+            array.dtype(),
+        );
+        let voxel_size = array.voxel_size();
+        metadata.pixel_size_y_um = Some(voxel_size.y_um);
+        metadata.z_step_um = Some(voxel_size.z_um);
+        metadata.timestamps_s = array.timestamps_s().map(<[f64]>::to_vec);
+        metadata.provenance = array.provenance().to_vec();
+        metadata.channels = array.channels().to_vec();
+        metadata.positions = array.positions().to_vec();
+        metadata
+    }
+}
+
+impl ArrayMetadata {
+    /// Create metadata of a little-endian raw (1.1) file
+    pub fn new(
+        dimensions: super::array_6d::Dimensions,
+        pixel_size_um: f64,
+        time_interval_s: f64,
+        channel_names: Vec<String>,
+        data_type: DataType,
+    ) -> Self {
+        Self {
+            dimensions,
+            pixel_size_um,
+            pixel_size_y_um: None,
+            z_step_um: None,
+            time_interval_s,
+            channel_names,
+            data_type: data_type.name().to_string(),
+            byte_order: ByteOrder::Little,
+            axis_order: None,
+            format_version: FORMAT_VERSION.to_string(),
+            created_at: now_rfc3339(),
+            timestamps_s: None,
+            provenance: Vec::new(),
+            channels: Vec::new(),
+            positions: Vec::new(),
+            chunks: None,
+            data_file: None,
+            data_offset: 0,
+            appending: false,
+            commit: None,
+            frame_checksums: None,
+            pyramid: Vec::new(),
+        }
+    }
+    
+    /// Get encoding of the samples in the `.data` file
+    ///
+    /// Version 1.0 files always hold f32 samples regardless of `data_type`.
+    pub fn encoding(&self) -> Result<SampleEncoding> {
+        let data_type = if self.format_version == LEGACY_F32_VERSION {
+            DataType::Float32
+        } else {
+            self.data_type.parse()?
+        };
+        Ok(SampleEncoding::new(data_type, self.byte_order))
+    }
+    
+    /// Get the order of the axes in the `.data` file
+    pub fn stored_order(&self) -> AxisSpec {
+        self.axis_order.clone().unwrap_or_else(AxisSpec::tpzcyx)
+    }
+    
+    /// Get the voxel size, isotropic for files that only record `pixel_size_um`
+    pub fn voxel_size(&self) -> VoxelSize {
+        VoxelSize::new(
+            self.pixel_size_um,
+            self.pixel_size_y_um.unwrap_or(self.pixel_size_um),
+            self.z_step_um.unwrap_or(self.pixel_size_um),
+        )
+    }
+    
+    /// Get path of the sample file belonging to the `.meta` file at `meta_path`
+    pub fn data_path(&self, meta_path: &Path) -> PathBuf {
+        match &self.data_file {
+            Some(file) => meta_path.parent().unwrap_or(Path::new("")).join(file),
+            None => meta_path.with_extension("data"),
+        }
+    }
+    
+    /// Get size of one decoded frame in bytes
+    pub fn frame_size(&self) -> Result<usize> {
+        Ok(self.dimensions.frame_elements() * self.encoding()?.size())
+    }
+    
+    /// Get expected size of the `.data` file in bytes
+    pub fn data_size(&self) -> Result<usize> {
+        match &self.chunks {
+            Some(index) => Ok(index.offsets.last().copied().unwrap_or(0) as usize),
+            None => Ok(self.dimensions.total_elements() * self.encoding()?.size()),
+        }
+    }
+    
+    /// Check the `.data` file size against the metadata
+    ///
+    /// Raw files must hold exactly every sample after `data_offset`, or at
+    /// least every sample while appending; chunked files must match their
+    /// chunk index.
+    pub fn check_data_size(&self, file_size: u64) -> Result<()> {
+        let data_size = file_size.checked_sub(self.data_offset).ok_or_else(|| anyhow!(
+            "Data file size mismatch: {} bytes is smaller than the {} byte header",
+            file_size, self.data_offset
+        ))?;
+        if let Some(index) = &self.chunks {
+            return index.validate(self.dimensions.frame_count(), data_size);
+        }
+        
+        let expected_size = self.data_size()? as u64;
+        if data_size != expected_size && !(self.appending && data_size > expected_size) {
+            return Err(anyhow!(
+                "Data file size mismatch: expected {} bytes, got {}",
+                expected_size, data_size
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Save a 6D array to split format (.meta + .data files)
+///
+/// Paths ending in `.tif`/`.tiff` are written as OME-TIFF with one series
+/// per position; use [`ome_tiff::save_tiff`] for ImageJ hyperstacks or one
+/// file per position. Paths ending in `.zarr` are written as OME-Zarr and
+/// paths ending in `.npy`/`.npz` as TPZCYX NumPy arrays.
+pub fn save_array<T: Pixel, P: AsRef<Path>>(array: &Array6D<T>, path: P) -> Result<()> {
+    let path = path.as_ref();
+    FormatRegistry::builtin().writer_for(path)?.save(array, path)
+}
+
+/// Save a 6D array to split format with the given `.data` layout
+pub fn save_array_with<T: Pixel, P: AsRef<Path>>(array: &Array6D<T>, path: P, format: SplitFormat) -> Result<()> {
+    save_split(array, path.as_ref(), format, None, &AxisSpec::tpzcyx())
+}
+
+/// Save a 6D array to split format with its axes stored in `axis_order`
+///
+/// Pick the order that suits how the file is read: e.g. `PTCZYX` keeps the
+/// time series of each position together. Axes missing from the order
+/// must have length 1. Chunked files need orders ending in `YX`. Readers
+/// still see a TPZCYX array.
+pub fn save_array_in_order<T: Pixel, P: AsRef<Path>>(
+    array: &Array6D<T>,
+    path: P,
+    format: SplitFormat,
+    axis_order: &AxisSpec,
+) -> Result<()> {
+    save_split(array, path.as_ref(), format, None, axis_order)
+}
+
+/// Save a 6D array to split format with a checksum of every frame
+///
+/// [`verify_file`](super::verify::verify_file) uses the checksums to name
+/// corrupt frames.
+pub fn save_array_with_checksums<T: Pixel, P: AsRef<Path>>(
+    array: &Array6D<T>,
+    path: P,
+    format: SplitFormat,
+    checksum: ChecksumKind,
+) -> Result<()> {
+    save_split(array, path.as_ref(), format, Some(checksum), &AxisSpec::tpzcyx())
+}
+
+/// Load a 6D array from split format (.meta + .data files), OME-TIFF, OME-Zarr or NumPy
+///
+/// A directory or JSON manifest of split files, one per position, loads as one array.
+pub fn load_array<P: AsRef<Path>>(path: P) -> Result<Array6D> {
+    load_array_as(path)
+}
+
+/// Load a 6D array from split format with pixel type `T`
+///
+/// Used for label images (u32) and masks (u8) stored in split format.
+pub fn load_array_as<T: Pixel, P: AsRef<Path>>(path: P) -> Result<Array6D<T>> {
+    let path = path.as_ref();
+    FormatRegistry::builtin().reader_for(path)?.load(path)
+}
+
+/// Open a 6D array from split format without loading the data
+///
+/// The `.data` file is memory-mapped and frames are read on demand, so
+/// datasets larger than available memory open instantly. OME-TIFF planes
+/// and OME-Zarr chunks are decoded on demand as well.
+pub fn open_array<P: AsRef<Path>>(path: P) -> Result<Array6D> {
+    open_array_as(path)
+}
+
+/// Open a 6D array from split format with pixel type `T` without loading the data
+pub fn open_array_as<T: Pixel, P: AsRef<Path>>(path: P) -> Result<Array6D<T>> {
+    let path = path.as_ref();
+    FormatRegistry::builtin().reader_for(path)?.open(path)
+}
+
+/// Read a single 2D frame (t, p, z, c) from split format
+///
+/// Only the bytes of the requested plane are read from the `.data` file; the
+/// offset is derived from the TPZCYX dimensions in the metadata, or taken
+/// from the chunk index for compressed files.
+pub fn read_frame<P: AsRef<Path>>(path: P, t: usize, p: usize, z: usize, c: usize) -> Result<Array2<f32>> {
+    read_frame_as(path, t, p, z, c)
+}
+
+/// Read a single 2D frame (t, p, z, c) from split format with pixel type `T`
+pub fn read_frame_as<T: Pixel, P: AsRef<Path>>(path: P, t: usize, p: usize, z: usize, c: usize) -> Result<Array2<T>> {
+    let path = path.as_ref();
+    FormatRegistry::builtin().reader_for(path)?.read_frame(path, t, p, z, c)
+}
+
+fn read_split_frame<T: Pixel>(path: &Path, t: usize, p: usize, z: usize, c: usize) -> Result<Array2<T>> {
+    let metadata = read_metadata(path)?;
+    let dims = metadata.dimensions;
+    dims.check_frame_index(t, p, z, c)?;
+    let encoding = metadata.encoding()?;
+    
+    let mut data_file = File::open(metadata.data_path(path))?;
+    
+    // Refuse truncated files instead of reading a partial frame
+    metadata.check_data_size(data_file.metadata()?.len())?;
+    
+    // Frames that are not contiguous in the file are gathered from the mapping
+    let order = metadata.stored_order();
+    if !order.has_contiguous_frames() {
+        return Ok(open_split(path)?.get_frame(t, p, z, c)?.into_owned());
+    }
+    let start = frame_start(&order.strides(&dims), &dims, dims.frame_index(t, p, z, c));
+    
+    let frame_size = metadata.frame_size()?;
+    let buffer = match &metadata.chunks {
+        Some(index) => {
+            let range = index.chunk_range(start / dims.frame_elements());
+            let mut chunk = vec![0u8; range.len()];
+            data_file.seek(SeekFrom::Start(metadata.data_offset + range.start as u64))?;
+            data_file.read_exact(&mut chunk)?;
+            index.compression.decompress(&chunk, frame_size)?
+        }
+        None => {
+            let offset = metadata.data_offset + (start * encoding.size()) as u64;
+            let mut buffer = vec![0u8; frame_size];
+            data_file.seek(SeekFrom::Start(offset))?;
+            data_file.read_exact(&mut buffer)?;
+            buffer
+        }
+    };
+    
+    frame_from_bytes(&buffer, &dims, encoding)
+}
+
+/// Save in split format (metadata + data files)
+///
+/// Both files are written under a `.tmp` suffix and renamed into place once
+/// complete, `.data` first. A save interrupted before that leaves the
+/// previous pair untouched; one interrupted between the renames is detected
+/// by the commit check when the file is read and completed by `recover_file`.
+fn save_split<T: Pixel>(
+    array: &Array6D<T>,
+    path: &Path,
+    format: SplitFormat,
+    checksum: Option<ChecksumKind>,
+    axis_order: &AxisSpec,
+) -> Result<()> {
+    axis_order.check_dimensions(array.dimensions())?;
+    if matches!(format, SplitFormat::Chunked(_)) && !axis_order.has_contiguous_frames() {
+        return Err(anyhow!("Compressed files must store whole YX frames, got axis order {}", axis_order));
+    }
+    let mut metadata = ArrayMetadata::from(array);
+    let mut entry = ProvenanceEntry::new("save_array").parameter("data_type", array.data_type());
+    if !axis_order.is_tpzcyx_order() {
+        metadata.axis_order = Some(axis_order.clone());
+        entry = entry.parameter("axis_order", axis_order);
+    }
+    entry = match format {
+        SplitFormat::Raw => entry.parameter("layout", "raw"),
+        SplitFormat::Chunked(compression) => entry.parameter("layout", "chunked").parameter("compression", compression.name()),
+    };
+    if let Some(checksum) = checksum {
+        entry = entry.parameter("frame_checksums", checksum);
+    }
+    metadata.provenance.push(entry);
+    let data_path = metadata.data_path(path);
+    let (meta_temp, data_temp) = (temp_path(path), temp_path(&data_path));
+    
+    let written = write_split(array, metadata, &meta_temp, &data_temp, format, checksum)
+        .and_then(|_| Ok(std::fs::rename(&data_temp, &data_path)?));
+    if let Err(e) = written {
+        remove_if_exists(&meta_temp)?;
+        remove_if_exists(&data_temp)?;
+        return Err(e);
+    }
+    // Data first: a crash or failure from here on leaves a .meta whose commit
+    // no longer matches, and the pending .meta for recover_file to finish with
+    std::fs::rename(&meta_temp, path)
+        .map_err(|e| anyhow!("{:?} was saved but not committed ({}); run recover_file to complete it", path, e))
+}
+
+/// Write the data and then the metadata of a split file to the given paths
+fn write_split<T: Pixel>(
+    array: &Array6D<T>,
+    mut metadata: ArrayMetadata,
+    meta_path: &Path,
+    data_path: &Path,
+    format: SplitFormat,
+    checksum: Option<ChecksumKind>,
+) -> Result<()> {
+    let mut data_file = CommitWriter::create(data_path)?;
+    let mut checksums = checksum.map(FrameChecksums::new);
+    
+    // Save samples in the array's data type, frame by frame in the stored
+    // order so lazily read arrays never need to be materialised
+    let dims = array.dimensions();
+    let encoding = metadata.encoding()?;
+    let order = metadata.stored_order();
+    let mut buffer = Vec::with_capacity(metadata.frame_size()?);
+    let mut offsets = vec![0u64];
+    let mut write_frame = |buffer: &[u8]| -> Result<()> {
+        if let Some(checksums) = &mut checksums {
+            checksums.push(buffer);
+        }
+        match format {
+            SplitFormat::Raw => data_file.write_all(buffer)?,
+            SplitFormat::Chunked(compression) => {
+                let chunk = compression.compress(buffer)?;
+                data_file.write_all(&chunk)?;
+                offsets.push(offsets[offsets.len() - 1] + chunk.len() as u64);
+            }
+        }
+        Ok(())
+    };
+    
+    if order.has_contiguous_frames() {
+        for index in 0..dims.frame_count() {
+            let (t, p, z, c) = order.stored_frame_coords(dims, index);
+            let frame = array.get_frame(t, p, z, c)?;
+            buffer.clear();
+            encoding.encode(frame.iter(), &mut buffer);
+            write_frame(&buffer)?;
+        }
+    } else {
+        // Frames are interleaved on disk, so the samples come from a permuted view
+        let materialized;
+        let data = match array.data() {
+            Some(data) => data,
+            None => {
+                materialized = array.materialize()?;
+                materialized.data().ok_or_else(|| anyhow!("Materialized array has no data"))?
+            }
+        };
+        let mut samples = data.view().permuted_axes(order.permutation()).into_iter();
+        for _ in 0..dims.frame_count() {
+            buffer.clear();
+            encoding.encode(samples.by_ref().take(dims.frame_elements()), &mut buffer);
+            write_frame(&buffer)?;
+        }
+    }
+    
+    metadata.commit = Some(data_file.sync()?);
+    metadata.frame_checksums = checksums;
+    
+    // Save metadata once the chunk offsets are known
+    if let SplitFormat::Chunked(compression) = format {
+        metadata.format_version = CHUNKED_FORMAT_VERSION.to_string();
+        metadata.chunks = Some(ChunkIndex { compression, offsets });
+    }
+    write_metadata(meta_path, &metadata)
+}
+
+/// Write the JSON metadata of a split format file and sync it to disk
+fn write_metadata(path: &Path, metadata: &ArrayMetadata) -> Result<()> {
+    let mut meta_file = File::create(path)?;
+    let meta_json = serde_json::to_string_pretty(metadata)?;
+    meta_file.write_all(meta_json.as_bytes())?;
+    meta_file.sync_all()?;
+    
+    Ok(())
+}
+
+/// Read the JSON metadata of a split format file
+pub(crate) fn read_metadata(path: &Path) -> Result<ArrayMetadata> {
+    let mut meta_file = File::open(path)?;
+    let mut meta_content = String::new();
+    meta_file.read_to_string(&mut meta_content)?;
+    let metadata: ArrayMetadata = serde_json::from_str(&meta_content)?;
+    
+    if metadata.format_version == CHUNKED_FORMAT_VERSION && metadata.chunks.is_none() {
+        return Err(anyhow!("Format version {} requires a chunk index", CHUNKED_FORMAT_VERSION));
+    }
+    if metadata.appending && metadata.chunks.is_some() {
+        return Err(anyhow!("Compressed files cannot be appended to"));
+    }
+    if let Some(checksums) = &metadata.frame_checksums {
+        checksums.validate(metadata.dimensions.frame_count())?;
+    }
+    if let Some(timestamps) = &metadata.timestamps_s {
+        metadata.dimensions.check_timestamps(timestamps)?;
+    }
+    metadata.dimensions.check_channels(&metadata.channels)?;
+    metadata.dimensions.check_positions(&metadata.positions)?;
+    if metadata.pixel_size_y_um.is_some() || metadata.z_step_um.is_some() {
+        metadata.voxel_size().validate()?;
+    }
+    if let Some(order) = &metadata.axis_order {
+        order.check_dimensions(&metadata.dimensions)?;
+        if metadata.chunks.is_some() && !order.has_contiguous_frames() {
+            return Err(anyhow!("Compressed files must store whole YX frames, got axis order {}", order));
+        }
+        if metadata.appending && !order.is_tpzcyx_order() {
+            return Err(anyhow!("Files in axis order {} cannot be appended to", order));
+        }
+    }
+    Ok(metadata)
+}
+
+/// Load from split format (metadata + data files)
+fn load_split<T: Pixel>(path: &Path) -> Result<Array6D<T>> {
+    // Load metadata
+    let metadata = read_metadata(path)?;
+    
+    // Load data, skipping any foreign header
+    let mut data_file = File::open(metadata.data_path(path))?;
+    metadata.check_data_size(data_file.metadata()?.len())?;
+    data_file.seek(SeekFrom::Start(metadata.data_offset))?;
+    let mut buffer = Vec::new();
+    data_file.take(metadata.data_size()? as u64).read_to_end(&mut buffer)?;
+    
+    // Convert stored samples to the requested pixel type
+    metadata.check_data_size(metadata.data_offset + buffer.len() as u64)?;
+    
+    let encoding = metadata.encoding()?;
+    let values = match &metadata.chunks {
+        Some(index) => {
+            let frame_size = metadata.frame_size()?;
+            let mut values = Vec::with_capacity(metadata.dimensions.total_elements());
+            for i in 0..metadata.dimensions.frame_count() {
+                let frame = index.compression.decompress(&buffer[index.chunk_range(i)], frame_size)?;
+                values.extend(encoding.decode::<T>(&frame));
+            }
+            values
+        }
+        None => encoding.decode(&buffer),
+    };
+    drop(buffer);
+    
+    // Reconstruct ndarray, transposing files stored in another order
+    let order = metadata.stored_order();
+    let data = if order.is_tpzcyx_order() {
+        ndarray::Array6::from_shape_vec(metadata.dimensions.shape(), values)?
+    } else {
+        let stored = ndarray::ArrayD::from_shape_vec(order.shape(&metadata.dimensions), values)?;
+        order.to_tpzcyx(stored)?
+    };
+    
+    let array = Array6D::new(
+        data,
+        metadata.dimensions,
+        metadata.pixel_size_um,
+        metadata.time_interval_s,
+        metadata.channel_names.clone(),
+        metadata.data_type.clone(),
+    )?;
+    with_details(array, metadata)
+}
+
+/// Carry the voxel size, timestamps, provenance log, channel settings and positions of a `.meta` file over to its array
+fn with_details<T: Pixel>(array: Array6D<T>, metadata: ArrayMetadata) -> Result<Array6D<T>> {
+    // Legacy files keep the isotropic voxel the array was created with
+    let array = if metadata.pixel_size_y_um.is_some() || metadata.z_step_um.is_some() {
+        array.with_voxel_size(metadata.voxel_size())?
+    } else {
+        array
+    };
+    let array = array
+        .with_provenance(metadata.provenance)
+        .with_channels(metadata.channels)?
+        .with_positions(metadata.positions)?;
+    match metadata.timestamps_s {
+        Some(timestamps_s) => array.with_timestamps(timestamps_s),
+        None => Ok(array),
+    }
+}
+
+/// Open split format with the data file memory-mapped
+///
+/// Compressed chunks are decompressed one frame at a time on access.
+fn open_split<T: Pixel>(path: &Path) -> Result<Array6D<T>> {
+    let mut metadata = read_metadata(path)?;
+    
+    let data_file = File::open(metadata.data_path(path))?;
+    metadata.check_data_size(data_file.metadata()?.len())?;
+    
+    // SAFETY: the mapping is read-only; the .data file must not be truncated
+    // or rewritten by another process while the array is open.
+    // Only the committed time points are mapped while a writer is appending
+    let mmap = unsafe {
+        MmapOptions::new()
+            .offset(metadata.data_offset)
+            .len(metadata.data_size()?)
+            .map(&data_file)?
+    };
+    
+    let encoding = metadata.encoding()?;
+    let order = metadata.stored_order();
+    if let Some(index) = metadata.chunks.take() {
+        let frame_size = metadata.dimensions.frame_elements() * encoding.size();
+        let frames = ChunkedFrames::new(mmap, index, metadata.dimensions.frame_count(), frame_size)?;
+        let source: Arc<dyn FrameSource> = if order.is_tpzcyx_order() {
+            Arc::new(frames)
+        } else {
+            Arc::new(ReorderedFrames::new(frames, metadata.dimensions, &order)?)
+        };
+        let array = Array6D::from_source(
+            source,
+            encoding,
+            metadata.dimensions,
+            metadata.pixel_size_um,
+            metadata.time_interval_s,
+            metadata.channel_names.clone(),
+            metadata.data_type.clone(),
+        )?;
+        return with_details(array, metadata);
+    }
+    
+    // Frames are read through the strides of the stored order, without copying the file
+    let frames = MappedFrames::new(mmap, metadata.dimensions, encoding.size(), &order);
+    let array = Array6D::from_source(
+        Arc::new(frames),
+        encoding,
+        metadata.dimensions,
+        metadata.pixel_size_um,
+        metadata.time_interval_s,
+        metadata.channel_names.clone(),
+        metadata.data_type.clone(),
+    )?;
+    with_details(array, metadata)
+}
+
+/// Get file size estimate for split format
+#[allow(dead_code)] // Utility function for size estimation
+pub fn estimate_file_size<T: Pixel>(array: &Array6D<T>) -> usize {
+    let data_size = array.memory_usage();
+    let metadata_size = 1024; // Rough estimate for JSON metadata
+    
+    // Raw data + JSON metadata
+    data_size + metadata_size * 2
+}
+
+/// Validate that a split format file can be loaded
+///
+/// Checks sizes only; [`atomic::verify_commit`] also compares the checksum.
+pub fn validate_file<P: AsRef<Path>>(path: P) -> Result<ArrayMetadata> {
+    let path = path.as_ref();
+    check_split_files(path).map_err(|e| {
+        // Point at the leftovers of an interrupted save
+        let leftovers: Vec<String> = [temp_path(path), temp_path(&path.with_extension("data"))]
+            .iter()
+            .filter(|p| p.exists())
+            .map(|p| p.display().to_string())
+            .collect();
+        if leftovers.is_empty() {
+            e
+        } else {
+            anyhow!("{} (an interrupted save left {}; use recover_file to complete or discard it)", e, leftovers.join(" and "))
+        }
+    })
+}
+
+fn check_split_files(path: &Path) -> Result<ArrayMetadata> {
+    // Load and validate metadata
+    let metadata = read_metadata(path)?;
+    
+    // Check if data file exists
+    let data_path = metadata.data_path(path);
+    if !data_path.exists() {
+        return Err(anyhow!("Data file not found: {:?}", data_path));
+    }
+    
+    // Validate data file size against the last commit, or the chunk index of compressed files
+    metadata.check_commit_size(&data_path, std::fs::metadata(&data_path)?.len())?;
+    
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::generators::ArrayGenerator;
+    use tempfile::NamedTempFile;
+    
+    #[test]
+    fn test_split_save_load() {
+        let array = ArrayGenerator::generate_minimal().unwrap();
+        let temp_file = NamedTempFile::new().unwrap();
+        let meta_path = temp_file.path().with_extension("meta");
+        let data_path = temp_file.path().with_extension("data");
+        
+        // Save
+        save_array(&array, &meta_path).unwrap();
+        
+        // Verify both files exist
+        assert!(meta_path.exists());
+        assert!(data_path.exists());
+        
+        // Load
+        let loaded_array = load_array(&meta_path).unwrap();
+        
+        // Compare metadata
+        assert_eq!(array.dimensions(), loaded_array.dimensions());
+        assert_eq!(array.channel_names(), loaded_array.channel_names());
+        assert_eq!(array.pixel_size_um(), loaded_array.pixel_size_um());
+        
+        // Compare data
+        let orig_frame = array.get_frame(0, 0, 0, 0).unwrap();
+        let loaded_frame = loaded_array.get_frame(0, 0, 0, 0).unwrap();
+        assert_eq!(orig_frame[[0, 0]], loaded_frame[[0, 0]]);
+        assert_eq!(orig_frame[[7, 7]], loaded_frame[[7, 7]]);
+        
+        // Clean up
+        std::fs::remove_file(&meta_path).ok();
+        std::fs::remove_file(&data_path).ok();
+    }
+    
+    #[test]
+    fn test_open_mapped() {
+        let array = ArrayGenerator::generate_test_array(3, 2, 2, 2, 8, 6).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("mapped.meta");
+        
+        save_array(&array, &path).unwrap();
+        let mapped = open_array(&path).unwrap();
+        
+        assert!(mapped.is_lazy());
+        assert!(mapped.data().is_none());
+        assert_eq!(mapped.dimensions(), array.dimensions());
+        assert_eq!(mapped.channel_names(), array.channel_names());
+        
+        // Every frame decoded from the mapping matches the source
+        let dims = array.dimensions();
+        for t in 0..dims.time {
+            for p in 0..dims.position {
+                for z in 0..dims.z {
+                    for c in 0..dims.channel {
+                        assert_eq!(
+                            array.get_frame(t, p, z, c).unwrap(),
+                            mapped.get_frame(t, p, z, c).unwrap()
+                        );
+                    }
+                }
+            }
+        }
+        
+        let stats = mapped.get_frame_stats(2, 1, 1, 1, 1000.0).unwrap();
+        assert_eq!(stats, array.get_frame_stats(2, 1, 1, 1, 1000.0).unwrap());
+        assert!(mapped.get_frame(3, 0, 0, 0).is_err());
+    }
+    
+    #[test]
+    fn test_mapped_is_read_only_but_resavable() {
+        let array = ArrayGenerator::generate_minimal().unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("source.meta");
+        let copy_path = temp_dir.path().join("copy.meta");
+        
+        save_array(&array, &path).unwrap();
+        let mut mapped = open_array(&path).unwrap();
+        
+        let frame = array.get_frame(0, 0, 0, 0).unwrap().to_owned();
+        assert!(mapped.set_frame(0, 0, 0, 0, &frame.view()).is_err());
+        
+        // Saving a mapped array streams its frames into a new file
+        save_array(&mapped, &copy_path).unwrap();
+        let copy = load_array(&copy_path).unwrap();
+        assert_eq!(copy.data(), array.data());
+    }
+    
+    #[test]
+    fn test_read_frame() {
+        let array = ArrayGenerator::generate_test_array(2, 2, 3, 2, 5, 4).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("frames.meta");
+        save_array(&array, &path).unwrap();
+        
+        for (t, p, z, c) in [(0, 0, 0, 0), (1, 0, 2, 1), (0, 1, 1, 0), (1, 1, 2, 1)] {
+            let frame = read_frame(&path, t, p, z, c).unwrap();
+            assert_eq!(frame, array.get_frame(t, p, z, c).unwrap());
+        }
+        
+        assert!(read_frame(&path, 2, 0, 0, 0).is_err());
+        assert!(read_frame(&path, 0, 0, 0, 2).is_err());
+    }
+    
+    #[test]
+    fn test_native_data_types() {
+        let source = ArrayGenerator::generate_test_array(2, 1, 1, 2, 6, 5).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        
+        for dtype in [DataType::UInt8, DataType::UInt16, DataType::Float32, DataType::Float64] {
+            let array = Array6D::new(
+                source.data().unwrap().mapv(|v| dtype.quantize(v)),
+                *source.dimensions(),
+                source.pixel_size_um(),
+                source.time_interval_s(),
+                source.channel_names().to_vec(),
+                dtype.name().to_string(),
+            ).unwrap();
+            
+            let path = temp_dir.path().join(format!("{}.meta", dtype));
+            save_array(&array, &path).unwrap();
+            
+            // Samples are stored at their native width
+            let data_size = std::fs::metadata(path.with_extension("data")).unwrap().len() as usize;
+            assert_eq!(data_size, array.dimensions().total_elements() * dtype.size());
+            assert_eq!(array.memory_usage(), data_size);
+            
+            let loaded = load_array(&path).unwrap();
+            assert_eq!(loaded.data_type(), dtype.name());
+            assert_eq!(loaded.data(), array.data());
+            
+            let mapped = open_array(&path).unwrap();
+            assert_eq!(mapped.get_frame(1, 0, 0, 1).unwrap(), array.get_frame(1, 0, 0, 1).unwrap());
+            assert_eq!(read_frame(&path, 1, 0, 0, 1).unwrap(), array.get_frame(1, 0, 0, 1).unwrap());
+        }
+    }
+    
+    #[test]
+    fn test_label_and_mask_roundtrip() {
+        let dims = crate::io::array_6d::Dimensions::new_2d(2, 1, 1, 4, 4);
+        let labels = ndarray::Array6::from_shape_fn(dims.shape(), |(t, _, _, _, y, x)| {
+            (t * 100_000 + y * 4 + x) as u32
+        });
+        let labels = Array6D::new(labels, dims, 0.65, 1.0, vec!["Labels".to_string()], "uint32".to_string()).unwrap();
+        let mask = Array6D::new(labels.data().unwrap().mapv(|l| (l % 2) as u8), dims, 0.65, 1.0, vec!["Mask".to_string()], "uint8".to_string()).unwrap();
+        
+        let temp_dir = tempfile::tempdir().unwrap();
+        let label_path = temp_dir.path().join("labels.meta");
+        let mask_path = temp_dir.path().join("mask.meta");
+        save_array(&labels, &label_path).unwrap();
+        save_array(&mask, &mask_path).unwrap();
+        
+        let loaded_labels = load_array_as::<u32, _>(&label_path).unwrap();
+        assert_eq!(loaded_labels.data(), labels.data());
+        assert_eq!(open_array_as::<u32, _>(&label_path).unwrap().get_frame(1, 0, 0, 0).unwrap()[[3, 3]], 100_015);
+        assert_eq!(read_frame_as::<u8, _>(&mask_path, 1, 0, 0, 0).unwrap(), mask.get_frame(1, 0, 0, 0).unwrap());
+        
+        // Masks can also be loaded as intensities
+        let as_float = load_array(&mask_path).unwrap();
+        assert_eq!(as_float.get_frame(0, 0, 0, 0).unwrap()[[0, 1]], 1.0);
+    }
+    
+    #[test]
+    fn test_load_big_endian_file() {
+        let array = ArrayGenerator::generate_test_array(2, 1, 2, 2, 4, 3).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("big.meta");
+        
+        let mut metadata = ArrayMetadata::from(&array);
+        metadata.byte_order = ByteOrder::Big;
+        std::fs::write(&path, serde_json::to_string_pretty(&metadata).unwrap()).unwrap();
+        let mut bytes = Vec::new();
+        DataType::UInt16.encode(array.data().unwrap().iter(), ByteOrder::Big, &mut bytes);
+        std::fs::write(path.with_extension("data"), bytes).unwrap();
+        
+        assert!(validate_file(&path).is_ok());
+        assert_eq!(load_array(&path).unwrap().data(), array.data());
+        assert_eq!(open_array(&path).unwrap().get_frame(1, 0, 1, 1).unwrap(), array.get_frame(1, 0, 1, 1).unwrap());
+        assert_eq!(read_frame(&path, 1, 0, 1, 0).unwrap(), array.get_frame(1, 0, 1, 0).unwrap());
+    }
+    
+    #[test]
+    fn test_load_legacy_f32_file() {
+        let array = ArrayGenerator::generate_minimal().unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("legacy.meta");
+        
+        // Version 1.0 wrote f32 samples even when data_type said uint16,
+        // and had no byte_order field
+        let mut metadata = ArrayMetadata::from(&array);
+        metadata.format_version = "1.0".to_string();
+        let mut json = serde_json::to_value(&metadata).unwrap();
+        json.as_object_mut().unwrap().remove("byte_order");
+        std::fs::write(&path, json.to_string()).unwrap();
+        let mut bytes = Vec::new();
+        DataType::Float32.encode(array.data().unwrap().iter(), ByteOrder::Little, &mut bytes);
+        std::fs::write(path.with_extension("data"), bytes).unwrap();
+        
+        assert_eq!(validate_file(&path).unwrap().byte_order, ByteOrder::Little);
+        let loaded = load_array(&path).unwrap();
+        assert_eq!(loaded.data_type(), "uint16");
+        assert_eq!(loaded.data(), array.data());
+        assert_eq!(open_array(&path).unwrap().get_frame(1, 0, 0, 1).unwrap(), array.get_frame(1, 0, 0, 1).unwrap());
+    }
+    
+    #[test]
+    fn test_chunked_roundtrip() {
+        let array = ArrayGenerator::generate_test_array(3, 2, 2, 2, 16, 12).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let raw_path = temp_dir.path().join("raw.meta");
+        save_array(&array, &raw_path).unwrap();
+        let raw_size = std::fs::metadata(raw_path.with_extension("data")).unwrap().len();
+        
+        for compression in [Compression::Zstd, Compression::Lz4] {
+            let path = temp_dir.path().join(format!("{:?}.meta", compression));
+            save_array_with(&array, &path, SplitFormat::Chunked(compression)).unwrap();
+            
+            let metadata = validate_file(&path).unwrap();
+            assert_eq!(metadata.format_version, CHUNKED_FORMAT_VERSION);
+            let index = metadata.chunks.unwrap();
+            assert_eq!(index.compression, compression);
+            assert_eq!(index.offsets.len(), array.dimensions().frame_count() + 1);
+            assert!(std::fs::metadata(path.with_extension("data")).unwrap().len() < raw_size);
+            
+            assert_eq!(load_array(&path).unwrap().data(), array.data());
+            
+            let opened = open_array(&path).unwrap();
+            assert!(opened.is_lazy());
+            assert_eq!(opened.materialize().unwrap().data(), array.data());
+            
+            for (t, p, z, c) in [(0, 0, 0, 0), (2, 1, 1, 1), (1, 0, 1, 0)] {
+                assert_eq!(opened.get_frame(t, p, z, c).unwrap(), array.get_frame(t, p, z, c).unwrap());
+                assert_eq!(read_frame(&path, t, p, z, c).unwrap(), array.get_frame(t, p, z, c).unwrap());
+            }
+        }
+    }
+    
+    #[test]
+    fn test_axis_order() {
+        let array = ArrayGenerator::generate_test_array(3, 2, 2, 2, 5, 4).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cases = [
+            ("PTCZYX", SplitFormat::Raw),
+            ("PTCZYX", SplitFormat::Chunked(Compression::Zstd)),
+            ("TPYXZC", SplitFormat::Raw),
+        ];
+        for (i, (order, format)) in cases.into_iter().enumerate() {
+            let order: AxisSpec = order.parse().unwrap();
+            let path = temp_dir.path().join(format!("ordered{}.meta", i));
+            save_array_in_order(&array, &path, format, &order).unwrap();
+            
+            let metadata = validate_file(&path).unwrap();
+            assert_eq!(metadata.axis_order.as_ref(), Some(&order));
+            assert_eq!(metadata.stored_order(), order);
+            assert!(crate::io::verify::verify_file(&path).unwrap().is_ok());
+            
+            assert_eq!(load_array(&path).unwrap().data(), array.data());
+            let opened = open_array(&path).unwrap();
+            assert!(opened.is_lazy());
+            assert_eq!(*opened.dimensions(), *array.dimensions());
+            assert_eq!(opened.materialize().unwrap().data(), array.data());
+            for (t, p, z, c) in [(0, 0, 0, 0), (2, 1, 1, 1), (1, 0, 1, 0)] {
+                assert_eq!(opened.get_frame(t, p, z, c).unwrap(), array.get_frame(t, p, z, c).unwrap());
+                assert_eq!(read_frame(&path, t, p, z, c).unwrap(), array.get_frame(t, p, z, c).unwrap());
+            }
+        }
+        
+        // TPZCYX files don't record an order
+        let path = temp_dir.path().join("default.meta");
+        save_array_in_order(&array, &path, SplitFormat::Raw, &AxisSpec::tpzcyx()).unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("axis_order"));
+        
+        // Chunks hold whole frames, and dropped axes must have length 1
+        let interleaved: AxisSpec = "TPYXZC".parse().unwrap();
+        assert!(save_array_in_order(&array, &path, SplitFormat::Chunked(Compression::Zstd), &interleaved).is_err());
+        assert!(save_array_in_order(&array, &path, SplitFormat::Raw, &"TCZYX".parse().unwrap()).is_err());
+    }
+    
+    #[test]
+    fn test_chunked_index_validation() {
+        let array = ArrayGenerator::generate_minimal().unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("chunked.meta");
+        save_array_with(&array, &path, SplitFormat::Chunked(Compression::Zstd)).unwrap();
+        let mut metadata = validate_file(&path).unwrap();
+        
+        // Truncated data no longer matches the end of the index
+        let data_path = path.with_extension("data");
+        let data = std::fs::read(&data_path).unwrap();
+        std::fs::write(&data_path, &data[..data.len() - 1]).unwrap();
+        assert!(validate_file(&path).is_err());
+        assert!(open_array(&path).is_err());
+        assert!(read_frame(&path, 0, 0, 0, 0).is_err());
+        std::fs::write(&data_path, &data).unwrap();
+        
+        // Missing chunk entries
+        metadata.chunks.as_mut().unwrap().offsets.remove(1);
+        std::fs::write(&path, serde_json::to_string(&metadata).unwrap()).unwrap();
+        assert!(validate_file(&path).is_err());
+        
+        // Version 2.0 without an index
+        metadata.chunks = None;
+        std::fs::write(&path, serde_json::to_string(&metadata).unwrap()).unwrap();
+        assert!(validate_file(&path).is_err());
+    }
+    
+    #[test]
+    fn test_ome_tiff_dispatch() {
+        let array = ArrayGenerator::generate_test_array(2, 2, 1, 2, 6, 4).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("export.ome.tif");
+        
+        save_array(&array, &path).unwrap();
+        assert!(!path.with_extension("data").exists());
+        
+        let loaded = load_array(&path).unwrap();
+        assert_eq!(loaded.data(), array.data());
+        assert_eq!(loaded.channel_names(), array.channel_names());
+        assert_eq!(open_array(&path).unwrap().get_frame(1, 1, 0, 1).unwrap(), array.get_frame(1, 1, 0, 1).unwrap());
+        assert_eq!(read_frame(&path, 0, 1, 0, 0).unwrap(), array.get_frame(0, 1, 0, 0).unwrap());
+    }
+    
+    #[test]
+    fn test_ome_zarr_dispatch() {
+        let array = ArrayGenerator::generate_test_array(2, 2, 1, 2, 6, 4).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("export.zarr");
+        
+        save_array(&array, &path).unwrap();
+        assert_eq!(load_array(&path).unwrap().data(), array.data());
+        assert!(open_array(&path).unwrap().is_lazy());
+        assert_eq!(read_frame(&path, 1, 1, 0, 1).unwrap(), array.get_frame(1, 1, 0, 1).unwrap());
+    }
+    
+    #[test]
+    fn test_numpy_dispatch() {
+        let array = ArrayGenerator::generate_test_array(2, 2, 1, 2, 6, 4).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        
+        for name in ["export.npy", "export.npz"] {
+            let path = temp_dir.path().join(name);
+            save_array(&array, &path).unwrap();
+            assert_eq!(load_array(&path).unwrap().data(), array.data());
+            assert_eq!(read_frame(&path, 1, 1, 0, 1).unwrap(), array.get_frame(1, 1, 0, 1).unwrap());
+        }
+        assert!(open_array(temp_dir.path().join("export.npy")).unwrap().is_lazy());
+    }
+    
+    #[test]
+    fn test_file_validation() {
+        let array = ArrayGenerator::generate_minimal().unwrap();
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path().with_extension("meta");
+        
+        // Save array
+        save_array(&array, &path).unwrap();
+        
+        // Validate
+        let metadata = validate_file(&path).unwrap();
+        assert_eq!(metadata.dimensions, *array.dimensions());
+        assert_eq!(metadata.channel_names, array.channel_names());
+        
+        // Clean up
+        std::fs::remove_file(path.with_extension("meta")).ok();
+        std::fs::remove_file(path.with_extension("data")).ok();
+    }
+    
+    #[test]
+    fn test_file_size_estimation() {
+        let array = ArrayGenerator::generate_minimal().unwrap();
+        let file_size = estimate_file_size(&array);
+        
+        // Should be reasonable size (data + metadata overhead)
+        let expected_data_size = array.memory_usage();
+        assert!(file_size > expected_data_size); // Should include metadata overhead
+        assert!(file_size > 1024); // Should be at least 1KB due to metadata
+    }
+    
+    #[test]
+    fn test_metadata_content() {
+        let array = ArrayGenerator::generate_minimal().unwrap();
+        let metadata = ArrayMetadata::from(&array);
+        
+        // Check metadata fields
+        assert_eq!(metadata.dimensions.time, 2);
+        assert_eq!(metadata.dimensions.channel, 2);
+        assert_eq!(metadata.format_version, FORMAT_VERSION);
+        assert_eq!(metadata.byte_order, ByteOrder::Little);
+        assert!(metadata.chunks.is_none());
+        assert!(humantime::parse_rfc3339(&metadata.created_at).is_ok());
+        assert_eq!(metadata.channel_names.len(), 2);
+    }
+    
+    #[test]
+    fn test_timestamps_and_provenance() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("irregular.meta");
+        let resaved = temp_dir.path().join("resaved.meta");
+        let mut array = ArrayGenerator::generate_minimal().unwrap();
+        assert_eq!(array.time_axis_s(), [0.0, array.time_interval_s()]);
+        assert!(array.clone().with_timestamps(vec![0.0]).is_err());
+        assert!(array.clone().with_timestamps(vec![5.0, 1.0]).is_err());
+        array.record(ProvenanceEntry::new("simulate").parameter("seed", 7));
+        let array = array.with_timestamps(vec![0.0, 42.5]).unwrap();
+        
+        save_array(&array, &path).unwrap();
+        let loaded = open_array(&path).unwrap();
+        assert_eq!(loaded.time_axis_s(), [0.0, 42.5]);
+        save_array_with(&loaded, &resaved, SplitFormat::Chunked(Compression::Lz4)).unwrap();
+        
+        // Every save appends to the log it was given
+        let metadata = validate_file(&resaved).unwrap();
+        assert_eq!(metadata.timestamps_s, Some(vec![0.0, 42.5]));
+        let operations: Vec<&str> = metadata.provenance.iter().map(|e| e.operation.as_str()).collect();
+        assert_eq!(operations, ["simulate", "save_array", "save_array"]);
+        assert_eq!(metadata.provenance[0].parameters["seed"], "7");
+        assert_eq!(metadata.provenance[2].parameters["compression"], "lz4");
+        assert_eq!(load_array(&resaved).unwrap().provenance(), &metadata.provenance[..]);
+    }
+    
+    #[test]
+    fn test_channel_metadata() {
+        use crate::io::channels::{ChannelRole, Color};
+        
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("channels.meta");
+        let array = ArrayGenerator::generate_minimal().unwrap();
+        assert!(array.channels().is_empty());
+        assert!(array.clone().with_channels(vec![ChannelMetadata::default()]).is_err());
+        
+        let channels = vec![
+            ChannelMetadata { role: Some(ChannelRole::Phase), exposure_ms: Some(20.0), ..Default::default() },
+            ChannelMetadata {
+                excitation_nm: Some(488.0),
+                emission_nm: Some(525.0),
+                exposure_ms: Some(100.0),
+                gain: Some(2.5),
+                camera_offset: Some(100.0),
+                filter: Some("FITC".to_string()),
+                color: Some(Color::new(0, 255, 0)),
+                role: Some(ChannelRole::Fluorescence),
+            },
+        ];
+        let array = array.with_channels(channels.clone()).unwrap();
+        save_array_with(&array, &path, SplitFormat::Chunked(Compression::Zstd)).unwrap();
+        assert_eq!(load_array(&path).unwrap().channels(), channels);
+        assert_eq!(open_array(&path).unwrap().channels(), channels);
+        
+        // Files without settings still load, files with the wrong number do not
+        let mut metadata = validate_file(&path).unwrap();
+        metadata.channels.clear();
+        std::fs::write(&path, serde_json::to_string(&metadata).unwrap()).unwrap();
+        assert!(open_array(&path).unwrap().channels().is_empty());
+        metadata.channels = channels[..1].to_vec();
+        std::fs::write(&path, serde_json::to_string(&metadata).unwrap()).unwrap();
+        assert!(validate_file(&path).is_err());
+    }
+    
+    #[test]
+    fn test_position_metadata() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("plate.meta");
+        let array = ArrayGenerator::generate_test_array(1, 3, 1, 1, 4, 4).unwrap();
+        assert!(array.clone().with_positions(vec![PositionMetadata::in_well("A01", 1)]).is_err());
+        assert!(array.clone().with_positions(vec![PositionMetadata::in_well("1A", 1); 3]).is_err());
+        
+        let positions = vec![
+            PositionMetadata::in_well("B03", 1).at(1000.0, 2000.0, Some(15.5)),
+            PositionMetadata::in_well("A01", 1).at(0.0, 0.0, None),
+            PositionMetadata::in_well("B03", 2).at(1100.0, 2000.0, Some(15.5)),
+        ];
+        let array = array.with_positions(positions.clone()).unwrap();
+        save_array(&array, &path).unwrap();
+        
+        let loaded = open_array(&path).unwrap();
+        assert_eq!(loaded.positions(), positions);
+        assert_eq!(loaded.wells()["B03"], [0, 2]);
+        assert_eq!(loaded.positions()[2].label().as_deref(), Some("B03 field 2"));
+        assert_eq!(validate_file(&path).unwrap().positions, positions);
+    }
+    
+    #[test]
+    fn test_voxel_size() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("stack.meta");
+        let array = ArrayGenerator::generate_test_array(1, 1, 3, 1, 4, 4).unwrap();
+        assert!(array.clone().with_voxel_size(VoxelSize::new(0.65, 0.0, 2.0)).is_err());
+        
+        let voxel_size = VoxelSize::new(0.65, 0.5, 2.0);
+        let array = array.with_voxel_size(voxel_size).unwrap();
+        assert_eq!(array.pixel_size_um(), 0.65);
+        save_array(&array, &path).unwrap();
+        assert_eq!(open_array(&path).unwrap().voxel_size(), voxel_size);
+        assert_eq!(validate_file(&path).unwrap().voxel_size(), voxel_size);
+        
+        // Files without the Y size and z-step have cubic voxels
+        let mut json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        json.as_object_mut().unwrap().remove("pixel_size_y_um");
+        json.as_object_mut().unwrap().remove("z_step_um");
+        std::fs::write(&path, json.to_string()).unwrap();
+        assert_eq!(load_array(&path).unwrap().voxel_size(), VoxelSize::isotropic(0.65));
+    }
+}
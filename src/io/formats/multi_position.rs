@@ -0,0 +1,248 @@
+//! Virtual dataset of one split file per position
+//!
+//! Acquisition software often writes one `.meta`/`.data` pair per position.
+//! A directory of such files, or a JSON manifest listing them, is presented
+//! as a single TPZCYX array whose P axis runs through the files in order.
+//! Frames are read on demand from the file that holds the position; the
+//! files must agree in every other extent, voxel size and data type.
+
+use super::{load_split, open_split, read_split_frame, validate_file};
+use crate::io::array_6d::Array6D;
+use crate::io::concat::{channel_order, concatenate_parts, ConcatAxis, PartLayout};
+use crate::io::dtype::Pixel;
+use crate::io::provenance::ProvenanceEntry;
+use anyhow::{Result, anyhow};
+use ndarray::Array2;
+use serde::{Serialize, Deserialize};
+use std::path::{Path, PathBuf};
+
+/// List of the split files of a multi-position dataset, stored as JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PositionManifest {
+    /// `.meta` files in position order, relative to the manifest's directory
+    pub files: Vec<PathBuf>,
+}
+
+impl PositionManifest {
+    /// Read a manifest file
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(|e| anyhow!("Invalid position manifest {:?}: {}", path, e))
+    }
+
+    /// Write the manifest as JSON
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// List the `.meta` files of a directory in natural order, so `pos2` comes before `pos10`
+    pub fn from_directory<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("meta")) {
+                files.extend(path.file_name().map(PathBuf::from));
+            }
+        }
+        files.sort_by_cached_key(|file| natural_key(&file.to_string_lossy()));
+        Ok(Self { files })
+    }
+}
+
+/// Part of a file name for natural ordering: runs of digits compare as numbers
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum NameChunk {
+    Number(u64),
+    Text(String),
+}
+
+fn natural_key(name: &str) -> Vec<NameChunk> {
+    let mut chunks = Vec::new();
+    let mut rest = name;
+    while let Some(first) = rest.chars().next() {
+        let digits = first.is_ascii_digit();
+        let end = rest.find(|c: char| c.is_ascii_digit() != digits).unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(match chunk.parse() {
+            Ok(number) if digits => NameChunk::Number(number),
+            _ => NameChunk::Text(chunk.to_lowercase()),
+        });
+        rest = tail;
+    }
+    chunks
+}
+
+/// Check whether `path` is a directory of split files that is not an OME-Zarr store
+pub fn is_position_directory(path: &Path) -> bool {
+    path.is_dir()
+        && !super::ome_zarr::is_zarr(path)
+        && PositionManifest::from_directory(path).is_ok_and(|manifest| !manifest.files.is_empty())
+}
+
+/// Check whether `path` is a JSON file with a top-level `files` array, like a [`PositionManifest`]
+///
+/// Other JSON files, such as split headers named `.json`, are left to the
+/// other formats.
+pub fn is_position_manifest(path: &Path) -> bool {
+    path.is_file()
+        && std::fs::read_to_string(path).ok()
+            .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+            .is_some_and(|value| value.get("files").is_some_and(serde_json::Value::is_array))
+}
+
+/// Get the `.meta` files of a directory or manifest, in position order
+pub fn position_files(path: &Path) -> Result<Vec<PathBuf>> {
+    let (manifest, base) = if path.is_dir() {
+        (PositionManifest::from_directory(path)?, path)
+    } else {
+        (PositionManifest::read(path)?, path.parent().unwrap_or(Path::new("")))
+    };
+    if manifest.files.is_empty() {
+        return Err(anyhow!("No split files found in {:?}", path));
+    }
+    Ok(manifest.files.iter().map(|file| base.join(file)).collect())
+}
+
+/// Open a directory or manifest of per-position files as one array, reading frames on demand
+pub fn open_positions<T: Pixel>(path: &Path) -> Result<Array6D<T>> {
+    let parts = position_files(path)?.iter().map(|file| open_split(file)).collect::<Result<Vec<_>>>()?;
+    combine(path, &parts)
+}
+
+/// Load a directory or manifest of per-position files into memory as one array
+pub fn load_positions<T: Pixel>(path: &Path) -> Result<Array6D<T>> {
+    let parts = position_files(path)?.iter().map(|file| load_split(file)).collect::<Result<Vec<_>>>()?;
+    combine(path, &parts)
+}
+
+fn combine<T: Pixel>(path: &Path, parts: &[Array6D<T>]) -> Result<Array6D<T>> {
+    let entry = ProvenanceEntry::new("open_positions")
+        .parameter("source", path.file_name().unwrap_or_default().to_string_lossy())
+        .parameter("files", parts.len());
+    concatenate_parts(parts, ConcatAxis::Position, None, entry)
+        .map_err(|e| anyhow!("Files of {:?} do not form one dataset: {}", path, e))
+}
+
+/// Read a single 2D frame (t, p, z, c) from the file holding position `p`
+///
+/// Only the `.meta` files are parsed, and checked like [`open_positions`]
+/// does, before the frame is read from its file; files that list their
+/// channels in another order than the first have them matched by name.
+pub fn read_positions_frame<T: Pixel>(path: &Path, t: usize, p: usize, z: usize, c: usize) -> Result<Array2<T>> {
+    let files = position_files(path)?;
+    let metadata = files.iter().map(validate_file).collect::<Result<Vec<_>>>()?;
+    let layouts: Vec<PartLayout> = metadata.iter()
+        .map(|m| PartLayout { dimensions: m.dimensions, data_type: &m.data_type, voxel_size: m.voxel_size() })
+        .collect();
+    let first = &metadata[0];
+    for (i, layout) in layouts.iter().enumerate().skip(1) {
+        layout.check(i, &layouts[0], ConcatAxis::Position)
+            .map_err(|e| anyhow!("Files of {:?} do not form one dataset: {}", path, e))?;
+    }
+
+    let mut local = p;
+    for (file, part) in files.iter().zip(&metadata) {
+        if local < part.dimensions.position {
+            let c = if part.channel_names == first.channel_names {
+                c
+            } else {
+                *channel_order(&part.channel_names, &first.channel_names)?.get(c)
+                    .ok_or_else(|| anyhow!("Channel index {} out of bounds (max: {})", c, first.dimensions.channel - 1))?
+            };
+            return read_split_frame(file, t, local, z, c);
+        }
+        local -= part.dimensions.position;
+    }
+    Err(anyhow!("Position index {} out of bounds (max: {})", p, p - local - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::formats::{load_array, open_array, read_frame, save_array};
+    use crate::io::generators::ArrayGenerator;
+    use crate::io::positions::PositionMetadata;
+    use crate::io::subset::Selection;
+
+    /// Write each position of `array` to its own file in `dir`, named `pos{p}.meta`
+    fn write_positions(array: &Array6D, dir: &Path) {
+        std::fs::create_dir_all(dir).unwrap();
+        for p in 0..array.dimensions().position {
+            let part = array.select(&Selection::all().positions(p)).unwrap();
+            save_array(&part, dir.join(format!("pos{}.meta", p))).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_natural_order() {
+        let mut names = vec!["pos10.meta", "pos2.meta", "Pos1.meta", "pos2b.meta"];
+        names.sort_by_cached_key(|name| natural_key(name));
+        assert_eq!(names, ["Pos1.meta", "pos2.meta", "pos2b.meta", "pos10.meta"]);
+    }
+
+    #[test]
+    fn test_position_directory() {
+        let wells: Vec<PositionMetadata> = (0..11).map(|p| PositionMetadata::in_well("C04", p + 1)).collect();
+        let array = ArrayGenerator::generate_test_array(2, 11, 1, 2, 6, 4).unwrap().with_positions(wells).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path().join("acquisition");
+        write_positions(&array, &dir);
+        std::fs::write(dir.join("notes.txt"), "not a position").unwrap();
+
+        assert!(is_position_directory(&dir));
+        assert!(!is_position_directory(temp_dir.path()));
+        let opened = open_array(&dir).unwrap();
+        assert!(opened.is_lazy());
+        assert_eq!(opened.dimensions(), array.dimensions());
+        assert_eq!(opened.positions(), array.positions());
+        assert_eq!(opened.provenance().last().unwrap().operation, "open_positions");
+        assert_eq!(load_array(&dir).unwrap().data(), array.data());
+        for (t, p, c) in [(0, 0, 0), (1, 2, 1), (1, 10, 0)] {
+            assert_eq!(opened.get_frame(t, p, 0, c).unwrap(), array.get_frame(t, p, 0, c).unwrap());
+            assert_eq!(read_frame(&dir, t, p, 0, c).unwrap(), array.get_frame(t, p, 0, c).unwrap());
+        }
+        assert!(read_frame(&dir, 0, 11, 0, 0).is_err());
+
+        // Channels listed in another order are matched by name
+        let swapped = array.select(&Selection::all().positions(1).channels([1, 0])).unwrap();
+        save_array(&swapped, dir.join("pos1.meta")).unwrap();
+        assert_eq!(read_frame(&dir, 1, 1, 0, 0).unwrap(), array.get_frame(1, 1, 0, 0).unwrap());
+
+        // All files must have the same extents outside P
+        let shorter = array.select(&Selection::all().time(0).positions(3)).unwrap();
+        save_array(&shorter, dir.join("pos3.meta")).unwrap();
+        assert!(open_array(&dir).is_err());
+        assert!(read_frame(&dir, 0, 0, 0, 0).is_err());
+    }
+
+    #[test]
+    fn test_position_manifest() {
+        let array = ArrayGenerator::generate_test_array(2, 3, 1, 1, 6, 4).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_positions(&array, &temp_dir.path().join("runs"));
+
+        let manifest = PositionManifest { files: ["runs/pos2.meta", "runs/pos0.meta"].map(PathBuf::from).to_vec() };
+        let path = temp_dir.path().join("positions.json");
+        manifest.write(&path).unwrap();
+        assert_eq!(PositionManifest::read(&path).unwrap(), manifest);
+
+        let opened = open_array(&path).unwrap();
+        assert_eq!(opened.dimensions().position, 2);
+        assert_eq!(opened.get_frame(1, 0, 0, 0).unwrap(), array.get_frame(1, 2, 0, 0).unwrap());
+        assert_eq!(read_frame(&path, 1, 1, 0, 0).unwrap(), array.get_frame(1, 0, 0, 0).unwrap());
+
+        std::fs::write(&path, r#"{"positions": []}"#).unwrap();
+        assert!(!is_position_manifest(&path));
+        assert!(open_array(&path).is_err());
+
+        // Other JSON files are not taken for manifests
+        let split = temp_dir.path().join("runs/pos1.meta");
+        std::fs::copy(&split, temp_dir.path().join("header.json")).unwrap();
+        std::fs::copy(split.with_extension("data"), temp_dir.path().join("header.data")).unwrap();
+        let header = temp_dir.path().join("header.json");
+        assert!(!is_position_manifest(&header));
+        assert_eq!(read_frame(&header, 1, 0, 0, 0).unwrap(), array.get_frame(1, 1, 0, 0).unwrap());
+    }
+}
@@ -0,0 +1,605 @@
+//! NumPy `.npy` / `.npz` import and export
+//!
+//! A `.npy` file holds one 2D–6D array whose axes are mapped onto TPZCYX by
+//! an [`AxisSpec`] such as `"TCYX"`; axes missing from the spec get length 1.
+//! Samples may be in C or Fortran order and in either byte order. `.npz`
+//! archives hold the pixels as `data` next to side arrays carrying the axis
+//! spec, channel names, pixel size and time interval.
+
+use crate::io::array_6d::{Array6D, Dimensions, FrameSource, VoxelSize};
+use crate::io::axes::AxisSpec;
+use crate::io::dtype::{ByteOrder, Pixel, SampleEncoding};
+use anyhow::{Result, anyhow};
+use memmap2::Mmap;
+use ndarray::{Array2, ArrayD, IxDyn, ShapeBuilder};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// `.npy` magic string
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Side arrays of a `.npz` archive written by [`save_npz`]
+const NPZ_DATA: &str = "data";
+const NPZ_AXES: &str = "axes";
+const NPZ_CHANNEL_NAMES: &str = "channel_names";
+const NPZ_PIXEL_SIZE: &str = "pixel_size_um";
+const NPZ_PIXEL_SIZE_Y: &str = "pixel_size_y_um";
+const NPZ_Z_STEP: &str = "z_step_um";
+const NPZ_TIME_INTERVAL: &str = "time_interval_s";
+
+/// Check whether a path has the `.npy` extension
+pub fn is_npy(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("npy"))
+}
+
+/// Check whether a path has the `.npz` extension
+pub fn is_npz(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("npz"))
+}
+
+/// Header of a `.npy` file
+#[derive(Debug, Clone, PartialEq)]
+struct NpyHeader {
+    descr: String,
+    fortran_order: bool,
+    shape: Vec<usize>,
+}
+
+impl NpyHeader {
+    fn element_count(&self) -> usize {
+        self.shape.iter().product()
+    }
+}
+
+/// Read the header of a `.npy` stream; returns it with the total header size
+fn read_header<R: Read>(reader: &mut R) -> Result<(NpyHeader, usize)> {
+    let mut preamble = [0u8; 8];
+    reader.read_exact(&mut preamble)?;
+    if &preamble[..6] != MAGIC {
+        return Err(anyhow!("Not a NumPy .npy file"));
+    }
+
+    let (len, prefix) = match preamble[6] {
+        1 => {
+            let mut len = [0u8; 2];
+            reader.read_exact(&mut len)?;
+            (u16::from_le_bytes(len) as usize, 10)
+        }
+        2 | 3 => {
+            let mut len = [0u8; 4];
+            reader.read_exact(&mut len)?;
+            (u32::from_le_bytes(len) as usize, 12)
+        }
+        version => return Err(anyhow!("Unsupported .npy version {}", version)),
+    };
+    let mut header = vec![0u8; len];
+    reader.read_exact(&mut header)?;
+    let header = String::from_utf8(header).map_err(|_| anyhow!("Invalid .npy header"))?;
+
+    Ok((parse_header(&header)?, prefix + len))
+}
+
+/// Parse the Python dict literal of a `.npy` header
+fn parse_header(header: &str) -> Result<NpyHeader> {
+    let value = |key: &str| -> Result<&str> {
+        ["'", "\""].iter()
+            .find_map(|q| header.split_once(&format!("{q}{key}{q}")))
+            .and_then(|(_, rest)| rest.trim_start().strip_prefix(':'))
+            .map(str::trim_start)
+            .ok_or_else(|| anyhow!("Missing '{}' in .npy header", key))
+    };
+
+    let descr = value("descr")?;
+    let quote = descr.chars().next().filter(|q| *q == '\'' || *q == '"')
+        .ok_or_else(|| anyhow!("Structured .npy dtypes are not supported"))?;
+    let descr = descr[1..].split(quote).next().unwrap_or_default().to_string();
+
+    let fortran_order = value("fortran_order")?.starts_with("True");
+
+    let shape = value("shape")?;
+    let shape = shape.strip_prefix('(')
+        .and_then(|s| s.split(')').next())
+        .ok_or_else(|| anyhow!("Invalid shape in .npy header"))?;
+    let shape = shape.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.trim_end_matches('L').parse().map_err(|_| anyhow!("Invalid .npy dimension '{}'", s)))
+        .collect::<Result<_>>()?;
+
+    Ok(NpyHeader { descr, fortran_order, shape })
+}
+
+/// Write a C-order `.npy` stream
+fn write_npy<W: Write>(writer: &mut W, descr: &str, shape: &[usize], data: &[u8]) -> Result<()> {
+    let shape = match shape {
+        [n] => format!("({},)", n),
+        _ => format!("({})", shape.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ")),
+    };
+    let mut header = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", descr, shape);
+
+    // Pad with spaces so the data starts on a 64-byte boundary
+    let version_1 = header.len() + 11 < u16::MAX as usize;
+    let prefix = if version_1 { 10 } else { 12 };
+    let padding = (64 - (prefix + header.len() + 1) % 64) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    writer.write_all(MAGIC)?;
+    if version_1 {
+        writer.write_all(&[1, 0])?;
+        writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    } else {
+        writer.write_all(&[2, 0])?;
+        writer.write_all(&(header.len() as u32).to_le_bytes())?;
+    }
+    writer.write_all(header.as_bytes())?;
+    writer.write_all(data)?;
+    Ok(())
+}
+
+/// Read a complete `.npy` stream
+fn read_npy<R: Read>(reader: &mut R) -> Result<(NpyHeader, Vec<u8>)> {
+    let (header, _) = read_header(reader)?;
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    Ok((header, data))
+}
+
+/// Build a TPZCYX array from decoded `.npy` samples
+fn to_array6d<T: Pixel>(
+    header: &NpyHeader,
+    data: &[u8],
+    axes: Option<&AxisSpec>,
+    pixel_size_um: f64,
+    time_interval_s: f64,
+    channel_names: Option<Vec<String>>,
+) -> Result<Array6D<T>> {
+    let axes = match axes {
+        Some(axes) => axes.clone(),
+        None => AxisSpec::default_for(header.shape.len())?,
+    };
+    axes.check_shape(&header.shape)?;
+
+    let encoding = SampleEncoding::from_numpy(&header.descr)?;
+    let expected_bytes = header.element_count() * encoding.size();
+    if data.len() != expected_bytes {
+        return Err(anyhow!(
+            "Data size mismatch: expected {} bytes, got {}",
+            expected_bytes, data.len()
+        ));
+    }
+
+    let values = encoding.decode::<T>(data);
+    let array = ArrayD::from_shape_vec(IxDyn(&header.shape).set_f(header.fortran_order), values)?;
+    let data = axes.to_tpzcyx(array)?;
+
+    let (time, position, z, channel, height, width) = data.dim();
+    let dimensions = Dimensions { time, position, z, channel, height, width };
+    let channel_names = channel_names
+        .unwrap_or_else(|| (0..channel).map(|c| format!("Channel {}", c)).collect());
+
+    Array6D::new(
+        data,
+        dimensions,
+        pixel_size_um,
+        time_interval_s,
+        channel_names,
+        encoding.data_type.name().to_string(),
+    )
+}
+
+/// Open a `.npy` file with the given axis spec (default by dimensionality)
+///
+/// C-order files whose last two axes are Y and X are memory-mapped and read
+/// frame by frame; other layouts are loaded into memory.
+pub fn open_npy<T: Pixel, P: AsRef<Path>>(path: P, axes: Option<&AxisSpec>) -> Result<Array6D<T>> {
+    let path = path.as_ref();
+    let file = File::open(path)?;
+    let (header, header_size) = read_header(&mut BufReader::new(&file))?;
+    let axes = match axes {
+        Some(axes) => axes.clone(),
+        None => AxisSpec::default_for(header.shape.len())?,
+    };
+
+    let contiguous_frames = !header.fortran_order && axes.letters().ends_with(&['Y', 'X']);
+    if !contiguous_frames || axes.ndim() != header.shape.len() {
+        return load_npy(path, Some(&axes));
+    }
+
+    let encoding = SampleEncoding::from_numpy(&header.descr)?;
+    let dimensions = axes.dimensions(&header.shape)?;
+
+    // SAFETY: the mapping is read-only; the file must not be truncated or
+    // rewritten by another process while the array is open.
+    let mmap = unsafe { Mmap::map(&file)? };
+    let frame_size = dimensions.frame_elements() * encoding.size();
+    let expected_size = header_size + dimensions.frame_count() * frame_size;
+    if mmap.len() != expected_size {
+        return Err(anyhow!(
+            "Data file size mismatch: expected {} bytes, got {}",
+            expected_size, mmap.len()
+        ));
+    }
+
+    // Frame strides of the T, P, Z and C axes in the file's axis order
+    let leading = &axes.letters()[..axes.ndim() - 2];
+    let stride = |letter: char| -> usize {
+        leading.iter().position(|&a| a == letter).map_or(0, |i| {
+            header.shape[i + 1..axes.ndim() - 2].iter().product()
+        })
+    };
+    let frames = NpyFrames {
+        mmap,
+        header_size,
+        frame_size,
+        dimensions,
+        strides: [stride('T'), stride('P'), stride('Z'), stride('C')],
+    };
+
+    Array6D::from_source(
+        Arc::new(frames),
+        encoding,
+        dimensions,
+        1.0,
+        1.0,
+        (0..dimensions.channel).map(|c| format!("Channel {}", c)).collect(),
+        encoding.data_type.name().to_string(),
+    )
+}
+
+/// Memory-mapped C-order `.npy` file with contiguous YX frames
+#[derive(Debug)]
+struct NpyFrames {
+    mmap: Mmap,
+    header_size: usize,
+    frame_size: usize,
+    dimensions: Dimensions,
+    /// Frame strides of the T, P, Z and C axes
+    strides: [usize; 4],
+}
+
+impl FrameSource for NpyFrames {
+    fn frame_bytes(&self, index: usize) -> Result<Cow<'_, [u8]>> {
+        let (t, p, z, c) = self.dimensions.frame_coords(index);
+        let [st, sp, sz, sc] = self.strides;
+        let start = self.header_size + (t * st + p * sp + z * sz + c * sc) * self.frame_size;
+        Ok(Cow::Borrowed(&self.mmap[start..start + self.frame_size]))
+    }
+}
+
+/// Load a `.npy` file into memory with the given axis spec (default by dimensionality)
+pub fn load_npy<T: Pixel, P: AsRef<Path>>(path: P, axes: Option<&AxisSpec>) -> Result<Array6D<T>> {
+    let (header, data) = read_npy(&mut BufReader::new(File::open(path)?))?;
+    to_array6d(&header, &data, axes, 1.0, 1.0, None)
+}
+
+/// Save a 6D array as a C-order TPZCYX `.npy` file in its native dtype
+pub fn save_npy<T: Pixel, P: AsRef<Path>>(array: &Array6D<T>, path: P) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let (descr, shape, data) = encode_array(array)?;
+    write_npy(&mut writer, &descr, &shape, &data)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Encode the samples of an array as little-endian TPZCYX bytes
+fn encode_array<T: Pixel>(array: &Array6D<T>) -> Result<(String, Vec<usize>, Vec<u8>)> {
+    let dims = array.dimensions();
+    let encoding = SampleEncoding::new(array.dtype(), ByteOrder::Little);
+    let mut data = Vec::with_capacity(array.memory_usage());
+    for t in 0..dims.time {
+        for p in 0..dims.position {
+            for z in 0..dims.z {
+                for c in 0..dims.channel {
+                    encoding.encode(array.get_frame(t, p, z, c)?.iter(), &mut data);
+                }
+            }
+        }
+    }
+    let shape = vec![dims.time, dims.position, dims.z, dims.channel, dims.height, dims.width];
+    Ok((encoding.numpy_descr(), shape, data))
+}
+
+/// Encode strings as a fixed-width little-endian unicode (`<U`) array
+fn encode_strings(values: &[String]) -> (String, Vec<u8>) {
+    let width = values.iter().map(|v| v.chars().count()).max().unwrap_or(0).max(1);
+    let mut data = Vec::with_capacity(values.len() * width * 4);
+    for value in values {
+        let chars: Vec<char> = value.chars().collect();
+        for i in 0..width {
+            data.extend_from_slice(&chars.get(i).map_or(0, |&c| c as u32).to_le_bytes());
+        }
+    }
+    (format!("<U{}", width), data)
+}
+
+/// Decode a unicode (`<U`, `>U`) or bytes (`|S`) array into strings
+fn decode_strings(header: &NpyHeader, data: &[u8]) -> Result<Vec<String>> {
+    let descr = header.descr.as_str();
+    let (order, kind, width) = match (descr.get(..1), descr.get(1..2), descr.get(2..).and_then(|w| w.parse::<usize>().ok())) {
+        (Some(order), Some(kind), Some(width)) if width > 0 => (order, kind, width),
+        _ => return Err(anyhow!("Expected a string array, got dtype '{}'", descr)),
+    };
+    let elem_size = match kind {
+        "U" => width * 4,
+        "S" => width,
+        _ => return Err(anyhow!("Expected a string array, got dtype '{}'", descr)),
+    };
+    if data.len() != header.element_count() * elem_size {
+        return Err(anyhow!("String array size does not match its shape {:?}", header.shape));
+    }
+
+    Ok(data.chunks_exact(elem_size)
+        .map(|element| match kind {
+            "U" => element.chunks_exact(4)
+                .map(|b| {
+                    let b = [b[0], b[1], b[2], b[3]];
+                    if order == ">" { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) }
+                })
+                .take_while(|&c| c != 0)
+                .map(|c| char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect(),
+            _ => {
+                let end = element.iter().position(|&b| b == 0).unwrap_or(element.len());
+                String::from_utf8_lossy(&element[..end]).into_owned()
+            }
+        })
+        .collect())
+}
+
+/// Decode a numeric scalar (or one-element array) as f64
+fn decode_scalar(header: &NpyHeader, data: &[u8]) -> Result<f64> {
+    let values = SampleEncoding::from_numpy(&header.descr)?.decode::<f64>(data);
+    match values.as_slice() {
+        [value] => Ok(*value),
+        _ => Err(anyhow!("Expected a scalar, got shape {:?}", header.shape)),
+    }
+}
+
+/// Load a `.npz` archive written by [`save_npz`] or by NumPy
+///
+/// The pixels come from `data` (or the only array in the archive). The axis
+/// spec defaults to the stored `axes` array, then to the dimensionality;
+/// `channel_names`, `pixel_size_um`, `pixel_size_y_um`, `z_step_um` and
+/// `time_interval_s` are optional.
+pub fn load_npz<T: Pixel, P: AsRef<Path>>(path: P, axes: Option<&AxisSpec>) -> Result<Array6D<T>> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+    let mut entry = |name: &str| -> Result<Option<(NpyHeader, Vec<u8>)>> {
+        match archive.by_name(&format!("{}.npy", name)) {
+            Ok(mut file) => Ok(Some(read_npy(&mut file)?)),
+            Err(zip::result::ZipError::FileNotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    };
+
+    let stored_axes = entry(NPZ_AXES)?
+        .map(|(h, d)| decode_strings(&h, &d)?.into_iter().next().ok_or_else(|| anyhow!("Empty axes array"))?.parse::<AxisSpec>())
+        .transpose()?;
+    let channel_names = entry(NPZ_CHANNEL_NAMES)?.map(|(h, d)| decode_strings(&h, &d)).transpose()?;
+    let pixel_size_um = entry(NPZ_PIXEL_SIZE)?.map(|(h, d)| decode_scalar(&h, &d)).transpose()?;
+    let pixel_size_y_um = entry(NPZ_PIXEL_SIZE_Y)?.map(|(h, d)| decode_scalar(&h, &d)).transpose()?;
+    let z_step_um = entry(NPZ_Z_STEP)?.map(|(h, d)| decode_scalar(&h, &d)).transpose()?;
+    let time_interval_s = entry(NPZ_TIME_INTERVAL)?.map(|(h, d)| decode_scalar(&h, &d)).transpose()?;
+
+    let (header, data) = match entry(NPZ_DATA)? {
+        Some(data) => data,
+        None => {
+            let side_arrays = [NPZ_AXES, NPZ_CHANNEL_NAMES, NPZ_PIXEL_SIZE, NPZ_PIXEL_SIZE_Y, NPZ_Z_STEP, NPZ_TIME_INTERVAL];
+            let names: Vec<String> = archive.file_names()
+                .filter_map(|n| n.strip_suffix(".npy"))
+                .filter(|n| !side_arrays.contains(n))
+                .map(str::to_string)
+                .collect();
+            match names.as_slice() {
+                [name] => {
+                    let mut file = archive.by_name(&format!("{}.npy", name))?;
+                    read_npy(&mut file)?
+                }
+                _ => return Err(anyhow!("Archive has no '{}' array and {} other arrays", NPZ_DATA, names.len())),
+            }
+        }
+    };
+
+    let x_um = pixel_size_um.unwrap_or(1.0);
+    let voxel_size = VoxelSize::new(x_um, pixel_size_y_um.unwrap_or(x_um), z_step_um.unwrap_or(x_um));
+    to_array6d(
+        &header,
+        &data,
+        axes.or(stored_axes.as_ref()),
+        x_um,
+        time_interval_s.unwrap_or(1.0),
+        channel_names,
+    )?
+    .with_voxel_size(voxel_size)
+}
+
+/// Save a 6D array as a `.npz` archive with its metadata as side arrays
+///
+/// `np.load(path)` gives `data` (TPZCYX), `axes`, `channel_names`,
+/// `pixel_size_um`, `pixel_size_y_um`, `z_step_um` and `time_interval_s`.
+pub fn save_npz<T: Pixel, P: AsRef<Path>>(array: &Array6D<T>, path: P) -> Result<()> {
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(path)?));
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(array.memory_usage() > u32::MAX as usize / 2);
+
+    let mut add = |name: &str, descr: &str, shape: &[usize], data: &[u8]| -> Result<()> {
+        zip.start_file(format!("{}.npy", name), options)?;
+        write_npy(&mut zip, descr, shape, data)
+    };
+
+    let (descr, shape, data) = encode_array(array)?;
+    add(NPZ_DATA, &descr, &shape, &data)?;
+
+    let axes = ["TPZCYX".to_string()];
+    let (descr, data) = encode_strings(&axes);
+    add(NPZ_AXES, &descr, &[], &data)?;
+
+    let names = array.channel_names();
+    let (descr, data) = encode_strings(names);
+    add(NPZ_CHANNEL_NAMES, &descr, &[names.len()], &data)?;
+
+    let voxel_size = array.voxel_size();
+    add(NPZ_PIXEL_SIZE, "<f8", &[], &voxel_size.x_um.to_le_bytes())?;
+    add(NPZ_PIXEL_SIZE_Y, "<f8", &[], &voxel_size.y_um.to_le_bytes())?;
+    add(NPZ_Z_STEP, "<f8", &[], &voxel_size.z_um.to_le_bytes())?;
+    add(NPZ_TIME_INTERVAL, "<f8", &[], &array.time_interval_s().to_le_bytes())?;
+
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+/// Read a single 2D frame (t, p, z, c) from a `.npy` or `.npz` file
+pub fn read_npy_frame<T: Pixel, P: AsRef<Path>>(path: P, t: usize, p: usize, z: usize, c: usize) -> Result<Array2<T>> {
+    let path = path.as_ref();
+    let array = if is_npz(path) { load_npz(path, None)? } else { open_npy(path, None)? };
+    Ok(array.get_frame(t, p, z, c)?.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::dtype::DataType;
+    use crate::io::generators::ArrayGenerator;
+
+    /// Write a `.npy` file with explicit header fields
+    fn write_raw_npy(path: &Path, descr: &str, fortran_order: bool, shape: &str, data: &[u8]) {
+        let header = format!("{{'descr': '{}', 'fortran_order': {}, 'shape': {}, }}\n", descr, if fortran_order { "True" } else { "False" }, shape);
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 0]);
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(data);
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let header = parse_header("{'descr': '<u2', 'fortran_order': False, 'shape': (3, 4), }").unwrap();
+        assert_eq!(header, NpyHeader { descr: "<u2".to_string(), fortran_order: false, shape: vec![3, 4] });
+
+        let header = parse_header("{\"shape\": (5,), \"fortran_order\": True, \"descr\": \">f8\"}").unwrap();
+        assert_eq!(header.shape, vec![5]);
+        assert!(header.fortran_order);
+        assert_eq!(header.descr, ">f8");
+
+        assert_eq!(parse_header("{'descr': '<f8', 'fortran_order': False, 'shape': (), }").unwrap().shape, Vec::<usize>::new());
+        assert!(parse_header("{'descr': [('a', '<u2')], 'fortran_order': False, 'shape': (2,), }").is_err());
+    }
+
+    #[test]
+    fn test_npy_roundtrip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = ArrayGenerator::generate_test_array(3, 2, 2, 2, 5, 4).unwrap();
+
+        for dtype in [DataType::UInt8, DataType::UInt16, DataType::UInt32, DataType::Float32, DataType::Float64] {
+            let array = Array6D::new(
+                source.data().unwrap().mapv(|v| dtype.quantize(v)),
+                *source.dimensions(),
+                1.0,
+                1.0,
+                vec!["Channel 0".to_string(), "Channel 1".to_string()],
+                dtype.name().to_string(),
+            ).unwrap();
+            let path = temp_dir.path().join(format!("{}.npy", dtype));
+            save_npy(&array, &path).unwrap();
+
+            // The data starts on a 64-byte boundary
+            let size = std::fs::metadata(&path).unwrap().len() as usize;
+            assert_eq!((size - array.memory_usage()) % 64, 0);
+
+            let loaded = load_npy::<f32, _>(&path, None).unwrap();
+            assert_eq!(loaded.data_type(), dtype.name());
+            assert_eq!(loaded.data(), array.data());
+
+            let opened = open_npy::<f32, _>(&path, None).unwrap();
+            assert!(opened.is_lazy());
+            assert_eq!(opened.get_frame(2, 1, 1, 0).unwrap(), array.get_frame(2, 1, 1, 0).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_axis_mapping() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        // A C-order (C=2, T=3, Y=2, X=4) uint16 array with value c*1000 + t*100 + y*10 + x
+        let mut data = Vec::new();
+        for c in 0..2u16 {
+            for t in 0..3u16 {
+                for y in 0..2u16 {
+                    for x in 0..4u16 {
+                        data.extend_from_slice(&(c * 1000 + t * 100 + y * 10 + x).to_le_bytes());
+                    }
+                }
+            }
+        }
+        let path = temp_dir.path().join("ctyx.npy");
+        write_raw_npy(&path, "<u2", false, "(2, 3, 2, 4)", &data);
+
+        let axes: AxisSpec = "CTYX".parse().unwrap();
+        for array in [load_npy::<u16, _>(&path, Some(&axes)).unwrap(), open_npy::<u16, _>(&path, Some(&axes)).unwrap()] {
+            assert_eq!(*array.dimensions(), Dimensions::new(3, 1, 1, 2, 2, 4));
+            assert_eq!(array.get_frame(2, 0, 0, 1).unwrap()[[1, 3]], 1213);
+            assert_eq!(array.get_frame(1, 0, 0, 0).unwrap()[[0, 2]], 102);
+        }
+        assert!(load_npy::<u16, _>(&path, Some(&"TYX".parse().unwrap())).is_err());
+
+        // The same values in Fortran order, big-endian, with X before Y
+        let mut data = Vec::new();
+        for y in 0..2u16 {
+            for x in 0..4u16 {
+                for t in 0..3u16 {
+                    for c in 0..2u16 {
+                        data.extend_from_slice(&(c * 1000 + t * 100 + y * 10 + x).to_be_bytes());
+                    }
+                }
+            }
+        }
+        let path = temp_dir.path().join("ctxy_fortran.npy");
+        write_raw_npy(&path, ">u2", true, "(2, 3, 4, 2)", &data);
+        let array = open_npy::<u16, _>(&path, Some(&"CTXY".parse().unwrap())).unwrap();
+        assert!(!array.is_lazy());
+        assert_eq!(array.get_frame(2, 0, 0, 1).unwrap()[[1, 3]], 1213);
+        assert_eq!(array.get_frame(1, 0, 0, 0).unwrap()[[0, 2]], 102);
+    }
+
+    #[test]
+    fn test_npz_metadata() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let array = ArrayGenerator::generate_test_array(2, 2, 1, 2, 4, 3).unwrap();
+        let array = Array6D::new(
+            array.data().unwrap().clone(),
+            *array.dimensions(),
+            0.108,
+            600.0,
+            vec!["Phase".to_string(), "mNeonGreen µ".to_string()],
+            array.data_type().to_string(),
+        ).unwrap()
+        .with_voxel_size(VoxelSize::new(0.108, 0.108, 0.5)).unwrap();
+        let path = temp_dir.path().join("handoff.npz");
+        save_npz(&array, &path).unwrap();
+
+        let loaded = load_npz::<f32, _>(&path, None).unwrap();
+        assert_eq!(loaded.data(), array.data());
+        assert_eq!(loaded.channel_names(), array.channel_names());
+        assert_eq!(loaded.voxel_size(), array.voxel_size());
+        assert_eq!(loaded.time_interval_s(), 600.0);
+        assert_eq!(read_npy_frame::<f32, _>(&path, 1, 1, 0, 1).unwrap(), array.get_frame(1, 1, 0, 1).unwrap());
+
+        // A plain np.savez archive with a single unnamed array
+        let plain = temp_dir.path().join("plain.npz");
+        let mut zip = ZipWriter::new(File::create(&plain).unwrap());
+        zip.start_file("arr_0.npy", SimpleFileOptions::default().compression_method(CompressionMethod::Deflated)).unwrap();
+        write_npy(&mut zip, "|u1", &[2, 3], &[1, 2, 3, 4, 5, 6]).unwrap();
+        zip.finish().unwrap();
+        let loaded = load_npz::<u8, _>(&plain, None).unwrap();
+        assert_eq!(*loaded.dimensions(), Dimensions::new(1, 1, 1, 1, 2, 3));
+        assert_eq!(loaded.get_frame(0, 0, 0, 0).unwrap()[[1, 2]], 6);
+        assert_eq!(loaded.voxel_size(), VoxelSize::isotropic(1.0));
+    }
+}
@@ -0,0 +1,1038 @@
+//! OME-TIFF import and export
+//!
+//! The OME-XML block in the ImageDescription of the first IFD describes the
+//! size, dimension order and pixel type of every OME `Image` (series) in the
+//! file, and which IFD holds each plane. Planes are mapped onto TPZCYX with
+//! one position per series and decoded on demand. ImageJ hyperstacks (see
+//! [`super::imagej`]) are read and written through the same functions.
+
+use super::imagej;
+use crate::io::array_6d::{Array6D, Dimensions, FrameSource, VoxelSize};
+use crate::io::channels::{ChannelMetadata, ChannelRole, Color};
+use crate::io::dtype::{ByteOrder, DataType, Pixel, SampleEncoding};
+use crate::io::positions::PositionMetadata;
+use anyhow::{Result, anyhow};
+use ndarray::{Array2, ArrayView2};
+use roxmltree::{Document, Node};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::encoder::{colortype, Rational, TiffEncoder, TiffKind};
+use tiff::tags::{ResolutionUnit, Tag};
+use tiff::ColorType;
+
+/// Largest file written as classic TIFF; bigger OME-TIFF files use BigTIFF
+const CLASSIC_TIFF_LIMIT: usize = 4_000_000_000;
+
+/// Metadata flavour written by [`save_tiff`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TiffFlavor {
+    /// OME-XML in the ImageDescription
+    #[default]
+    Ome,
+    /// ImageJ hyperstack description and slice labels
+    ImageJ,
+}
+
+/// Placement of positions written by [`save_tiff`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PositionLayout {
+    /// One file with one OME series per position
+    #[default]
+    Series,
+    /// One file per position, named `<name>_p<index>.ome.tif`
+    Files,
+}
+
+/// Check whether a path has a TIFF extension (`.tif`/`.tiff`, including `.ome.tif`)
+pub fn is_tiff(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case("tif") || ext.eq_ignore_ascii_case("tiff"))
+        .unwrap_or(false)
+}
+
+/// Array layout described by the OME-XML (or ImageJ metadata) of a TIFF file
+#[derive(Debug, Clone, PartialEq)]
+pub struct OmeMetadata {
+    pub dimensions: Dimensions,
+    pub voxel_size: VoxelSize,
+    pub time_interval_s: f64,
+    pub channel_names: Vec<String>,
+    /// Wavelengths, colours and contrast methods of the channels; empty if none are given
+    pub channels: Vec<ChannelMetadata>,
+    /// Stage coordinates of the series; empty if none has a StageLabel
+    pub positions: Vec<PositionMetadata>,
+    pub data_type: DataType,
+    /// IFD holding each frame, in TPZCYX frame order
+    pub plane_ifds: Vec<usize>,
+}
+
+/// Read the OME-XML (or ImageJ) metadata of a TIFF file
+pub fn read_ome_metadata<P: AsRef<Path>>(path: P) -> Result<OmeMetadata> {
+    let path = path.as_ref();
+    let mut decoder = Decoder::new(BufReader::new(File::open(path)?))?;
+    let metadata = read_ome_xml(&mut decoder, path)?;
+    Ok(metadata)
+}
+
+/// Open an OME-TIFF file; frames are decoded from the file on access
+pub fn open_ome_tiff<T: Pixel, P: AsRef<Path>>(path: P) -> Result<Array6D<T>> {
+    let path = path.as_ref();
+    let mut decoder = Decoder::new(BufReader::new(File::open(path)?))?;
+    let metadata = read_ome_xml(&mut decoder, path)?;
+
+    let encoding = SampleEncoding::new(metadata.data_type, ByteOrder::Little);
+    let frames = OmeTiffFrames {
+        decoder: Mutex::new(decoder),
+        plane_ifds: metadata.plane_ifds,
+        width: metadata.dimensions.width,
+        height: metadata.dimensions.height,
+        encoding,
+    };
+
+    Array6D::from_source(
+        Arc::new(frames),
+        encoding,
+        metadata.dimensions,
+        metadata.voxel_size.x_um,
+        metadata.time_interval_s,
+        metadata.channel_names,
+        metadata.data_type.name().to_string(),
+    )?
+    .with_voxel_size(metadata.voxel_size)?
+    .with_channels(metadata.channels)?
+    .with_positions(metadata.positions)
+}
+
+/// Load an OME-TIFF file into memory
+pub fn load_ome_tiff<T: Pixel, P: AsRef<Path>>(path: P) -> Result<Array6D<T>> {
+    open_ome_tiff(path)?.materialize()
+}
+
+/// Read a single 2D frame (t, p, z, c) from an OME-TIFF file
+pub fn read_ome_tiff_frame<T: Pixel, P: AsRef<Path>>(path: P, t: usize, p: usize, z: usize, c: usize) -> Result<Array2<T>> {
+    Ok(open_ome_tiff(path)?.get_frame(t, p, z, c)?.into_owned())
+}
+
+/// Read and parse the OME-XML or ImageJ description stored in the first IFD
+fn read_ome_xml(decoder: &mut Decoder<BufReader<File>>, path: &Path) -> Result<OmeMetadata> {
+    let description = decoder.get_tag_ascii_string(Tag::ImageDescription)
+        .map_err(|_| anyhow!("No OME-XML or ImageJ metadata found in {:?}", path))?;
+
+    if imagej::is_imagej_description(&description) {
+        // ImageJ writes big-endian ("MM") files itself
+        let mut header = [0u8; 2];
+        File::open(path)?.read_exact(&mut header)?;
+        return imagej::read_metadata(decoder, &description, &header == b"MM");
+    }
+
+    parse_ome_xml(&description)
+}
+
+/// Save a 6D array as OME-TIFF or ImageJ hyperstack
+///
+/// Returns the written files: `path` itself for [`PositionLayout::Series`],
+/// or one file per position for [`PositionLayout::Files`]. ImageJ has no
+/// notion of series, so multi-position arrays need the `Files` layout.
+pub fn save_tiff<T: Pixel, P: AsRef<Path>>(
+    array: &Array6D<T>,
+    path: P,
+    flavor: TiffFlavor,
+    layout: PositionLayout,
+) -> Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    let positions = array.dimensions().position;
+
+    match layout {
+        PositionLayout::Series => {
+            if flavor == TiffFlavor::ImageJ && positions > 1 {
+                return Err(anyhow!(
+                    "ImageJ hyperstacks hold a single position; save {} positions as separate files",
+                    positions
+                ));
+            }
+            write_tiff_file(array, path, flavor, 0..positions)?;
+            Ok(vec![path.to_path_buf()])
+        }
+        PositionLayout::Files => (0..positions)
+            .map(|p| {
+                let file = position_path(path, p);
+                write_tiff_file(array, &file, flavor, p..p + 1)?;
+                Ok(file)
+            })
+            .collect(),
+    }
+}
+
+/// Get the file name of position `p`: `stack.ome.tif` becomes `stack_p2.ome.tif`
+fn position_path(path: &Path, p: usize) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let lower = name.to_lowercase();
+    let suffix_len = [".ome.tiff", ".ome.tif"].iter()
+        .find(|suffix| lower.ends_with(*suffix))
+        .map(|suffix| suffix.len())
+        .or_else(|| name.rfind('.').map(|dot| name.len() - dot))
+        .unwrap_or(0);
+    let (stem, suffix) = name.split_at(name.len() - suffix_len);
+    path.with_file_name(format!("{}_p{}{}", stem, p, suffix))
+}
+
+/// Tags written to the first page only
+struct FirstPage {
+    description: String,
+    /// X and Y pixels per micrometer (ImageJ)
+    resolution: Option<(Rational, Rational)>,
+    /// IJMetadata byte counts and blocks (ImageJ)
+    ij_metadata: Option<(Vec<u32>, Vec<u8>)>,
+}
+
+/// Write the given positions of an array into one TIFF file
+fn write_tiff_file<T: Pixel>(array: &Array6D<T>, path: &Path, flavor: TiffFlavor, positions: Range<usize>) -> Result<()> {
+    let dims = array.dimensions();
+    let data_type = array.dtype();
+    let planes = positions.len() * dims.time * dims.z * dims.channel;
+    let data_size = planes * dims.frame_elements() * data_type.size();
+
+    let first_page = match flavor {
+        TiffFlavor::Ome => {
+            FirstPage { description: ome_xml(array, positions.clone()), resolution: None, ij_metadata: None }
+        }
+        TiffFlavor::ImageJ => {
+            if !matches!(data_type, DataType::UInt8 | DataType::UInt16 | DataType::Float32) {
+                return Err(anyhow!("ImageJ hyperstacks support uint8, uint16 and float32 data, not {}", data_type));
+            }
+            if data_size > CLASSIC_TIFF_LIMIT {
+                return Err(anyhow!("ImageJ hyperstacks are limited to 4 GB, got {} bytes", data_size));
+            }
+
+            let voxel_size = array.voxel_size();
+            let resolution = (pixels_per_um(voxel_size.x_um), pixels_per_um(voxel_size.y_um));
+            let labels: Vec<String> = (0..dims.time * dims.z)
+                .flat_map(|_| array.channel_names().iter().cloned())
+                .collect();
+            FirstPage {
+                description: imagej::description(dims, voxel_size.z_um, array.time_interval_s()),
+                resolution: Some(resolution),
+                ij_metadata: Some(imagej::encode_labels(&labels)),
+            }
+        }
+    };
+
+    let file = BufWriter::new(File::create(path)?);
+    if data_size > CLASSIC_TIFF_LIMIT {
+        write_pages(TiffEncoder::new_big(file)?, array, positions, first_page)
+    } else {
+        write_pages(TiffEncoder::new(file)?, array, positions, first_page)
+    }
+}
+
+/// Get a rational of pixels per micrometer that reads back as the exact pixel size
+///
+/// Pixel sizes below 1e-6 µm come out as 1e-6 µm rather than a zero denominator.
+fn pixels_per_um(pixel_size_um: f64) -> Rational {
+    let scale = (u32::MAX as f64 / pixel_size_um).min(1e6).floor();
+    Rational { n: scale as u32, d: ((pixel_size_um * scale).round() as u32).max(1) }
+}
+
+/// Write one page per plane, positions first, then T, Z and C (XYCZT order)
+fn write_pages<W: Write + Seek, K: TiffKind, T: Pixel>(
+    mut tiff: TiffEncoder<W, K>,
+    array: &Array6D<T>,
+    positions: Range<usize>,
+    first_page: FirstPage,
+) -> Result<()> {
+    let dims = array.dimensions();
+    let mut first_page = Some(first_page);
+    for p in positions {
+        for t in 0..dims.time {
+            for z in 0..dims.z {
+                for c in 0..dims.channel {
+                    let frame = array.get_frame(t, p, z, c)?;
+                    let first = first_page.take();
+                    match array.dtype() {
+                        DataType::UInt8 => write_page::<colortype::Gray8, _, _, _>(&mut tiff, frame.view(), first)?,
+                        DataType::UInt16 => write_page::<colortype::Gray16, _, _, _>(&mut tiff, frame.view(), first)?,
+                        DataType::UInt32 => write_page::<colortype::Gray32, _, _, _>(&mut tiff, frame.view(), first)?,
+                        DataType::Float32 => write_page::<colortype::Gray32Float, _, _, _>(&mut tiff, frame.view(), first)?,
+                        DataType::Float64 => write_page::<colortype::Gray64Float, _, _, _>(&mut tiff, frame.view(), first)?,
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write one plane as a page with samples of colour type `C`
+fn write_page<C, W, K, T>(tiff: &mut TiffEncoder<W, K>, frame: ArrayView2<T>, first_page: Option<FirstPage>) -> Result<()>
+where
+    C: tiff::encoder::colortype::ColorType,
+    C::Inner: Pixel,
+    [C::Inner]: tiff::encoder::TiffValue,
+    W: Write + Seek,
+    K: TiffKind,
+    T: Pixel,
+{
+    let (height, width) = frame.dim();
+    let mut image = tiff.new_image::<C>(width as u32, height as u32)?;
+    if let Some(first) = first_page {
+        image.encoder().write_tag(Tag::ImageDescription, first.description.as_str())?;
+        if let Some((x_resolution, y_resolution)) = first.resolution {
+            image.resolution_unit(ResolutionUnit::None);
+            image.x_resolution(x_resolution);
+            image.y_resolution(y_resolution);
+        }
+        if let Some((counts, data)) = first.ij_metadata {
+            image.encoder().write_tag(Tag::Unknown(imagej::IJ_METADATA_BYTE_COUNTS), counts.as_slice())?;
+            image.encoder().write_tag(Tag::Unknown(imagej::IJ_METADATA), data.as_slice())?;
+        }
+    }
+
+    let samples: Vec<C::Inner> = frame.iter().map(|v| C::Inner::from_f64(v.to_f64())).collect();
+    image.write_data(&samples)?;
+    Ok(())
+}
+
+/// Build the OME-XML for the given positions, one series each
+///
+/// Every file holds all of its planes, so TiffData carries no UUID: it
+/// refers to the file it is in.
+fn ome_xml<T: Pixel>(array: &Array6D<T>, positions: Range<usize>) -> String {
+    let dims = array.dimensions();
+    let planes = dims.time * dims.z * dims.channel;
+    let pixel_type = match array.dtype() {
+        DataType::Float32 => "float",
+        DataType::Float64 => "double",
+        integer => integer.name(),
+    };
+
+    let mut xml = String::from(concat!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#, "\n",
+        r#"<OME xmlns="http://www.openmicroscopy.org/Schemas/OME/2016-06" Creator="pyama-rust">"#, "\n",
+    ));
+    for (series, p) in positions.enumerate() {
+        let position = array.positions().get(p);
+        let name = position.and_then(PositionMetadata::label).unwrap_or_else(|| format!("Position {}", p));
+        xml += &format!("  <Image ID=\"Image:{}\" Name=\"{}\">\n", series, escape_xml(&name));
+        if let Some(stage) = position.map(stage_label_attributes).filter(|a| !a.is_empty()) {
+            xml += &format!("    <StageLabel Name=\"{}\"{}/>\n", escape_xml(&name), stage);
+        }
+        xml += &format!(
+            concat!(
+                r#"    <Pixels ID="Pixels:{series}" DimensionOrder="XYCZT" Type="{pixel_type}" BigEndian="false""#,
+                r#" SizeX="{width}" SizeY="{height}" SizeZ="{z}" SizeC="{c}" SizeT="{t}""#,
+                r#" PhysicalSizeX="{px}" PhysicalSizeXUnit="&#181;m" PhysicalSizeY="{py}" PhysicalSizeYUnit="&#181;m""#,
+                r#" PhysicalSizeZ="{pz}" PhysicalSizeZUnit="&#181;m""#,
+                r#" TimeIncrement="{dt}" TimeIncrementUnit="s">"#, "\n",
+            ),
+            series = series, pixel_type = pixel_type,
+            width = dims.width, height = dims.height, z = dims.z, c = dims.channel, t = dims.time,
+            px = array.voxel_size().x_um, py = array.voxel_size().y_um, pz = array.voxel_size().z_um,
+            dt = array.time_interval_s(),
+        );
+        for (c, name) in array.channel_names().iter().enumerate() {
+            xml += &format!(
+                "      <Channel ID=\"Channel:{}:{}\" Name=\"{}\" SamplesPerPixel=\"1\"{}/>\n",
+                series, c, escape_xml(name),
+                array.channels().get(c).map(channel_attributes).unwrap_or_default()
+            );
+        }
+        xml += &format!(
+            "      <TiffData IFD=\"{}\" PlaneCount=\"{}\"/>\n",
+            series * planes, planes
+        );
+        xml += "    </Pixels>\n  </Image>\n";
+    }
+    xml + "</OME>\n"
+}
+
+/// Build the OME-XML attributes of the settings a `Channel` element can hold
+///
+/// Gain, offset and exposure belong to instrument and plane elements that
+/// are not written, so they are only kept by the split format.
+fn channel_attributes(channel: &ChannelMetadata) -> String {
+    let mut attributes = String::new();
+    if let Some(color) = channel.color {
+        attributes += &format!(" Color=\"{}\"", color.to_ome());
+    }
+    if let Some(nm) = channel.excitation_nm {
+        attributes += &format!(" ExcitationWavelength=\"{}\" ExcitationWavelengthUnit=\"nm\"", nm);
+    }
+    if let Some(nm) = channel.emission_nm {
+        attributes += &format!(" EmissionWavelength=\"{}\" EmissionWavelengthUnit=\"nm\"", nm);
+    }
+    match channel.role {
+        Some(ChannelRole::Phase) => attributes += " ContrastMethod=\"Phase\"",
+        Some(ChannelRole::Fluorescence) => attributes += " ContrastMethod=\"Fluorescence\"",
+        Some(ChannelRole::Label) | None => {}
+    }
+    attributes
+}
+
+/// Build the OME-XML attributes of a `StageLabel` element from stage coordinates
+///
+/// Wells and fields have no place in a plain OME `Image`; they only name it.
+fn stage_label_attributes(position: &PositionMetadata) -> String {
+    [("X", position.stage_x_um), ("Y", position.stage_y_um), ("Z", position.stage_z_um)]
+        .iter()
+        .filter_map(|(axis, value)| value.map(|v| format!(" {axis}=\"{v}\" {axis}Unit=\"&#181;m\"")))
+        .collect()
+}
+
+/// Read the stage coordinates of an OME `StageLabel` element
+fn parse_stage_label(label: Node) -> Result<PositionMetadata> {
+    let coordinate = |axis: &str| -> Result<Option<f64>> {
+        label.attribute(axis)
+            .map(|value| {
+                // The default unit is the stage's reference frame, taken as µm
+                let unit = label.attribute(format!("{}Unit", axis).as_str()).unwrap_or("µm");
+                length_in_um(parse_f64(axis, value)?, unit)
+            })
+            .transpose()
+    };
+    Ok(PositionMetadata {
+        stage_x_um: coordinate("X")?,
+        stage_y_um: coordinate("Y")?,
+        stage_z_um: coordinate("Z")?,
+        ..Default::default()
+    })
+}
+
+/// Read the settings of an OME `Channel` element
+fn parse_channel(channel: Node) -> Result<ChannelMetadata> {
+    let wavelength_nm = |name: &str| -> Result<Option<f64>> {
+        channel.attribute(name)
+            .map(|value| {
+                let unit = channel.attribute(format!("{}Unit", name).as_str()).unwrap_or("nm");
+                Ok(length_in_um(parse_f64(name, value)?, unit)? * 1e3)
+            })
+            .transpose()
+    };
+    let color = channel.attribute("Color")
+        .map(|value| value.parse().map(Color::from_ome).map_err(|_| anyhow!("Invalid Color '{}'", value)))
+        .transpose()?;
+    let role = match channel.attribute("ContrastMethod") {
+        Some("Phase" | "Brightfield" | "DIC" | "Hoffman") => Some(ChannelRole::Phase),
+        Some("Fluorescence") => Some(ChannelRole::Fluorescence),
+        _ => None,
+    };
+    let detector = channel.children().find(|n| n.has_tag_name("DetectorSettings"));
+    let setting = |name: &str| -> Result<Option<f64>> {
+        detector.and_then(|d| d.attribute(name)).map(|value| parse_f64(name, value)).transpose()
+    };
+
+    Ok(ChannelMetadata {
+        excitation_nm: wavelength_nm("ExcitationWavelength")?,
+        emission_nm: wavelength_nm("EmissionWavelength")?,
+        gain: setting("Gain")?,
+        camera_offset: setting("Offset")?,
+        color,
+        role,
+        ..Default::default()
+    })
+}
+
+/// Escape text for use in an XML attribute
+///
+/// Non-ASCII characters become character references because TIFF ASCII tags
+/// cannot hold UTF-8.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            ch if ch.is_ascii() => escaped.push(ch),
+            ch => escaped.push_str(&format!("&#{};", ch as u32)),
+        }
+    }
+    escaped
+}
+
+/// Parse OME-XML into a TPZCYX layout with one position per series
+///
+/// TiffData entries may name the file holding their planes; as long as
+/// they all name the same one it is taken to be this file, which may have
+/// been renamed since it was written. Datasets spread over several files
+/// are rejected.
+pub fn parse_ome_xml(xml: &str) -> Result<OmeMetadata> {
+    let doc = Document::parse(xml).map_err(|e| anyhow!("Invalid OME-XML: {}", e))?;
+    let root = doc.root_element();
+    if !root.has_tag_name("OME") {
+        return Err(anyhow!("Not an OME-XML document (root element <{}>)", root.tag_name().name()));
+    }
+
+    let mut files: Vec<&str> = root.descendants()
+        .filter(|n| n.has_tag_name("TiffData"))
+        .filter_map(|entry| entry.children().find(|n| n.has_tag_name("UUID")))
+        .filter_map(|uuid| uuid.attribute("FileName"))
+        .collect();
+    files.sort_unstable();
+    files.dedup();
+    if files.len() > 1 {
+        return Err(anyhow!("Planes stored in several files ({}) are not supported", files.join(", ")));
+    }
+
+    let series: Vec<Series> = root.children()
+        .filter(|n| n.has_tag_name("Image"))
+        .map(Series::parse)
+        .collect::<Result<_>>()?;
+    let first = series.first().ok_or_else(|| anyhow!("OME-XML contains no Image"))?;
+
+    for (i, other) in series.iter().enumerate().skip(1) {
+        if other.shape != first.shape || other.data_type != first.data_type {
+            return Err(anyhow!(
+                "Series {} has shape {:?} ({}), but series 0 has {:?} ({})",
+                i, other.shape, other.data_type, first.shape, first.data_type
+            ));
+        }
+    }
+
+    let [width, height, size_z, size_c, size_t] = first.shape;
+    let dimensions = Dimensions {
+        time: size_t,
+        position: series.len(),
+        z: size_z,
+        channel: size_c,
+        height,
+        width,
+    };
+    dimensions.validate_extents()?;
+
+    // Resolve the IFD of every plane, series after series when TiffData is absent
+    let mut next_ifd = 0;
+    let mut series_ifds = Vec::with_capacity(series.len());
+    for (i, s) in series.iter().enumerate() {
+        let ifds = s.plane_ifds(next_ifd)
+            .map_err(|e| anyhow!("Series {}: {}", i, e))?;
+        next_ifd = ifds.iter().max().map_or(next_ifd, |&max| max + 1);
+        series_ifds.push(ifds);
+    }
+
+    let mut plane_ifds = Vec::with_capacity(dimensions.frame_count());
+    for t in 0..size_t {
+        for ifds in &series_ifds {
+            for z in 0..size_z {
+                for c in 0..size_c {
+                    plane_ifds.push(ifds[first.plane_index(z, c, t)]);
+                }
+            }
+        }
+    }
+
+    Ok(OmeMetadata {
+        dimensions,
+        voxel_size: first.voxel_size,
+        time_interval_s: first.time_interval_s,
+        channel_names: first.channel_names.clone(),
+        channels: first.channels.clone(),
+        positions: if series.iter().all(|s| s.position.is_empty()) {
+            Vec::new()
+        } else {
+            series.iter().map(|s| s.position.clone()).collect()
+        },
+        data_type: first.data_type,
+        plane_ifds,
+    })
+}
+
+/// One OME `Image` element
+struct Series<'a, 'input> {
+    pixels: Node<'a, 'input>,
+    /// SizeX, SizeY, SizeZ, SizeC, SizeT
+    shape: [usize; 5],
+    /// Axes after XY in DimensionOrder, fastest first
+    order: [u8; 3],
+    data_type: DataType,
+    voxel_size: VoxelSize,
+    time_interval_s: f64,
+    channel_names: Vec<String>,
+    channels: Vec<ChannelMetadata>,
+    position: PositionMetadata,
+}
+
+impl<'a, 'input> Series<'a, 'input> {
+    fn parse(image: Node<'a, 'input>) -> Result<Self> {
+        let pixels = image.children()
+            .find(|n| n.has_tag_name("Pixels"))
+            .ok_or_else(|| anyhow!("OME Image without Pixels element"))?;
+
+        let size = |name: &str| -> Result<usize> {
+            let value = pixels.attribute(name)
+                .ok_or_else(|| anyhow!("Pixels element is missing {}", name))?;
+            value.parse().map_err(|_| anyhow!("Invalid {} '{}'", name, value))
+        };
+        let shape = [size("SizeX")?, size("SizeY")?, size("SizeZ")?, size("SizeC")?, size("SizeT")?];
+
+        let order_name = pixels.attribute("DimensionOrder").unwrap_or("XYZCT");
+        let order = match order_name.as_bytes() {
+            [b'X', b'Y', rest @ ..] if is_zct_permutation(rest) => [rest[0], rest[1], rest[2]],
+            _ => return Err(anyhow!("Unsupported DimensionOrder '{}'", order_name)),
+        };
+
+        let type_name = pixels.attribute("Type")
+            .ok_or_else(|| anyhow!("Pixels element is missing Type"))?;
+        let data_type = match type_name {
+            "uint8" => DataType::UInt8,
+            "uint16" => DataType::UInt16,
+            "uint32" => DataType::UInt32,
+            "float" => DataType::Float32,
+            "double" => DataType::Float64,
+            _ => return Err(anyhow!("Unsupported OME pixel type '{}'", type_name)),
+        };
+
+        let physical_size = |axis: &str| -> Result<Option<f64>> {
+            let name = format!("PhysicalSize{}", axis);
+            pixels.attribute(name.as_str())
+                .map(|value| {
+                    let unit = pixels.attribute(format!("{}Unit", name).as_str()).unwrap_or("µm");
+                    length_in_um(parse_f64(&name, value)?, unit)
+                })
+                .transpose()
+        };
+        // Missing sizes follow the pixel width, as in files without a z-step
+        let x_um = physical_size("X")?.unwrap_or(1.0);
+        let voxel_size = VoxelSize::new(x_um, physical_size("Y")?.unwrap_or(x_um), physical_size("Z")?.unwrap_or(x_um));
+        let time_interval_s = match pixels.attribute("TimeIncrement") {
+            Some(value) => time_in_s(parse_f64("TimeIncrement", value)?, pixels.attribute("TimeIncrementUnit").unwrap_or("s"))?,
+            None => 1.0,
+        };
+
+        let channels: Vec<Node> = pixels.children().filter(|n| n.has_tag_name("Channel")).collect();
+        if channels.iter().any(|ch| ch.attribute("SamplesPerPixel").is_some_and(|s| s != "1")) {
+            return Err(anyhow!("Channels with more than one sample per pixel (RGB) are not supported"));
+        }
+        let channel_names = (0..shape[3])
+            .map(|i| {
+                channels.get(i)
+                    .and_then(|ch| ch.attribute("Name"))
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("Channel {}", i))
+            })
+            .collect();
+        let mut settings: Vec<ChannelMetadata> = (0..shape[3])
+            .map(|i| channels.get(i).map_or(Ok(ChannelMetadata::default()), |ch| parse_channel(*ch)))
+            .collect::<Result<_>>()?;
+        if settings.iter().all(ChannelMetadata::is_empty) {
+            settings.clear();
+        }
+
+        let position = image.children()
+            .find(|n| n.has_tag_name("StageLabel"))
+            .map_or(Ok(PositionMetadata::default()), parse_stage_label)?;
+
+        Ok(Self { pixels, shape, order, data_type, voxel_size, time_interval_s, channel_names, channels: settings, position })
+    }
+
+    /// Get size of axis `Z`, `C` or `T`
+    fn axis_size(&self, axis: u8) -> usize {
+        match axis {
+            b'Z' => self.shape[2],
+            b'C' => self.shape[3],
+            _ => self.shape[4],
+        }
+    }
+
+    /// Get number of planes in this series
+    fn plane_count(&self) -> usize {
+        self.shape[2] * self.shape[3] * self.shape[4]
+    }
+
+    /// Get index of plane (z, c, t) in DimensionOrder
+    fn plane_index(&self, z: usize, c: usize, t: usize) -> usize {
+        let coord = |axis: u8| match axis {
+            b'Z' => z,
+            b'C' => c,
+            _ => t,
+        };
+        let [fast, mid, slow] = self.order;
+        coord(fast) + self.axis_size(fast) * (coord(mid) + self.axis_size(mid) * coord(slow))
+    }
+
+    /// Map every plane (in DimensionOrder) to its IFD
+    ///
+    /// Without TiffData the planes occupy consecutive IFDs from `first_ifd`.
+    fn plane_ifds(&self, first_ifd: usize) -> Result<Vec<usize>> {
+        let plane_count = self.plane_count();
+        let tiff_data: Vec<Node> = self.pixels.children().filter(|n| n.has_tag_name("TiffData")).collect();
+        if tiff_data.is_empty() {
+            return Ok((first_ifd..first_ifd + plane_count).collect());
+        }
+
+        let mut ifds = vec![None; plane_count];
+        for entry in tiff_data {
+            let attr = |name: &str| -> Result<Option<usize>> {
+                entry.attribute(name)
+                    .map(|v| v.parse().map_err(|_| anyhow!("Invalid TiffData {} '{}'", name, v)))
+                    .transpose()
+            };
+            let ifd = attr("IFD")?;
+            let count = match attr("PlaneCount")? {
+                Some(count) => count,
+                None if ifd.is_some() => 1,
+                None => plane_count,
+            };
+            let start = self.plane_index(
+                attr("FirstZ")?.unwrap_or(0),
+                attr("FirstC")?.unwrap_or(0),
+                attr("FirstT")?.unwrap_or(0),
+            );
+            if start + count > plane_count {
+                return Err(anyhow!("TiffData covers planes {}..{} of {}", start, start + count, plane_count));
+            }
+
+            for k in 0..count {
+                ifds[start + k] = Some(ifd.unwrap_or(0) + k);
+            }
+        }
+
+        ifds.into_iter()
+            .enumerate()
+            .map(|(i, ifd)| ifd.ok_or_else(|| anyhow!("TiffData does not map plane {} to an IFD", i)))
+            .collect()
+    }
+}
+
+/// Check that `axes` is a permutation of Z, C and T
+fn is_zct_permutation(axes: &[u8]) -> bool {
+    let mut sorted = axes.to_vec();
+    sorted.sort_unstable();
+    sorted == b"CTZ"
+}
+
+fn parse_f64(name: &str, value: &str) -> Result<f64> {
+    value.parse().map_err(|_| anyhow!("Invalid {} '{}'", name, value))
+}
+
+/// Convert an OME length to micrometers
+fn length_in_um(value: f64, unit: &str) -> Result<f64> {
+    let scale = match unit {
+        "pm" => 1e-6,
+        "nm" => 1e-3,
+        "µm" | "μm" | "um" => 1.0,
+        "mm" => 1e3,
+        "cm" => 1e4,
+        "m" => 1e6,
+        _ => return Err(anyhow!("Unsupported length unit '{}'", unit)),
+    };
+    Ok(value * scale)
+}
+
+/// Convert an OME time to seconds
+fn time_in_s(value: f64, unit: &str) -> Result<f64> {
+    let scale = match unit {
+        "ns" => 1e-9,
+        "µs" | "μs" | "us" => 1e-6,
+        "ms" => 1e-3,
+        "s" => 1.0,
+        "min" => 60.0,
+        "h" => 3600.0,
+        _ => return Err(anyhow!("Unsupported time unit '{}'", unit)),
+    };
+    Ok(value * scale)
+}
+
+/// OME-TIFF file whose planes are decoded on demand
+#[derive(Debug)]
+struct OmeTiffFrames {
+    decoder: Mutex<Decoder<BufReader<File>>>,
+    plane_ifds: Vec<usize>,
+    width: usize,
+    height: usize,
+    /// Encoding the decoded planes are handed out in
+    encoding: SampleEncoding,
+}
+
+impl FrameSource for OmeTiffFrames {
+    fn frame_bytes(&self, index: usize) -> Result<Cow<'_, [u8]>> {
+        let ifd = self.plane_ifds[index];
+        let mut decoder = self.decoder.lock().map_err(|_| anyhow!("OME-TIFF decoder lock poisoned"))?;
+        decoder.seek_to_image(ifd)?;
+
+        let (width, height) = decoder.dimensions()?;
+        if (width as usize, height as usize) != (self.width, self.height) {
+            return Err(anyhow!(
+                "IFD {} is {}x{}, expected {}x{}",
+                ifd, width, height, self.width, self.height
+            ));
+        }
+        if !matches!(decoder.colortype()?, ColorType::Gray(_)) {
+            return Err(anyhow!("IFD {} is not a grayscale image", ifd));
+        }
+
+        let mut bytes = Vec::with_capacity(self.width * self.height * self.encoding.size());
+        match decoder.read_image()? {
+            DecodingResult::U8(values) => self.encoding.encode(&values, &mut bytes),
+            DecodingResult::U16(values) => self.encoding.encode(&values, &mut bytes),
+            DecodingResult::U32(values) => self.encoding.encode(&values, &mut bytes),
+            DecodingResult::F32(values) => self.encoding.encode(&values, &mut bytes),
+            DecodingResult::F64(values) => self.encoding.encode(&values, &mut bytes),
+            _ => return Err(anyhow!("IFD {} has an unsupported sample format", ifd)),
+        }
+        Ok(Cow::Owned(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiff::encoder::{colortype, TiffEncoder};
+
+    /// Write 16-bit planes with the given ImageDescription on the first page
+    fn write_tiff(path: &Path, description: &str, planes: &[Vec<u16>], width: u32, height: u32) {
+        let mut tiff = TiffEncoder::new(File::create(path).unwrap()).unwrap();
+        for (i, plane) in planes.iter().enumerate() {
+            let mut image = tiff.new_image::<colortype::Gray16>(width, height).unwrap();
+            if i == 0 {
+                image.encoder().write_tag(Tag::ImageDescription, description).unwrap();
+            }
+            image.write_data(plane).unwrap();
+        }
+    }
+
+    /// Plane whose every pixel encodes its origin
+    fn plane(series: usize, t: usize, z: usize, c: usize) -> Vec<u16> {
+        vec![(series * 1000 + t * 100 + z * 10 + c) as u16; 6]
+    }
+
+    #[test]
+    fn test_dimension_order() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("stack.ome.tif");
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <OME xmlns="http://www.openmicroscopy.org/Schemas/OME/2016-06">
+              <Image ID="Image:0">
+                <Pixels ID="Pixels:0" DimensionOrder="XYCZT" Type="uint16" SizeX="3" SizeY="2"
+                        SizeZ="3" SizeC="2" SizeT="2" PhysicalSizeX="0.65" TimeIncrement="30">
+                  <Channel ID="Channel:0:0" Name="Phase" SamplesPerPixel="1" ContrastMethod="Phase"/>
+                  <Channel ID="Channel:0:1" Name="GFP" SamplesPerPixel="1" Color="16711935"
+                           ExcitationWavelength="0.488" ExcitationWavelengthUnit="um" EmissionWavelength="510">
+                    <DetectorSettings ID="Detector:0" Gain="2.5" Offset="100"/>
+                  </Channel>
+                </Pixels>
+              </Image>
+            </OME>"#;
+
+        // XYCZT: C varies fastest, then Z, then T
+        let mut planes = Vec::new();
+        for t in 0..2 {
+            for z in 0..3 {
+                for c in 0..2 {
+                    planes.push(plane(0, t, z, c));
+                }
+            }
+        }
+        write_tiff(&path, xml, &planes, 3, 2);
+
+        let array = load_ome_tiff::<u16, _>(&path).unwrap();
+        assert_eq!(*array.dimensions(), Dimensions { time: 2, position: 1, z: 3, channel: 2, height: 2, width: 3 });
+        assert_eq!(array.channel_names(), ["Phase", "GFP"]);
+        assert_eq!(array.channels()[0].role, Some(ChannelRole::Phase));
+        assert_eq!(array.channels()[1], ChannelMetadata {
+            excitation_nm: Some(488.0),
+            emission_nm: Some(510.0),
+            gain: Some(2.5),
+            camera_offset: Some(100.0),
+            color: Some(Color::new(0, 255, 0)),
+            ..Default::default()
+        });
+        assert_eq!(array.pixel_size_um(), 0.65);
+        assert_eq!(array.time_interval_s(), 30.0);
+        assert_eq!(array.data_type(), "uint16");
+
+        for (t, z, c) in [(0, 0, 0), (1, 2, 1), (0, 1, 1), (1, 0, 0)] {
+            assert_eq!(array.get_frame(t, 0, z, c).unwrap()[[1, 2]], plane(0, t, z, c)[0]);
+        }
+        assert_eq!(read_ome_tiff_frame::<f32, _>(&path, 1, 0, 2, 0).unwrap()[[0, 0]], 120.0);
+    }
+
+    #[test]
+    fn test_multi_series_positions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("plate.ome.tiff");
+
+        // Two series with explicit TiffData; series 1 is stored before series 0
+        let series = |id: usize, first_ifd: usize| format!(
+            r#"<Image ID="Image:{id}"><Pixels DimensionOrder="XYZCT" Type="uint16" SizeX="3" SizeY="2"
+                    SizeZ="1" SizeC="2" SizeT="2" PhysicalSizeX="325" PhysicalSizeXUnit="nm"
+                    TimeIncrement="1500" TimeIncrementUnit="ms">
+                <Channel Name="DAPI"/><Channel/>
+                <TiffData IFD="{first_ifd}" PlaneCount="4"><UUID FileName="plate.ome.tiff">urn:uuid:1</UUID></TiffData>
+            </Pixels></Image>"#
+        );
+        let xml = format!(r#"<OME>{}{}</OME>"#, series(0, 4), series(1, 0));
+
+        let mut planes = Vec::new();
+        for s in [1, 0] {
+            for t in 0..2 {
+                for c in 0..2 {
+                    planes.push(plane(s, t, 0, c));
+                }
+            }
+        }
+        write_tiff(&path, &xml, &planes, 3, 2);
+
+        let metadata = read_ome_metadata(&path).unwrap();
+        assert_eq!(metadata.dimensions.position, 2);
+        assert_eq!(metadata.channel_names, ["DAPI", "Channel 1"]);
+        assert!((metadata.voxel_size.x_um - 0.325).abs() < 1e-12);
+        assert_eq!(metadata.time_interval_s, 1.5);
+
+        let array = open_ome_tiff::<u16, _>(&path).unwrap();
+        assert!(array.is_lazy());
+        for (t, p, c) in [(0, 0, 0), (1, 0, 1), (0, 1, 1), (1, 1, 0)] {
+            assert_eq!(array.get_frame(t, p, 0, c).unwrap()[[0, 0]], plane(p, t, 0, c)[0]);
+        }
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("plain.tif");
+        write_tiff(&path, "Plain TIFF", &[plane(0, 0, 0, 0)], 3, 2);
+        assert!(read_ome_metadata(&path).is_err());
+
+        let mismatched = r#"<OME>
+            <Image><Pixels DimensionOrder="XYZCT" Type="uint16" SizeX="3" SizeY="2" SizeZ="1" SizeC="1" SizeT="1"/></Image>
+            <Image><Pixels DimensionOrder="XYZCT" Type="uint16" SizeX="4" SizeY="2" SizeZ="1" SizeC="1" SizeT="1"/></Image>
+        </OME>"#;
+        assert!(parse_ome_xml(mismatched).is_err());
+
+        let external = r#"<OME><Image><Pixels DimensionOrder="XYZCT" Type="uint16" SizeX="3" SizeY="2" SizeZ="1" SizeC="2" SizeT="1">
+            <TiffData IFD="0" FirstC="0"><UUID FileName="c0.ome.tif">urn:uuid:2</UUID></TiffData>
+            <TiffData IFD="0" FirstC="1"><UUID FileName="c1.ome.tif">urn:uuid:3</UUID></TiffData>
+        </Pixels></Image></OME>"#;
+        let error = parse_ome_xml(external).unwrap_err();
+        assert!(error.to_string().contains("several files"), "{}", error);
+
+        let signed = r#"<OME><Image><Pixels DimensionOrder="XYZCT" Type="int16" SizeX="3" SizeY="2" SizeZ="1" SizeC="1" SizeT="1"/></Image></OME>"#;
+        assert!(parse_ome_xml(signed).is_err());
+
+        assert!(parse_ome_xml("<OME/>").is_err());
+        assert!(is_tiff(Path::new("a/b.OME.TIF")));
+        assert!(is_tiff(Path::new("b.tiff")));
+        assert!(!is_tiff(Path::new("b.meta")));
+    }
+
+    /// Array with distinct, integer-valued frames and non-default metadata
+    fn test_array(data_type: DataType, positions: usize) -> Array6D {
+        let dims = Dimensions::new(2, positions, 3, 2, 4, 5);
+        let data = ndarray::Array6::from_shape_fn(dims.shape(), |(t, p, z, c, y, x)| {
+            (t * 10000 + p * 1000 + z * 100 + c * 10 + y * 5 + x) as f32 % 250.0
+        });
+        Array6D::new(
+            data,
+            dims,
+            0.325,
+            7.5,
+            vec!["Phase <BF>".to_string(), "GFP & \"mCherry\" µ".to_string()],
+            data_type.name().to_string(),
+        ).unwrap()
+        .with_voxel_size(VoxelSize::new(0.325, 0.3, 2.0)).unwrap()
+    }
+
+    fn assert_same_metadata(loaded: &Array6D, array: &Array6D) {
+        assert_eq!(loaded.voxel_size(), array.voxel_size());
+        assert_eq!(loaded.time_interval_s(), array.time_interval_s());
+        assert_eq!(loaded.channel_names(), array.channel_names());
+        assert_eq!(loaded.data_type(), array.data_type());
+    }
+
+    #[test]
+    fn test_ome_series_roundtrip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let channels = vec![
+            ChannelMetadata { role: Some(ChannelRole::Phase), ..Default::default() },
+            ChannelMetadata {
+                excitation_nm: Some(561.0),
+                emission_nm: Some(610.0),
+                color: Some(Color::new(255, 0, 255)),
+                role: Some(ChannelRole::Fluorescence),
+                ..Default::default()
+            },
+        ];
+        for data_type in [DataType::UInt8, DataType::UInt16, DataType::UInt32, DataType::Float32, DataType::Float64] {
+            let array = test_array(data_type, 2)
+                .with_channels(channels.clone()).unwrap()
+                .with_positions(vec![
+                    PositionMetadata::in_well("B03", 2).at(1250.5, -300.0, Some(12.0)),
+                    PositionMetadata::default(),
+                ]).unwrap();
+            assert!(!ome_xml(&array, 0..2).contains("UUID"));
+            let path = temp_dir.path().join(format!("{}.ome.tif", data_type));
+            let written = save_tiff(&array, &path, TiffFlavor::Ome, PositionLayout::Series).unwrap();
+            assert_eq!(written, vec![path.clone()]);
+
+            let loaded = load_ome_tiff::<f32, _>(&path).unwrap();
+            assert_eq!(loaded.dimensions(), array.dimensions());
+            assert_same_metadata(&loaded, &array);
+            assert_eq!(loaded.channels(), channels);
+            // Only the stage coordinates survive; the well names the Image
+            assert_eq!(loaded.positions(), [PositionMetadata::default().at(1250.5, -300.0, Some(12.0)), PositionMetadata::default()]);
+            assert_eq!(loaded.data(), array.data());
+
+            // A renamed copy still reads its own planes
+            let renamed = temp_dir.path().join(format!("renamed_{}.ome.tif", data_type));
+            std::fs::rename(&path, &renamed).unwrap();
+            assert_eq!(load_ome_tiff::<f32, _>(&renamed).unwrap().data(), array.data());
+        }
+    }
+
+    #[test]
+    fn test_ome_files_per_position() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let array = test_array(DataType::UInt16, 3);
+        let path = temp_dir.path().join("stack.ome.tif");
+
+        let written = save_tiff(&array, &path, TiffFlavor::Ome, PositionLayout::Files).unwrap();
+        assert_eq!(written.len(), 3);
+        assert_eq!(written[2], temp_dir.path().join("stack_p2.ome.tif"));
+
+        for (p, file) in written.iter().enumerate() {
+            let loaded = load_ome_tiff::<f32, _>(file).unwrap();
+            assert_eq!(loaded.dimensions().position, 1);
+            assert_same_metadata(&loaded, &array);
+            assert_eq!(loaded.get_frame(1, 0, 2, 1).unwrap(), array.get_frame(1, p, 2, 1).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_imagej_roundtrip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let array = test_array(DataType::UInt16, 2);
+
+        // A hyperstack has no series, so positions need separate files
+        let path = temp_dir.path().join("hyperstack.tif");
+        assert!(save_tiff(&array, &path, TiffFlavor::ImageJ, PositionLayout::Series).is_err());
+
+        let written = save_tiff(&array, &path, TiffFlavor::ImageJ, PositionLayout::Files).unwrap();
+        assert_eq!(written[1], temp_dir.path().join("hyperstack_p1.tif"));
+        for (p, file) in written.iter().enumerate() {
+            let loaded = load_ome_tiff::<f32, _>(file).unwrap();
+            assert_eq!(*loaded.dimensions(), Dimensions { position: 1, ..*array.dimensions() });
+            assert_same_metadata(&loaded, &array);
+            assert_eq!(loaded.get_frame(1, 0, 1, 1).unwrap(), array.get_frame(1, p, 1, 1).unwrap());
+        }
+
+        let labels = test_array(DataType::UInt32, 1);
+        assert!(save_tiff(&labels, temp_dir.path().join("labels.tif"), TiffFlavor::ImageJ, PositionLayout::Series).is_err());
+    }
+
+    #[test]
+    fn test_pixels_per_um() {
+        for pixel_size_um in [0.65, 6.5, 1e-6, 1e-9] {
+            let resolution = pixels_per_um(pixel_size_um);
+            assert!(resolution.d >= 1);
+            let read_back = resolution.d as f64 / resolution.n as f64;
+            assert!((read_back - pixel_size_um.max(1e-6)).abs() <= 1e-9 * pixel_size_um.max(1e-6), "{}", pixel_size_um);
+        }
+    }
+}
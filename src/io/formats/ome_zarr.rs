@@ -0,0 +1,856 @@
+//! OME-Zarr (OME-NGFF v0.4) import and export
+//!
+//! Stores are Zarr v2 hierarchies on the local filesystem. Every position is
+//! an NGFF image with `multiscales` metadata whose first dataset is a TCZYX
+//! array chunked per plane. Positions are written as `bioformats2raw` series
+//! (`<store>/<p>/0`); plate/well stores and single images are read as well.
+//! Chunks are read on demand. Further datasets of the multiscales hold
+//! downsampled pyramid levels (`<store>/<p>/1`, ...).
+
+use super::atomic::temp_path;
+use crate::io::array_6d::{Array6D, Dimensions, FrameSource, VoxelSize};
+use crate::io::channels::{ChannelMetadata, Color};
+use crate::io::dtype::{ByteOrder, Pixel, SampleEncoding};
+use anyhow::{Result, anyhow};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use ndarray::Array2;
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// OME-NGFF version written by this build
+pub const NGFF_VERSION: &str = "0.4";
+
+/// zstd level used for new chunks
+const ZSTD_LEVEL: i32 = 3;
+
+/// Check whether a path is an OME-Zarr store (`.zarr` or a Zarr group directory)
+pub fn is_zarr(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zarr"))
+        || path.join(".zgroup").is_file()
+}
+
+/// Zarr v2 array metadata (`.zarray`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ZarrArray {
+    zarr_format: u32,
+    shape: Vec<usize>,
+    chunks: Vec<usize>,
+    dtype: String,
+    compressor: Option<Value>,
+    #[serde(default)]
+    fill_value: Value,
+    order: String,
+    filters: Option<Vec<Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dimension_separator: Option<String>,
+}
+
+/// Chunk compressors understood by the reader
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Codec {
+    Raw,
+    Zstd,
+    /// numcodecs LZ4: little-endian size header followed by an LZ4 block
+    Lz4,
+    Zlib,
+    Gzip,
+}
+
+impl Codec {
+    fn from_compressor(compressor: Option<&Value>) -> Result<Self> {
+        let Some(compressor) = compressor.filter(|c| !c.is_null()) else {
+            return Ok(Codec::Raw);
+        };
+        match compressor.get("id").and_then(Value::as_str) {
+            Some("zstd") => Ok(Codec::Zstd),
+            Some("lz4") => Ok(Codec::Lz4),
+            Some("zlib") => Ok(Codec::Zlib),
+            Some("gzip") => Ok(Codec::Gzip),
+            Some("blosc") => Err(anyhow!(
+                "Blosc-compressed Zarr arrays are not supported; re-save the store with \
+                 compressor=numcodecs.Zstd() or compressor=None"
+            )),
+            _ => Err(anyhow!("Unsupported Zarr compressor {}", compressor)),
+        }
+    }
+
+    fn decompress(self, chunk: Vec<u8>, size: usize) -> Result<Vec<u8>> {
+        let data = match self {
+            Codec::Raw => chunk,
+            Codec::Zstd => zstd::bulk::decompress(&chunk, size)?,
+            Codec::Lz4 => {
+                let block = chunk.get(4..).ok_or_else(|| anyhow!("LZ4 chunk is missing its size header"))?;
+                lz4_flex::block::decompress(block, size)
+                    .map_err(|e| anyhow!("LZ4 decompression failed: {}", e))?
+            }
+            Codec::Zlib => {
+                let mut data = Vec::with_capacity(size);
+                ZlibDecoder::new(chunk.as_slice()).read_to_end(&mut data)?;
+                data
+            }
+            Codec::Gzip => {
+                let mut data = Vec::with_capacity(size);
+                GzDecoder::new(chunk.as_slice()).read_to_end(&mut data)?;
+                data
+            }
+        };
+
+        if data.len() != size {
+            return Err(anyhow!("Zarr chunk holds {} bytes, expected {}", data.len(), size));
+        }
+        Ok(data)
+    }
+}
+
+/// Save a 6D array as an OME-Zarr store with one image per position
+///
+/// The store is written next to `path` and moved into place once complete,
+/// so a failed save leaves an existing store at `path` untouched. An
+/// existing Zarr store is replaced; any other existing path is reported as
+/// an error.
+pub fn save_zarr<T: Pixel, P: AsRef<Path>>(array: &Array6D<T>, path: P) -> Result<()> {
+    let path = path.as_ref();
+    if path.exists() && !path.join(".zgroup").is_file() {
+        return Err(anyhow!("{:?} exists and is not a Zarr store", path));
+    }
+
+    let staging = temp_path(path);
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    if let Err(e) = write_store(array, &staging) {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    // Swap the stores; the old one is only deleted once the new one is in place
+    let mut previous = staging.clone().into_os_string();
+    previous.push(".old");
+    let previous = PathBuf::from(previous);
+    if path.exists() {
+        if previous.exists() {
+            fs::remove_dir_all(&previous)?;
+        }
+        fs::rename(path, &previous)?;
+    }
+    fs::rename(&staging, path)?;
+    if previous.exists() {
+        fs::remove_dir_all(&previous)?;
+    }
+    Ok(())
+}
+
+/// Write the groups and arrays of a new store at `path`
+fn write_store<T: Pixel>(array: &Array6D<T>, path: &Path) -> Result<()> {
+    let dims = array.dimensions();
+    let encoding = SampleEncoding::new(array.dtype(), ByteOrder::Little);
+    write_json(&path.join(".zgroup"), &json!({ "zarr_format": 2 }))?;
+    write_json(&path.join(".zattrs"), &json!({ "bioformats2raw.layout": 3 }))?;
+
+    for p in 0..dims.position {
+        let image_dir = path.join(p.to_string());
+        write_json(&image_dir.join(".zgroup"), &json!({ "zarr_format": 2 }))?;
+        write_json(&image_dir.join(".zattrs"), &image_attrs(array, p))?;
+
+        write_image_array(&image_dir.join("0"), array, p, encoding)?;
+    }
+
+    Ok(())
+}
+
+/// Write position `p` of `array` as a TCZYX Zarr array chunked per plane
+fn write_image_array<T: Pixel>(array_dir: &Path, array: &Array6D<T>, p: usize, encoding: SampleEncoding) -> Result<()> {
+    let dims = array.dimensions();
+    let zarray = ZarrArray {
+        zarr_format: 2,
+        shape: vec![dims.time, dims.channel, dims.z, dims.height, dims.width],
+        chunks: vec![1, 1, 1, dims.height, dims.width],
+        dtype: encoding.numpy_descr(),
+        compressor: Some(json!({ "id": "zstd", "level": ZSTD_LEVEL })),
+        fill_value: json!(0),
+        order: "C".to_string(),
+        filters: None,
+        dimension_separator: Some("/".to_string()),
+    };
+    write_json(&array_dir.join(".zarray"), &serde_json::to_value(&zarray)?)?;
+
+    let mut buffer = Vec::with_capacity(dims.frame_elements() * encoding.size());
+    for t in 0..dims.time {
+        for z in 0..dims.z {
+            for c in 0..dims.channel {
+                let frame = array.get_frame(t, p, z, c)?;
+                buffer.clear();
+                encoding.encode(frame.iter(), &mut buffer);
+
+                let chunk_path = array_dir.join(format!("{}/{}/{}/0/0", t, c, z));
+                fs::create_dir_all(chunk_path.parent().expect("chunk path has a parent"))?;
+                fs::write(chunk_path, zstd::bulk::compress(&buffer, ZSTD_LEVEL)?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Store `level`, downsampled by `factor` in Y and X, as dataset `index` of every image
+///
+/// Datasets from `index` on are replaced. Only TCZYX images, as written by
+/// [`save_zarr`], can take new levels.
+pub fn write_zarr_level<T: Pixel, P: AsRef<Path>>(path: P, level: &Array6D<T>, index: usize, factor: usize) -> Result<()> {
+    let path = path.as_ref();
+    let images = image_paths(path)?;
+    if images.len() != level.dimensions().position || index == 0 {
+        return Err(anyhow!("Level {} does not fit the {} images of {:?}", index, images.len(), path));
+    }
+    let encoding = SampleEncoding::new(level.dtype(), ByteOrder::Little);
+
+    for (p, image_dir) in images.iter().enumerate() {
+        if ZarrImage::open(image_dir, 0)?.axes != [Some(0), Some(1), Some(2), Some(3), Some(4)] {
+            return Err(anyhow!("{:?} is not a TCZYX image; pyramid levels cannot be added", image_dir));
+        }
+        let mut attrs = read_json(&image_dir.join(".zattrs"))?;
+        let datasets = attrs.pointer_mut("/multiscales/0/datasets")
+            .and_then(Value::as_array_mut)
+            .ok_or_else(|| anyhow!("{:?} has no datasets", image_dir))?;
+        if datasets.len() < index {
+            return Err(anyhow!("{:?} has no level {} to build level {} on", image_dir, index - 1, index));
+        }
+
+        // The level covers the same space with larger pixels
+        let mut dataset = datasets[0].clone();
+        if let Some(scale) = dataset.pointer_mut("/coordinateTransformations/0/scale").and_then(Value::as_array_mut) {
+            for value in &mut scale[3..] {
+                *value = json!(value.as_f64().unwrap_or(1.0) * factor as f64);
+            }
+        }
+        dataset["path"] = json!(index.to_string());
+        datasets.truncate(index);
+        datasets.push(dataset);
+
+        let array_dir = image_dir.join(index.to_string());
+        if array_dir.exists() {
+            fs::remove_dir_all(&array_dir)?;
+        }
+        write_image_array(&array_dir, level, p, encoding)?;
+        write_json(&image_dir.join(".zattrs"), &attrs)?;
+    }
+    Ok(())
+}
+
+/// Get the downsampling factor of every dataset of the store's first image, starting with 1
+pub fn zarr_level_factors<P: AsRef<Path>>(path: P) -> Result<Vec<usize>> {
+    let path = path.as_ref();
+    let image_dir = image_paths(path)?.into_iter().next()
+        .ok_or_else(|| anyhow!("Zarr store {:?} contains no images", path))?;
+    let attrs = read_json(&image_dir.join(".zattrs"))?;
+    let levels = attrs.pointer("/multiscales/0/datasets").and_then(Value::as_array).map_or(0, Vec::len);
+    let base = ZarrImage::open(&image_dir, 0)?;
+    (0..levels)
+        .map(|level| {
+            let image = ZarrImage::open(&image_dir, level)?;
+            Ok((image.voxel_size.x_um / base.voxel_size.x_um).round().max(1.0) as usize)
+        })
+        .collect()
+}
+
+/// Build the `multiscales` and `omero` attributes of position `p`
+fn image_attrs<T: Pixel>(array: &Array6D<T>, p: usize) -> Value {
+    let voxel_size = array.voxel_size();
+    json!({
+        "multiscales": [{
+            "version": NGFF_VERSION,
+            "name": format!("Position {}", p),
+            "axes": [
+                { "name": "t", "type": "time", "unit": "second" },
+                { "name": "c", "type": "channel" },
+                { "name": "z", "type": "space", "unit": "micrometer" },
+                { "name": "y", "type": "space", "unit": "micrometer" },
+                { "name": "x", "type": "space", "unit": "micrometer" },
+            ],
+            "datasets": [{
+                "path": "0",
+                "coordinateTransformations": [
+                    { "type": "scale", "scale": [array.time_interval_s(), 1.0, voxel_size.z_um, voxel_size.y_um, voxel_size.x_um] },
+                ],
+            }],
+        }],
+        "omero": {
+            "channels": array.channel_names().iter().enumerate()
+                .map(|(c, name)| match array.channels().get(c).and_then(|channel| channel.color) {
+                    Some(color) => json!({
+                        "label": name,
+                        "active": true,
+                        "color": format!("{:02X}{:02X}{:02X}", color.r, color.g, color.b),
+                    }),
+                    None => json!({ "label": name, "active": true }),
+                })
+                .collect::<Vec<_>>(),
+        },
+    })
+}
+
+/// Write pretty-printed JSON, creating parent directories
+fn write_json(path: &Path, value: &Value) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(value)?)?;
+    Ok(())
+}
+
+/// Read a JSON file of the store, or an empty object if it does not exist
+fn read_json(path: &Path) -> Result<Value> {
+    if !path.is_file() {
+        return Ok(json!({}));
+    }
+    serde_json::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| anyhow!("Invalid JSON in {:?}: {}", path, e))
+}
+
+/// Open an OME-Zarr store; chunks are read from disk on access
+pub fn open_zarr<T: Pixel, P: AsRef<Path>>(path: P) -> Result<Array6D<T>> {
+    open_zarr_level(path, 0)
+}
+
+/// Open dataset `level` of the multiscales pyramid of every image, 0 being full resolution
+pub fn open_zarr_level<T: Pixel, P: AsRef<Path>>(path: P, level: usize) -> Result<Array6D<T>> {
+    let path = path.as_ref();
+    let images = image_paths(path)?
+        .iter()
+        .map(|image| ZarrImage::open(image, level))
+        .collect::<Result<Vec<_>>>()?;
+    let first = images.first().ok_or_else(|| anyhow!("Zarr store {:?} contains no images", path))?;
+
+    for (p, image) in images.iter().enumerate().skip(1) {
+        if image.sizes != first.sizes || image.encoding.data_type != first.encoding.data_type {
+            return Err(anyhow!(
+                "Image {} has TCZYX shape {:?} ({}), but image 0 has {:?} ({})",
+                p, image.sizes, image.encoding.data_type, first.sizes, first.encoding.data_type
+            ));
+        }
+    }
+
+    let [time, channel, z, height, width] = first.sizes;
+    let dimensions = Dimensions { time, position: images.len(), z, channel, height, width };
+    dimensions.validate_extents()?;
+
+    let data_type = first.encoding.data_type;
+    let voxel_size = first.voxel_size;
+    let time_interval_s = first.time_interval_s;
+    let channel_names = first.channel_names.clone();
+    let channels = first.channels.clone();
+
+    // Planes are handed out in the byte order of the store
+    let encoding = first.encoding;
+    Array6D::from_source(
+        Arc::new(ZarrFrames { images, dimensions }),
+        encoding,
+        dimensions,
+        voxel_size.x_um,
+        time_interval_s,
+        channel_names,
+        data_type.name().to_string(),
+    )?
+    .with_voxel_size(voxel_size)?
+    .with_channels(channels)
+}
+
+/// Load an OME-Zarr store into memory
+pub fn load_zarr<T: Pixel, P: AsRef<Path>>(path: P) -> Result<Array6D<T>> {
+    open_zarr(path)?.materialize()
+}
+
+/// Read a single 2D frame (t, p, z, c) from an OME-Zarr store
+pub fn read_zarr_frame<T: Pixel, P: AsRef<Path>>(path: P, t: usize, p: usize, z: usize, c: usize) -> Result<Array2<T>> {
+    Ok(open_zarr(path)?.get_frame(t, p, z, c)?.into_owned())
+}
+
+/// Find the image groups of a store in position order
+fn image_paths(root: &Path) -> Result<Vec<PathBuf>> {
+    let attrs = read_json(&root.join(".zattrs"))?;
+
+    if attrs.get("multiscales").is_some() {
+        return Ok(vec![root.to_path_buf()]);
+    }
+
+    if let Some(plate) = attrs.get("plate") {
+        // Wells in plate order, fields in well order
+        let mut images = Vec::new();
+        for well in plate.get("wells").and_then(Value::as_array).into_iter().flatten() {
+            let well_path = well.get("path").and_then(Value::as_str)
+                .ok_or_else(|| anyhow!("Plate well without path"))?;
+            let well_dir = root.join(well_path);
+            let well_attrs = read_json(&well_dir.join(".zattrs"))?;
+            for field in well_attrs.pointer("/well/images").and_then(Value::as_array).into_iter().flatten() {
+                let field_path = field.get("path").and_then(Value::as_str)
+                    .ok_or_else(|| anyhow!("Well {} has an image without path", well_path))?;
+                images.push(well_dir.join(field_path));
+            }
+        }
+        return Ok(images);
+    }
+
+    if attrs.get("bioformats2raw.layout").is_some() {
+        // Series listed in OME/.zattrs, or numbered groups 0, 1, ...
+        let series = read_json(&root.join("OME/.zattrs"))?;
+        if let Some(series) = series.get("series").and_then(Value::as_array) {
+            return series.iter()
+                .map(|s| s.as_str().map(|s| root.join(s)).ok_or_else(|| anyhow!("Invalid bioformats2raw series entry {}", s)))
+                .collect();
+        }
+        return Ok((0..)
+            .map(|p: usize| root.join(p.to_string()))
+            .take_while(|dir| dir.join(".zattrs").is_file())
+            .collect());
+    }
+
+    Err(anyhow!("{:?} has no multiscales, plate or bioformats2raw metadata", root))
+}
+
+/// Array of one NGFF image at one resolution level
+#[derive(Debug)]
+struct ZarrImage {
+    array_dir: PathBuf,
+    array: ZarrArray,
+    codec: Codec,
+    encoding: SampleEncoding,
+    /// Index of the t, c, z, y and x axes in the array
+    axes: [Option<usize>; 5],
+    /// Sizes along t, c, z, y and x (1 for absent axes)
+    sizes: [usize; 5],
+    /// Element strides within a chunk
+    chunk_strides: Vec<usize>,
+    voxel_size: VoxelSize,
+    time_interval_s: f64,
+    channel_names: Vec<String>,
+    /// Display colours from the `omero` metadata; empty if it has none
+    channels: Vec<ChannelMetadata>,
+}
+
+/// Axis names in the order of `ZarrImage::axes`
+const AXIS_NAMES: [&str; 5] = ["t", "c", "z", "y", "x"];
+
+impl ZarrImage {
+    /// Open dataset `level` of the image's multiscales, 0 being full resolution
+    fn open(image_dir: &Path, level: usize) -> Result<Self> {
+        let attrs = read_json(&image_dir.join(".zattrs"))?;
+        let multiscale = attrs.pointer("/multiscales/0")
+            .ok_or_else(|| anyhow!("{:?} has no multiscales metadata", image_dir))?;
+
+        // v0.4 axes are objects, v0.3 axes plain names; v0.2 images are TCZYX
+        let axis_names: Vec<String> = match multiscale.get("axes").and_then(Value::as_array) {
+            Some(axes) => axes.iter()
+                .map(|axis| axis.get("name").unwrap_or(axis).as_str().map(str::to_lowercase))
+                .collect::<Option<_>>()
+                .ok_or_else(|| anyhow!("Invalid multiscales axes in {:?}", image_dir))?,
+            None => AXIS_NAMES.iter().map(|a| a.to_string()).collect(),
+        };
+        let axis_units: Vec<Option<&str>> = multiscale.get("axes").and_then(Value::as_array)
+            .map(|axes| axes.iter().map(|a| a.get("unit").and_then(Value::as_str)).collect())
+            .unwrap_or_else(|| vec![None; axis_names.len()]);
+
+        let dataset = multiscale.pointer(&format!("/datasets/{}", level))
+            .ok_or_else(|| anyhow!("{:?} has no dataset {}", image_dir, level))?;
+        let dataset_path = dataset.get("path").and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("Dataset without path in {:?}", image_dir))?;
+        let array_dir = image_dir.join(dataset_path);
+        let array: ZarrArray = serde_json::from_value(read_json(&array_dir.join(".zarray"))?)
+            .map_err(|e| anyhow!("Invalid .zarray in {:?}: {}", array_dir, e))?;
+
+        if array.zarr_format != 2 {
+            return Err(anyhow!("Unsupported Zarr format {}", array.zarr_format));
+        }
+        if array.filters.as_ref().is_some_and(|f| !f.is_empty()) {
+            return Err(anyhow!("Zarr filters are not supported"));
+        }
+        let ndim = array.shape.len();
+        if axis_names.len() != ndim || array.chunks.len() != ndim {
+            return Err(anyhow!(
+                "{:?} has {} axes but a {}-dimensional array",
+                image_dir, axis_names.len(), ndim
+            ));
+        }
+        if array.chunks.contains(&0) {
+            return Err(anyhow!("{:?} has empty chunks {:?}", array_dir, array.chunks));
+        }
+
+        let mut axes = [None; 5];
+        for (i, name) in axis_names.iter().enumerate() {
+            let slot = AXIS_NAMES.iter().position(|a| a == name)
+                .ok_or_else(|| anyhow!("Unsupported axis '{}' in {:?}", name, image_dir))?;
+            axes[slot] = Some(i);
+        }
+        if axes[3].is_none() || axes[4].is_none() {
+            return Err(anyhow!("{:?} has no y and x axes", image_dir));
+        }
+        let sizes = axes.map(|axis| axis.map_or(1, |i| array.shape[i]));
+
+        let chunk_strides = match array.order.as_str() {
+            "C" => (0..ndim).map(|i| array.chunks[i + 1..].iter().product()).collect(),
+            "F" => (0..ndim).map(|i| array.chunks[..i].iter().product()).collect(),
+            order => return Err(anyhow!("Invalid Zarr order '{}'", order)),
+        };
+
+        // Scale of the dataset along x, y, z and t
+        let scale: Vec<f64> = dataset.get("coordinateTransformations").and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .find(|t| t.get("type").and_then(Value::as_str) == Some("scale"))
+            .and_then(|t| t.get("scale"))
+            .and_then(|s| serde_json::from_value(s.clone()).ok())
+            .unwrap_or_default();
+        let scaled = |slot: usize, convert: fn(f64, &str) -> Result<f64>, default_unit: &str| -> Result<Option<f64>> {
+            axes[slot].and_then(|i| scale.get(i).map(|s| (i, *s)))
+                .map(|(i, value)| convert(value, axis_units[i].unwrap_or(default_unit)))
+                .transpose()
+        };
+        // Missing y and z scales follow x
+        let x_um = scaled(4, length_in_um, "micrometer")?.unwrap_or(1.0);
+        let y_um = scaled(3, length_in_um, "micrometer")?.unwrap_or(x_um);
+        let z_um = match axes[2] {
+            Some(_) => scaled(2, length_in_um, "micrometer")?.unwrap_or(x_um),
+            None => x_um,
+        };
+        let voxel_size = VoxelSize::new(x_um, y_um, z_um);
+        let time_interval_s = scaled(0, time_in_s, "second")?.unwrap_or(1.0);
+
+        let omero_channels = attrs.pointer("/omero/channels").and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let channel_names = (0..sizes[1])
+            .map(|c| omero_channels.get(c).and_then(|ch| ch.get("label")).and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("Channel {}", c)))
+            .collect();
+        // Colours are kept only when the metadata describes every channel
+        let colors = omero_channels.iter()
+            .map(|ch| {
+                ch.get("color").and_then(Value::as_str)
+                    .map(|value| value.parse::<Color>().map_err(|_| anyhow!("Invalid omero colour '{}' in {:?}", value, image_dir)))
+                    .transpose()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let channels = if colors.len() == sizes[1] && colors.iter().any(Option::is_some) {
+            colors.into_iter().map(|color| ChannelMetadata { color, ..Default::default() }).collect()
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            codec: Codec::from_compressor(array.compressor.as_ref())?,
+            encoding: SampleEncoding::from_numpy(&array.dtype)?,
+            array_dir,
+            array,
+            axes,
+            sizes,
+            chunk_strides,
+            voxel_size,
+            time_interval_s,
+            channel_names,
+            channels,
+        })
+    }
+
+    /// Read the decompressed chunk at `chunk_coords`, filling missing chunks
+    fn read_chunk(&self, chunk_coords: &[usize]) -> Result<Vec<u8>> {
+        let separator = self.array.dimension_separator.as_deref().unwrap_or(".");
+        let key = chunk_coords.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(separator);
+        let size = self.array.chunks.iter().product::<usize>() * self.encoding.size();
+
+        match fs::read(self.array_dir.join(key)) {
+            Ok(chunk) => self.codec.decompress(chunk, size),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let fill = match &self.array.fill_value {
+                    Value::Number(n) => n.as_f64().unwrap_or(0.0),
+                    Value::String(s) if s == "NaN" => f64::NAN,
+                    _ => 0.0,
+                };
+                let mut element = Vec::new();
+                self.encoding.encode(&[fill], &mut element);
+                Ok(element.repeat(size / element.len()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Assemble plane (t, c, z) from every chunk it intersects
+    fn read_plane(&self, t: usize, c: usize, z: usize) -> Result<Vec<u8>> {
+        let elem = self.encoding.size();
+        let [_, _, _, height, width] = self.sizes;
+        let (ay, ax) = (self.axes[3].expect("y axis"), self.axes[4].expect("x axis"));
+        let (chunk_h, chunk_w) = (self.array.chunks[ay], self.array.chunks[ax]);
+
+        let mut coords = vec![0; self.array.shape.len()];
+        for (slot, value) in [(0, t), (1, c), (2, z)] {
+            if let Some(i) = self.axes[slot] {
+                coords[i] = value;
+            }
+        }
+
+        let mut plane = vec![0u8; height * width * elem];
+        for y0 in (0..height).step_by(chunk_h) {
+            for x0 in (0..width).step_by(chunk_w) {
+                coords[ay] = y0;
+                coords[ax] = x0;
+                let chunk_coords: Vec<usize> = coords.iter().zip(&self.array.chunks).map(|(i, n)| i / n).collect();
+                let chunk = self.read_chunk(&chunk_coords)?;
+
+                let mut local: Vec<usize> = coords.iter().zip(&self.array.chunks).map(|(i, n)| i % n).collect();
+                let x_len = chunk_w.min(width - x0);
+                // Rows of C-order chunks are contiguous and copied at once
+                let run = if self.chunk_strides[ax] == 1 { x_len } else { 1 };
+                for y in y0..(y0 + chunk_h).min(height) {
+                    local[ay] = y - y0;
+                    for dx in (0..x_len).step_by(run) {
+                        local[ax] = dx;
+                        let src: usize = local.iter().zip(&self.chunk_strides).map(|(i, s)| i * s).sum();
+                        let dst = y * width + x0 + dx;
+                        plane[dst * elem..(dst + run) * elem].copy_from_slice(&chunk[src * elem..(src + run) * elem]);
+                    }
+                }
+            }
+        }
+        Ok(plane)
+    }
+}
+
+/// Images of a store whose planes are read on demand
+#[derive(Debug)]
+struct ZarrFrames {
+    images: Vec<ZarrImage>,
+    dimensions: Dimensions,
+}
+
+impl FrameSource for ZarrFrames {
+    fn frame_bytes(&self, index: usize) -> Result<Cow<'_, [u8]>> {
+        let (t, p, z, c) = self.dimensions.frame_coords(index);
+        Ok(Cow::Owned(self.images[p].read_plane(t, c, z)?))
+    }
+}
+
+/// Convert an NGFF space unit to micrometers
+fn length_in_um(value: f64, unit: &str) -> Result<f64> {
+    let scale = match unit {
+        "nanometer" => 1e-3,
+        "micrometer" => 1.0,
+        "millimeter" => 1e3,
+        "centimeter" => 1e4,
+        "meter" => 1e6,
+        _ => return Err(anyhow!("Unsupported space unit '{}'", unit)),
+    };
+    Ok(value * scale)
+}
+
+/// Convert an NGFF time unit to seconds
+fn time_in_s(value: f64, unit: &str) -> Result<f64> {
+    let scale = match unit {
+        "nanosecond" => 1e-9,
+        "microsecond" => 1e-6,
+        "millisecond" => 1e-3,
+        "second" => 1.0,
+        "minute" => 60.0,
+        "hour" => 3600.0,
+        _ => return Err(anyhow!("Unsupported time unit '{}'", unit)),
+    };
+    Ok(value * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::dtype::DataType;
+    use crate::io::generators::ArrayGenerator;
+
+    #[test]
+    fn test_bioformats2raw_roundtrip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = ArrayGenerator::generate_test_array(3, 2, 2, 2, 7, 5).unwrap();
+
+        for data_type in [DataType::UInt16, DataType::Float32] {
+            let array = Array6D::new(
+                source.data().unwrap().mapv(|v| data_type.quantize(v)),
+                *source.dimensions(),
+                0.65,
+                90.0,
+                source.channel_names().to_vec(),
+                data_type.name().to_string(),
+            ).unwrap()
+            .with_voxel_size(VoxelSize::new(0.65, 0.65, 1.5)).unwrap();
+            let path = temp_dir.path().join(format!("{}.zarr", data_type));
+            save_zarr(&array, &path).unwrap();
+
+            assert!(is_zarr(&path));
+            assert!(path.join("1/0/2/1/1/0/0").is_file());
+            let zarray = read_json(&path.join("0/0/.zarray")).unwrap();
+            assert_eq!(zarray["shape"], json!([3, 2, 2, 7, 5]));
+            let attrs = read_json(&path.join("1/.zattrs")).unwrap();
+            assert_eq!(attrs["multiscales"][0]["version"], NGFF_VERSION);
+
+            let opened = open_zarr::<f32, _>(&path).unwrap();
+            assert!(opened.is_lazy());
+            assert_eq!(opened.dimensions(), array.dimensions());
+            assert_eq!(opened.data_type(), data_type.name());
+            assert_eq!(opened.voxel_size(), array.voxel_size());
+            assert_eq!(opened.time_interval_s(), 90.0);
+            assert_eq!(opened.channel_names(), array.channel_names());
+            assert_eq!(opened.materialize().unwrap().data(), array.data());
+
+            // Saving again replaces the store, leaving nothing else behind
+            fs::create_dir_all(temp_path(&path).join("stale")).unwrap();
+            save_zarr(&array, &path).unwrap();
+            assert_eq!(read_zarr_frame::<f32, _>(&path, 2, 1, 1, 0).unwrap(), array.get_frame(2, 1, 1, 0).unwrap());
+            assert!(!temp_path(&path).exists());
+            assert!(fs::read_dir(temp_dir.path()).unwrap().all(|entry| !entry.unwrap().path().to_string_lossy().ends_with(".old")));
+        }
+
+        let not_a_store = temp_dir.path().join("notes");
+        fs::create_dir(&not_a_store).unwrap();
+        assert!(save_zarr(&source, &not_a_store).is_err());
+
+        // Channel colours go to the omero metadata as RRGGBB
+        let colored = source.clone().with_channels(vec![
+            ChannelMetadata { color: Some(Color::new(0, 255, 0)), ..Default::default() },
+            ChannelMetadata::default(),
+        ]).unwrap();
+        let path = temp_dir.path().join("colored.zarr");
+        save_zarr(&colored, &path).unwrap();
+        let attrs = read_json(&path.join("0/.zattrs")).unwrap();
+        assert_eq!(attrs["omero"]["channels"][0]["color"], "00FF00");
+        assert!(attrs["omero"]["channels"][1].get("color").is_none());
+        assert_eq!(open_zarr::<f32, _>(&path).unwrap().channels(), colored.channels());
+        assert!(open_zarr::<f32, _>(temp_dir.path().join("float32.zarr")).unwrap().channels().is_empty());
+    }
+
+    /// Write a 2-well plate whose images use tiles, F order and various codecs
+    fn write_plate(root: &Path) -> Vec<Vec<u16>> {
+        write_json(&root.join(".zgroup"), &json!({ "zarr_format": 2 })).unwrap();
+        write_json(&root.join(".zattrs"), &json!({
+            "plate": { "wells": [{ "path": "B/3" }, { "path": "A/1" }], "version": "0.4" }
+        })).unwrap();
+        write_json(&root.join("B/3/.zattrs"), &json!({ "well": { "images": [{ "path": "0" }] } })).unwrap();
+        write_json(&root.join("A/1/.zattrs"), &json!({ "well": { "images": [{ "path": "1" }, { "path": "2" }] } })).unwrap();
+
+        // ZYX images of 2 × 5 × 4 pixels in 2 × 3 × 3 chunks
+        let (depth, height, width) = (2, 5, 4);
+        let mut planes: Vec<Vec<u16>> = Vec::new();
+        let codecs = [
+            (json!(null), "C", "."),
+            (json!({ "id": "lz4" }), "F", "/"),
+            (json!({ "id": "zlib", "level": 1 }), "C", "/"),
+        ];
+        for (field, (compressor, order, separator)) in codecs.into_iter().enumerate() {
+            let well = if field == 0 { "B/3" } else { "A/1" };
+            let image_dir = root.join(format!("{}/{}", well, field));
+            write_json(&image_dir.join(".zattrs"), &json!({
+                "multiscales": [{
+                    "version": "0.4",
+                    "axes": [
+                        { "name": "z", "type": "space", "unit": "micrometer" },
+                        { "name": "y", "type": "space", "unit": "nanometer" },
+                        { "name": "x", "type": "space", "unit": "nanometer" },
+                    ],
+                    "datasets": [{ "path": "s0", "coordinateTransformations": [{ "type": "scale", "scale": [2.0, 250.0, 250.0] }] }],
+                }],
+            })).unwrap();
+
+            let array_dir = image_dir.join("s0");
+            let chunks = [2, 3, 3];
+            write_json(&array_dir.join(".zarray"), &json!({
+                "zarr_format": 2, "shape": [depth, height, width], "chunks": chunks, "dtype": ">u2",
+                "compressor": compressor, "fill_value": 7, "order": order, "filters": null,
+                "dimension_separator": separator,
+            })).unwrap();
+
+            let value = |z: usize, y: usize, x: usize| (field * 1000 + z * 100 + y * 10 + x) as u16;
+            for z in 0..depth {
+                planes.push((0..height * width).map(|i| value(z, i / width, i % width)).collect());
+            }
+            // The last x tile of z-chunk 0 is left out and reads as the fill value
+            for (cy, cx) in [(0, 0), (1, 0), (1, 1), (0, 1)] {
+                if field == 2 && (cy, cx) == (1, 1) {
+                    for z in 0..depth {
+                        for y in 3..height {
+                            for x in 3..width {
+                                planes[field * depth + z][y * width + x] = 7;
+                            }
+                        }
+                    }
+                    continue;
+                }
+                let mut chunk = Vec::new();
+                for i in 0..chunks.iter().product::<usize>() {
+                    // Local (z, y, x) of element i in the chunk's memory order
+                    let (lz, ly, lx) = match order {
+                        "C" => (i / 9, i / 3 % 3, i % 3),
+                        _ => (i % 2, i / 2 % 3, i / 6),
+                    };
+                    let (y, x) = (cy * 3 + ly, cx * 3 + lx);
+                    let v = if y < height && x < width { value(lz, y, x) } else { 0 };
+                    chunk.extend_from_slice(&v.to_be_bytes());
+                }
+                let chunk = match field {
+                    1 => {
+                        let mut lz4 = (chunk.len() as u32).to_le_bytes().to_vec();
+                        lz4.extend(lz4_flex::block::compress(&chunk));
+                        lz4
+                    }
+                    2 => {
+                        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::fast());
+                        std::io::Write::write_all(&mut encoder, &chunk).unwrap();
+                        encoder.finish().unwrap()
+                    }
+                    _ => chunk,
+                };
+                let key = ["0", &cy.to_string(), &cx.to_string()].join(separator);
+                let chunk_path = array_dir.join(key);
+                fs::create_dir_all(chunk_path.parent().unwrap()).unwrap();
+                fs::write(chunk_path, chunk).unwrap();
+            }
+        }
+        planes
+    }
+
+    #[test]
+    fn test_plate_store() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("plate.zarr");
+        let planes = write_plate(&root);
+
+        let array = open_zarr::<u16, _>(&root).unwrap();
+        assert_eq!(*array.dimensions(), Dimensions::new(1, 3, 2, 1, 5, 4));
+        assert_eq!(array.pixel_size_um(), 0.25);
+        assert_eq!(array.channel_names(), ["Channel 0"]);
+
+        for p in 0..3 {
+            for z in 0..2 {
+                let frame = array.get_frame(0, p, z, 0).unwrap();
+                assert_eq!(frame.iter().copied().collect::<Vec<_>>(), planes[p * 2 + z], "position {} z {}", p, z);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_stores() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("empty.zarr");
+        write_json(&root.join(".zgroup"), &json!({ "zarr_format": 2 })).unwrap();
+        assert!(open_zarr::<f32, _>(&root).is_err());
+
+        let plate = temp_dir.path().join("plate.zarr");
+        write_plate(&plate);
+        let zarray = plate.join("B/3/0/s0/.zarray");
+        let mut array = read_json(&zarray).unwrap();
+        array["chunks"] = json!([2, 0, 3]);
+        write_json(&zarray, &array).unwrap();
+        let error = open_zarr::<f32, _>(&plate).unwrap_err();
+        assert!(error.to_string().contains("empty chunks"), "{}", error);
+
+        let error = Codec::from_compressor(Some(&json!({ "id": "blosc", "cname": "lz4" }))).unwrap_err();
+        assert!(error.to_string().contains("Blosc"), "{}", error);
+        assert_eq!(Codec::from_compressor(Some(&json!(null))).unwrap(), Codec::Raw);
+    }
+}
@@ -0,0 +1,324 @@
+//! Import of headerless binary files
+//!
+//! Some instruments dump frames as a bare blob of samples behind an optional
+//! header. A [`RawImport`] describes such a file by its header size, sample
+//! encoding, axis order and extents. A split-format `.meta` can point at the
+//! blob in place, recording its axis order; any file can also be read into
+//! an array and converted.
+
+use super::{save_array, validate_file, write_metadata, ArrayMetadata};
+use crate::io::array_6d::{Array6D, Dimensions, MappedFrames, VoxelSize};
+use crate::io::axes::AxisSpec;
+use crate::io::dtype::{Pixel, SampleEncoding};
+use crate::io::provenance::ProvenanceEntry;
+use anyhow::{Result, anyhow};
+use memmap2::MmapOptions;
+use ndarray::{ArrayD, IxDyn};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Layout and physical metadata of a headerless binary file
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawImport {
+    encoding: SampleEncoding,
+    axes: AxisSpec,
+    shape: Vec<usize>,
+    dimensions: Dimensions,
+    offset: u64,
+    pixel_size_um: f64,
+    z_step_um: Option<f64>,
+    time_interval_s: f64,
+    channel_names: Option<Vec<String>>,
+}
+
+impl RawImport {
+    /// Describe a C-order array of `shape` whose axes are named by `axes`
+    pub fn new(encoding: SampleEncoding, axes: AxisSpec, shape: Vec<usize>) -> Result<Self> {
+        let dimensions = axes.dimensions(&shape)?;
+        Ok(Self {
+            encoding,
+            axes,
+            shape,
+            dimensions,
+            offset: 0,
+            pixel_size_um: 1.0,
+            z_step_um: None,
+            time_interval_s: 1.0,
+            channel_names: None,
+        })
+    }
+
+    /// Set number of header bytes before the first sample
+    pub fn offset(mut self, bytes: u64) -> Self {
+        self.offset = bytes;
+        self
+    }
+
+    /// Set pixel size
+    pub fn pixel_size(mut self, size_um: f64) -> Self {
+        self.pixel_size_um = size_um;
+        self
+    }
+
+    /// Set step between z-planes (default the pixel size)
+    pub fn z_step(mut self, step_um: f64) -> Self {
+        self.z_step_um = Some(step_um);
+        self
+    }
+
+    /// Get the voxel size of the imported array
+    fn voxel_size(&self) -> VoxelSize {
+        VoxelSize::new(self.pixel_size_um, self.pixel_size_um, self.z_step_um.unwrap_or(self.pixel_size_um))
+    }
+
+    /// Set time interval
+    pub fn time_interval(mut self, interval_s: f64) -> Self {
+        self.time_interval_s = interval_s;
+        self
+    }
+
+    /// Set channel names (default "Channel <i>")
+    pub fn channel_names(mut self, names: Vec<String>) -> Self {
+        self.channel_names = Some(names);
+        self
+    }
+
+    /// Get TPZCYX dimensions of the imported array
+    pub fn dimensions(&self) -> &Dimensions {
+        &self.dimensions
+    }
+
+    /// Get size of the samples in bytes, excluding the header
+    pub fn data_size(&self) -> u64 {
+        (self.dimensions.total_elements() * self.encoding.size()) as u64
+    }
+
+    /// Get the axis order of the raw file
+    pub fn axes(&self) -> &AxisSpec {
+        &self.axes
+    }
+
+    /// Check that the raw file holds exactly the header and the samples
+    pub fn check_file(&self, raw_path: &Path) -> Result<()> {
+        let file_size = std::fs::metadata(raw_path)?.len();
+        let expected_size = self.offset + self.data_size();
+        if file_size != expected_size {
+            return Err(anyhow!(
+                "{} is {} bytes, but a {} byte header and {} {} samples of shape {:?} need {} bytes",
+                raw_path.display(), file_size, self.offset, self.dimensions.total_elements(),
+                self.encoding.data_type, self.shape, expected_size
+            ));
+        }
+        Ok(())
+    }
+
+    /// Describe the import of `raw_path` for the provenance log
+    fn provenance(&self, raw_path: &Path) -> ProvenanceEntry {
+        ProvenanceEntry::new("import_raw")
+            .parameter("file", raw_path.display())
+            .parameter("offset", self.offset)
+            .parameter("data_type", self.encoding.data_type)
+            .parameter("byte_order", format!("{:?}", self.encoding.byte_order).to_lowercase())
+            .parameter("axes", &self.axes)
+            .parameter("shape", format!("{:?}", self.shape))
+    }
+
+    fn channel_names_or_default(&self) -> Vec<String> {
+        self.channel_names.clone()
+            .unwrap_or_else(|| (0..self.dimensions.channel).map(|c| format!("Channel {}", c)).collect())
+    }
+
+    /// Write a `.meta` file next to the raw file that reads it in place
+    ///
+    /// Returns the path of the `.meta` file, which [`validate_file`] and
+    /// `open_array` then accept like any split file.
+    pub fn write_meta(&self, raw_path: &Path) -> Result<PathBuf> {
+        self.check_file(raw_path)?;
+
+        let meta_path = raw_path.with_extension("meta");
+        if meta_path == raw_path {
+            return Err(anyhow!("Raw file {} must not have the .meta extension", raw_path.display()));
+        }
+        let file_name = raw_path.file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow!("Invalid raw file name: {}", raw_path.display()))?;
+
+        let mut metadata = ArrayMetadata::new(
+            self.dimensions,
+            self.pixel_size_um,
+            self.time_interval_s,
+            self.channel_names_or_default(),
+            self.encoding.data_type,
+        );
+        metadata.z_step_um = self.z_step_um;
+        metadata.byte_order = self.encoding.byte_order;
+        metadata.data_file = Some(file_name.to_string());
+        metadata.data_offset = self.offset;
+        if !self.axes.is_tpzcyx_order() {
+            metadata.axis_order = Some(self.axes.clone());
+        }
+        metadata.provenance.push(self.provenance(raw_path));
+        write_metadata(&meta_path, &metadata)?;
+
+        validate_file(&meta_path)?;
+        Ok(meta_path)
+    }
+
+    /// Open the raw file as an array with pixel type `T`
+    ///
+    /// The file is memory-mapped and read frame by frame in any axis order;
+    /// frames that are not contiguous in the file are gathered on access.
+    pub fn open<T: Pixel>(&self, raw_path: &Path) -> Result<Array6D<T>> {
+        self.check_file(raw_path)?;
+
+        let file = File::open(raw_path)?;
+        // SAFETY: the mapping is read-only; the raw file must not be truncated
+        // or rewritten by another process while the array is open.
+        let mmap = unsafe { MmapOptions::new().offset(self.offset).map(&file)? };
+        let frames = MappedFrames::new(mmap, self.dimensions, self.encoding.size(), &self.axes);
+        let array = Array6D::from_source(
+            Arc::new(frames),
+            self.encoding,
+            self.dimensions,
+            self.pixel_size_um,
+            self.time_interval_s,
+            self.channel_names_or_default(),
+            self.encoding.data_type.name().to_string(),
+        )?;
+        Ok(array.with_voxel_size(self.voxel_size())?.with_provenance(vec![self.provenance(raw_path)]))
+    }
+
+    /// Load the raw file into memory as an array with pixel type `T`
+    pub fn load<T: Pixel>(&self, raw_path: &Path) -> Result<Array6D<T>> {
+        self.check_file(raw_path)?;
+
+        let mut file = File::open(raw_path)?;
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut buffer = Vec::with_capacity(self.data_size() as usize);
+        file.read_to_end(&mut buffer)?;
+
+        let values = self.encoding.decode::<T>(&buffer);
+        drop(buffer);
+        let data = self.axes.to_tpzcyx(ArrayD::from_shape_vec(IxDyn(&self.shape), values)?)?;
+
+        let array = Array6D::new(
+            data,
+            self.dimensions,
+            self.pixel_size_um,
+            self.time_interval_s,
+            self.channel_names_or_default(),
+            self.encoding.data_type.name().to_string(),
+        )?;
+        Ok(array.with_voxel_size(self.voxel_size())?.with_provenance(vec![self.provenance(raw_path)]))
+    }
+
+    /// Convert the raw file to any format supported by `save_array`
+    ///
+    /// Samples keep their data type; f64 pixels hold every type exactly.
+    pub fn convert(&self, raw_path: &Path, output: &Path) -> Result<()> {
+        save_array(&self.open::<f64>(raw_path)?, output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::dtype::{ByteOrder, DataType};
+    use crate::io::formats::{load_array, open_array, read_frame};
+
+    /// Write a raw file of a (C=2, T=3, Y=2, X=4) big-endian uint16 array
+    /// behind a 16-byte header, with value 1000c + 100t + 10y + x
+    fn write_ctyx(path: &Path) {
+        let mut bytes = vec![0xAB; 16];
+        for c in 0..2u16 {
+            for t in 0..3u16 {
+                for y in 0..2u16 {
+                    for x in 0..4u16 {
+                        bytes.extend_from_slice(&(c * 1000 + t * 100 + y * 10 + x).to_be_bytes());
+                    }
+                }
+            }
+        }
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    fn encoding() -> SampleEncoding {
+        SampleEncoding::new(DataType::UInt16, ByteOrder::Big)
+    }
+
+    #[test]
+    fn test_write_meta_in_place() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let raw_path = temp_dir.path().join("dump.bin");
+        write_ctyx(&raw_path);
+
+        // Read the channel axis as T and the time axis as P to stay in TPZCYX order
+        let import = RawImport::new(encoding(), "TPYX".parse().unwrap(), vec![2, 3, 2, 4])
+            .unwrap()
+            .offset(16)
+            .pixel_size(0.5)
+            .z_step(2.0);
+        let meta_path = import.write_meta(&raw_path).unwrap();
+        assert_eq!(meta_path, temp_dir.path().join("dump.meta"));
+
+        let metadata = validate_file(&meta_path).unwrap();
+        assert_eq!(metadata.dimensions, Dimensions::new(2, 3, 1, 1, 2, 4));
+        assert_eq!(metadata.data_offset, 16);
+        assert_eq!(metadata.data_path(&meta_path), raw_path);
+
+        let array = open_array(&meta_path).unwrap();
+        assert!(array.is_lazy());
+        assert_eq!(array.voxel_size(), VoxelSize::new(0.5, 0.5, 2.0));
+        assert_eq!(array.get_frame(1, 2, 0, 0).unwrap()[[1, 3]], 1213.0);
+        assert_eq!(load_array(&meta_path).unwrap().get_frame(0, 1, 0, 0).unwrap()[[0, 2]], 102.0);
+        assert_eq!(read_frame(&meta_path, 1, 2, 0, 0).unwrap()[[1, 3]], 1213.0);
+    }
+
+    #[test]
+    fn test_convert_transposed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let raw_path = temp_dir.path().join("dump.raw");
+        write_ctyx(&raw_path);
+
+        let import = RawImport::new(encoding(), "CTYX".parse().unwrap(), vec![2, 3, 2, 4])
+            .unwrap()
+            .offset(16);
+        let opened = import.open::<f32>(&raw_path).unwrap();
+        assert!(opened.is_lazy());
+        assert_eq!(opened.get_frame(2, 0, 0, 1).unwrap()[[1, 3]], 1213.0);
+
+        let output = temp_dir.path().join("converted.meta");
+        import.convert(&raw_path, &output).unwrap();
+        let array = load_array(&output).unwrap();
+        assert_eq!(array.data_type(), "uint16");
+        assert_eq!(*array.dimensions(), Dimensions::new(3, 1, 1, 2, 2, 4));
+        assert_eq!(array.get_frame(2, 0, 0, 1).unwrap()[[1, 3]], 1213.0);
+        assert_eq!(array.get_frame(1, 0, 0, 0).unwrap()[[0, 2]], 102.0);
+        let operations: Vec<&str> = array.provenance().iter().map(|e| e.operation.as_str()).collect();
+        assert_eq!(operations, ["import_raw", "save_array"]);
+        assert_eq!(array.provenance()[0].parameters["axes"], "CTYX");
+
+        // The .meta of the raw file records its axis order instead
+        let meta_path = import.write_meta(&raw_path).unwrap();
+        assert_eq!(validate_file(&meta_path).unwrap().axis_order, Some("CTYX".parse().unwrap()));
+        assert_eq!(open_array(&meta_path).unwrap().get_frame(2, 0, 0, 1).unwrap()[[1, 3]], 1213.0);
+        assert_eq!(load_array(&meta_path).unwrap().data(), array.data());
+        assert_eq!(read_frame(&meta_path, 1, 0, 0, 0).unwrap()[[0, 2]], 102.0);
+    }
+
+    #[test]
+    fn test_size_mismatch() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let raw_path = temp_dir.path().join("dump.bin");
+        write_ctyx(&raw_path);
+
+        // Forgetting the header leaves 16 bytes unaccounted for
+        let import = RawImport::new(encoding(), "CTYX".parse().unwrap(), vec![2, 3, 2, 4]).unwrap();
+        let error = import.load::<u16>(&raw_path).unwrap_err().to_string();
+        assert!(error.contains("112 bytes"), "{}", error);
+
+        assert!(RawImport::new(encoding(), "CTYX".parse().unwrap(), vec![2, 3, 2]).is_err());
+    }
+}
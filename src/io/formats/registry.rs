@@ -0,0 +1,607 @@
+//! Registry of file formats with detection from the path
+//!
+//! Every format implements [`Format`] (its name, extensions and magic
+//! bytes) and [`FormatReader`] and/or [`FormatWriter`] for the pixel types
+//! it supports. [`FormatRegistry::builtin`] lists the formats of this crate;
+//! `load_array`, `save_array` and the UI file dialogs go through it, so a
+//! new format only needs to be registered there.
+//!
+//! Readers are picked by extension first, then by the magic bytes at the
+//! start of the file. Writers are picked by extension; paths without one
+//! are written in the split format.
+
+use super::multi_position::{is_position_directory, is_position_manifest, load_positions, open_positions, read_positions_frame};
+use super::npy::{load_npy, load_npz, open_npy, read_npy_frame, save_npy, save_npz};
+use super::ome_tiff::{open_ome_tiff, load_ome_tiff, read_ome_tiff_frame, save_tiff, PositionLayout, TiffFlavor};
+use super::ome_zarr::{load_zarr, open_zarr, read_zarr_frame, save_zarr};
+use super::{load_split, open_split, read_split_frame, save_array_with, validate_file, SplitFormat};
+use crate::io::array_6d::{Array6D, Dimensions, VoxelSize};
+use crate::io::channels::ChannelMetadata;
+use crate::io::dtype::{DataType, Pixel};
+use crate::io::positions::PositionMetadata;
+use crate::io::provenance::ProvenanceEntry;
+use anyhow::{Result, anyhow};
+use ndarray::Array2;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Number of bytes read from the start of a file for [`Format::sniff`]
+const HEADER_SIZE: usize = 16;
+
+/// Name, extensions and magic bytes of a file format
+pub trait Format: Send + Sync {
+    /// Name shown in file dialogs, e.g. `OME-TIFF`
+    fn name(&self) -> &'static str;
+
+    /// Extensions without the dot, lowercase
+    fn extensions(&self) -> &'static [&'static str];
+
+    /// Whether the format is stored as a directory rather than a file
+    fn is_directory(&self) -> bool {
+        false
+    }
+
+    /// Check whether `path` has one of the format's extensions
+    fn matches(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.extensions().iter().any(|e| ext.eq_ignore_ascii_case(e)))
+    }
+
+    /// Check whether the first bytes of a file belong to this format
+    fn sniff(&self, _header: &[u8]) -> bool {
+        false
+    }
+}
+
+/// Reads arrays with pixel type `T`
+pub trait FormatReader<T: Pixel>: Format {
+    /// Load the whole array into memory
+    fn load(&self, path: &Path) -> Result<Array6D<T>>;
+
+    /// Open the array, reading frames on demand where the format allows
+    fn open(&self, path: &Path) -> Result<Array6D<T>> {
+        self.load(path)
+    }
+
+    /// Read a single 2D frame (t, p, z, c)
+    fn read_frame(&self, path: &Path, t: usize, p: usize, z: usize, c: usize) -> Result<Array2<T>> {
+        Ok(self.open(path)?.get_frame(t, p, z, c)?.into_owned())
+    }
+
+    /// Describe the file without reading its pixels where the format allows
+    fn info(&self, path: &Path) -> Result<FileInfo> {
+        self.open(path).map(|array| FileInfo::from(&array))
+    }
+
+    /// Check that the file can be read, without reading the pixels if possible
+    fn validate(&self, path: &Path) -> Result<()> {
+        self.open(path).map(|_| ())
+    }
+}
+
+/// Format-neutral description of an array file
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileInfo {
+    pub dimensions: Dimensions,
+    pub voxel_size: VoxelSize,
+    pub time_interval_s: f64,
+    pub channel_names: Vec<String>,
+    pub data_type: DataType,
+    /// Seconds since the first time point, for irregular acquisitions
+    pub timestamps_s: Option<Vec<f64>>,
+    pub provenance: Vec<ProvenanceEntry>,
+    pub channels: Vec<ChannelMetadata>,
+    pub positions: Vec<PositionMetadata>,
+    /// When the file was written, for formats that record it
+    pub created_at: Option<String>,
+    /// Whether a writer is still appending time points to the file
+    pub appending: bool,
+}
+
+impl<T: Pixel> From<&Array6D<T>> for FileInfo {
+    fn from(array: &Array6D<T>) -> Self {
+        Self {
+            dimensions: *array.dimensions(),
+            voxel_size: array.voxel_size(),
+            time_interval_s: array.time_interval_s(),
+            channel_names: array.channel_names().to_vec(),
+            data_type: array.dtype(),
+            timestamps_s: array.timestamps_s().map(<[f64]>::to_vec),
+            provenance: array.provenance().to_vec(),
+            channels: array.channels().to_vec(),
+            positions: array.positions().to_vec(),
+            created_at: None,
+            appending: false,
+        }
+    }
+}
+
+/// Writes arrays with pixel type `T`
+pub trait FormatWriter<T: Pixel>: Format {
+    fn save(&self, array: &Array6D<T>, path: &Path) -> Result<()>;
+}
+
+/// Split format: a JSON `.meta` file next to a `.data` file of samples
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SplitFiles;
+
+impl Format for SplitFiles {
+    fn name(&self) -> &'static str {
+        "Split"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["meta"]
+    }
+
+    fn sniff(&self, header: &[u8]) -> bool {
+        header.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{')
+    }
+}
+
+impl<T: Pixel> FormatReader<T> for SplitFiles {
+    fn load(&self, path: &Path) -> Result<Array6D<T>> {
+        load_split(path)
+    }
+
+    fn open(&self, path: &Path) -> Result<Array6D<T>> {
+        open_split(path)
+    }
+
+    fn read_frame(&self, path: &Path, t: usize, p: usize, z: usize, c: usize) -> Result<Array2<T>> {
+        read_split_frame(path, t, p, z, c)
+    }
+
+    fn info(&self, path: &Path) -> Result<FileInfo> {
+        let metadata = validate_file(path)?;
+        Ok(FileInfo {
+            dimensions: metadata.dimensions,
+            voxel_size: metadata.voxel_size(),
+            time_interval_s: metadata.time_interval_s,
+            data_type: metadata.data_type.parse()?,
+            channel_names: metadata.channel_names,
+            timestamps_s: metadata.timestamps_s,
+            provenance: metadata.provenance,
+            channels: metadata.channels,
+            positions: metadata.positions,
+            created_at: Some(metadata.created_at),
+            appending: metadata.appending,
+        })
+    }
+
+    fn validate(&self, path: &Path) -> Result<()> {
+        validate_file(path).map(|_| ())
+    }
+}
+
+impl<T: Pixel> FormatWriter<T> for SplitFiles {
+    fn save(&self, array: &Array6D<T>, path: &Path) -> Result<()> {
+        save_array_with(array, path, SplitFormat::Raw)
+    }
+}
+
+/// OME-TIFF, written with one series per position; ImageJ hyperstacks are read as well
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OmeTiff;
+
+impl Format for OmeTiff {
+    fn name(&self) -> &'static str {
+        "OME-TIFF"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["tif", "tiff"]
+    }
+
+    fn sniff(&self, header: &[u8]) -> bool {
+        // Classic TIFF (42) and BigTIFF (43) in either byte order
+        matches!(header, [b'I', b'I', 42 | 43, 0, ..] | [b'M', b'M', 0, 42 | 43, ..])
+    }
+}
+
+impl<T: Pixel> FormatReader<T> for OmeTiff {
+    fn load(&self, path: &Path) -> Result<Array6D<T>> {
+        load_ome_tiff(path)
+    }
+
+    fn open(&self, path: &Path) -> Result<Array6D<T>> {
+        open_ome_tiff(path)
+    }
+
+    fn read_frame(&self, path: &Path, t: usize, p: usize, z: usize, c: usize) -> Result<Array2<T>> {
+        read_ome_tiff_frame(path, t, p, z, c)
+    }
+}
+
+impl<T: Pixel> FormatWriter<T> for OmeTiff {
+    fn save(&self, array: &Array6D<T>, path: &Path) -> Result<()> {
+        save_tiff(array, path, TiffFlavor::Ome, PositionLayout::Series).map(|_| ())
+    }
+}
+
+/// OME-Zarr (NGFF) store
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OmeZarr;
+
+impl Format for OmeZarr {
+    fn name(&self) -> &'static str {
+        "OME-Zarr"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["zarr"]
+    }
+
+    fn is_directory(&self) -> bool {
+        true
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        super::ome_zarr::is_zarr(path)
+    }
+}
+
+impl<T: Pixel> FormatReader<T> for OmeZarr {
+    fn load(&self, path: &Path) -> Result<Array6D<T>> {
+        load_zarr(path)
+    }
+
+    fn open(&self, path: &Path) -> Result<Array6D<T>> {
+        open_zarr(path)
+    }
+
+    fn read_frame(&self, path: &Path, t: usize, p: usize, z: usize, c: usize) -> Result<Array2<T>> {
+        read_zarr_frame(path, t, p, z, c)
+    }
+}
+
+impl<T: Pixel> FormatWriter<T> for OmeZarr {
+    fn save(&self, array: &Array6D<T>, path: &Path) -> Result<()> {
+        save_zarr(array, path)
+    }
+}
+
+/// NumPy `.npy` array, memory-mapped where the layout allows
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Npy;
+
+impl Format for Npy {
+    fn name(&self) -> &'static str {
+        "NumPy Array"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["npy"]
+    }
+
+    fn sniff(&self, header: &[u8]) -> bool {
+        header.starts_with(b"\x93NUMPY")
+    }
+}
+
+impl<T: Pixel> FormatReader<T> for Npy {
+    fn load(&self, path: &Path) -> Result<Array6D<T>> {
+        load_npy(path, None)
+    }
+
+    fn open(&self, path: &Path) -> Result<Array6D<T>> {
+        open_npy(path, None)
+    }
+
+    fn read_frame(&self, path: &Path, t: usize, p: usize, z: usize, c: usize) -> Result<Array2<T>> {
+        read_npy_frame(path, t, p, z, c)
+    }
+}
+
+impl<T: Pixel> FormatWriter<T> for Npy {
+    fn save(&self, array: &Array6D<T>, path: &Path) -> Result<()> {
+        save_npy(array, path)
+    }
+}
+
+/// NumPy `.npz` archive with the metadata as side arrays
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Npz;
+
+impl Format for Npz {
+    fn name(&self) -> &'static str {
+        "NumPy Archive"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["npz"]
+    }
+
+    fn sniff(&self, header: &[u8]) -> bool {
+        header.starts_with(b"PK\x03\x04")
+    }
+}
+
+impl<T: Pixel> FormatReader<T> for Npz {
+    fn load(&self, path: &Path) -> Result<Array6D<T>> {
+        load_npz(path, None)
+    }
+}
+
+impl<T: Pixel> FormatWriter<T> for Npz {
+    fn save(&self, array: &Array6D<T>, path: &Path) -> Result<()> {
+        save_npz(array, path)
+    }
+}
+
+/// Directory of split files, one per position, read as one array
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PositionDirectory;
+
+impl Format for PositionDirectory {
+    fn name(&self) -> &'static str {
+        "Position Folder"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn is_directory(&self) -> bool {
+        true
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        is_position_directory(path)
+    }
+}
+
+impl<T: Pixel> FormatReader<T> for PositionDirectory {
+    fn load(&self, path: &Path) -> Result<Array6D<T>> {
+        load_positions(path)
+    }
+
+    fn open(&self, path: &Path) -> Result<Array6D<T>> {
+        open_positions(path)
+    }
+
+    fn read_frame(&self, path: &Path, t: usize, p: usize, z: usize, c: usize) -> Result<Array2<T>> {
+        read_positions_frame(path, t, p, z, c)
+    }
+}
+
+/// JSON manifest listing split files, one per position, read as one array
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PositionManifestFile;
+
+impl Format for PositionManifestFile {
+    fn name(&self) -> &'static str {
+        "Position Manifest"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["json"]
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) && is_position_manifest(path)
+    }
+}
+
+impl<T: Pixel> FormatReader<T> for PositionManifestFile {
+    fn load(&self, path: &Path) -> Result<Array6D<T>> {
+        load_positions(path)
+    }
+
+    fn open(&self, path: &Path) -> Result<Array6D<T>> {
+        open_positions(path)
+    }
+
+    fn read_frame(&self, path: &Path, t: usize, p: usize, z: usize, c: usize) -> Result<Array2<T>> {
+        read_positions_frame(path, t, p, z, c)
+    }
+}
+
+/// Readers and writers for arrays with pixel type `T`, tried in registration order
+pub struct FormatRegistry<T: Pixel = f32> {
+    readers: Vec<Arc<dyn FormatReader<T>>>,
+    writers: Vec<Arc<dyn FormatWriter<T>>>,
+}
+
+impl<T: Pixel> FormatRegistry<T> {
+    /// Create a registry without formats
+    pub fn empty() -> Self {
+        Self { readers: Vec::new(), writers: Vec::new() }
+    }
+
+    /// Create a registry of all formats of this crate
+    pub fn builtin() -> Self {
+        Self::empty()
+            .with(SplitFiles)
+            .with(OmeTiff)
+            .with(OmeZarr)
+            .with(Npy)
+            .with(Npz)
+            .with_reader(Arc::new(PositionManifestFile))
+            .with_reader(Arc::new(PositionDirectory))
+    }
+
+    /// Register a format that can be read and written
+    pub fn with<F: FormatReader<T> + FormatWriter<T> + 'static>(self, format: F) -> Self {
+        let format = Arc::new(format);
+        self.with_reader(format.clone()).with_writer(format)
+    }
+
+    /// Register a reader
+    pub fn with_reader(mut self, reader: Arc<dyn FormatReader<T>>) -> Self {
+        self.readers.push(reader);
+        self
+    }
+
+    /// Register a writer
+    pub fn with_writer(mut self, writer: Arc<dyn FormatWriter<T>>) -> Self {
+        self.writers.push(writer);
+        self
+    }
+
+    pub fn readers(&self) -> impl Iterator<Item = &dyn FormatReader<T>> {
+        self.readers.iter().map(|reader| reader.as_ref())
+    }
+
+    pub fn writers(&self) -> impl Iterator<Item = &dyn FormatWriter<T>> {
+        self.writers.iter().map(|writer| writer.as_ref())
+    }
+
+    /// Find the reader of `path` from its extension, then its magic bytes
+    pub fn reader_for(&self, path: &Path) -> Result<&dyn FormatReader<T>> {
+        if let Some(reader) = self.readers().find(|reader| reader.matches(path)) {
+            return Ok(reader);
+        }
+        let header = read_header(path)?;
+        self.readers()
+            .find(|reader| reader.sniff(&header))
+            .ok_or_else(|| anyhow!("Unrecognized file format: {:?}", path))
+    }
+
+    /// Find the writer of `path` from its extension; paths without one get the first writer
+    pub fn writer_for(&self, path: &Path) -> Result<&dyn FormatWriter<T>> {
+        if let Some(writer) = self.writers().find(|writer| writer.matches(path)) {
+            return Ok(writer);
+        }
+        match path.extension() {
+            Some(ext) => Err(anyhow!("Unsupported extension '.{}' for writing {:?}", ext.to_string_lossy(), path)),
+            None => self.writers().next().ok_or_else(|| anyhow!("No format registered to write {:?}", path)),
+        }
+    }
+
+    /// Check whether `path` is read as a split file
+    pub fn is_split(&self, path: &Path) -> bool {
+        self.reader_for(path).is_ok_and(|reader| reader.name() == Format::name(&SplitFiles))
+    }
+
+    /// Get file dialog filters of the readable files: all of them, then one per format
+    ///
+    /// Formats stored as directories cannot be picked in a file dialog and are left out.
+    pub fn open_filters(&self) -> Vec<(String, Vec<&'static str>)> {
+        dialog_filters(self.readers().map(|reader| reader as &dyn Format))
+    }
+
+    /// Get file dialog filters of the writable files, one per format
+    pub fn save_filters(&self) -> Vec<(String, Vec<&'static str>)> {
+        let mut filters = dialog_filters(self.writers().map(|writer| writer as &dyn Format));
+        filters.remove(0);
+        filters
+    }
+}
+
+impl<T: Pixel> Default for FormatRegistry<T> {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// Build a combined filter followed by one filter per file format
+fn dialog_filters<'a>(formats: impl Iterator<Item = &'a dyn Format>) -> Vec<(String, Vec<&'static str>)> {
+    let per_format: Vec<(String, Vec<&'static str>)> = formats
+        .filter(|format| !format.is_directory())
+        .map(|format| (format!("{} Files", format.name()), format.extensions().to_vec()))
+        .collect();
+    let all = per_format.iter().flat_map(|(_, extensions)| extensions.iter().copied()).collect();
+    std::iter::once(("6D Data Files".to_string(), all)).chain(per_format).collect()
+}
+
+/// Read the first bytes of a file; directories and missing files give none
+fn read_header(path: &Path) -> Result<Vec<u8>> {
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+    File::open(path)?.take(HEADER_SIZE as u64).read_to_end(&mut header)?;
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::formats::{load_array, open_array, read_frame, save_array};
+    use crate::io::generators::ArrayGenerator;
+
+    #[test]
+    fn test_detection() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let array = ArrayGenerator::generate_test_array(2, 1, 1, 2, 4, 3).unwrap();
+        let registry = FormatRegistry::<f32>::builtin();
+
+        for (name, format) in [("a.meta", "Split"), ("a.ome.tif", "OME-TIFF"), ("a.zarr", "OME-Zarr"), ("a.npy", "NumPy Array"), ("a.npz", "NumPy Archive")] {
+            let path = temp_dir.path().join(name);
+            assert_eq!(registry.writer_for(&path).unwrap().name(), format);
+            save_array(&array, &path).unwrap();
+            assert_eq!(registry.reader_for(&path).unwrap().name(), format);
+
+            // Renamed files are recognized from their magic bytes
+            if !path.is_dir() {
+                let renamed = temp_dir.path().join(format!("{}.bin", name));
+                std::fs::copy(&path, &renamed).unwrap();
+                assert_eq!(registry.reader_for(&renamed).unwrap().name(), format, "{}", name);
+                // Split headers need their `.data` file next to them; the others are self-contained
+                if format != "Split" {
+                    assert_eq!(load_array(&renamed).unwrap().data(), array.data(), "{}", name);
+                }
+            }
+        }
+
+        // Split files without an extension still have a JSON header
+        let path = temp_dir.path().join("unnamed");
+        assert_eq!(registry.writer_for(&path).unwrap().name(), "Split");
+        save_array(&array, &path).unwrap();
+        assert_eq!(open_array(&path).unwrap().dimensions(), array.dimensions());
+        assert_eq!(read_frame(&path, 1, 0, 0, 1).unwrap(), array.get_frame(1, 0, 0, 1).unwrap());
+        assert!(registry.is_split(&path));
+        assert!(!registry.is_split(&temp_dir.path().join("a.npy")));
+        let info = registry.reader_for(&path).unwrap().info(&path).unwrap();
+        assert_eq!((info.dimensions, info.appending), (*array.dimensions(), false));
+        assert!(info.created_at.is_some());
+
+        // Other extensions are not silently written as split files
+        let error = save_array(&array, temp_dir.path().join("out.h5")).unwrap_err();
+        assert!(error.to_string().contains("Unsupported extension"), "{}", error);
+        assert!(!temp_dir.path().join("out.h5").exists());
+
+        std::fs::write(temp_dir.path().join("notes.txt"), "not an array").unwrap();
+        let error = load_array(temp_dir.path().join("notes.txt")).unwrap_err();
+        assert!(error.to_string().contains("Unrecognized file format"));
+        assert!(registry.reader_for(&temp_dir.path().join("missing")).is_err());
+    }
+
+    /// A format that only reads, registered next to the built-in ones
+    struct Constant;
+
+    impl Format for Constant {
+        fn name(&self) -> &'static str {
+            "Constant"
+        }
+
+        fn extensions(&self) -> &'static [&'static str] {
+            &["const"]
+        }
+    }
+
+    impl FormatReader<f32> for Constant {
+        fn load(&self, _path: &Path) -> Result<Array6D<f32>> {
+            ArrayGenerator::generate_test_array(1, 1, 1, 1, 2, 2)
+        }
+    }
+
+    #[test]
+    fn test_custom_format_and_filters() {
+        let registry = FormatRegistry::<f32>::builtin().with_reader(Arc::new(Constant));
+        let reader = registry.reader_for(Path::new("values.const")).unwrap();
+        assert_eq!(reader.load(Path::new("values.const")).unwrap().dimensions().width, 2);
+        assert!(reader.validate(Path::new("values.const")).is_ok());
+        assert_eq!(reader.info(Path::new("values.const")).unwrap().created_at, None);
+
+        let filters = registry.open_filters();
+        assert_eq!(filters[0], ("6D Data Files".to_string(), vec!["meta", "tif", "tiff", "npy", "npz", "json", "const"]));
+        assert_eq!(filters[2], ("OME-TIFF Files".to_string(), vec!["tif", "tiff"]));
+        assert!(filters.iter().all(|(name, _)| name != "OME-Zarr Files" && name != "Position Folder Files"));
+        assert_eq!(registry.save_filters()[0], ("Split Files".to_string(), vec!["meta"]));
+    }
+}
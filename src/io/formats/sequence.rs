@@ -0,0 +1,382 @@
+//! Image-sequence folder import
+//!
+//! Older experiments are stored as one grayscale TIFF or PNG file per frame,
+//! named like `pos03_t0042_c1.tif`. A regular expression with named captures
+//! `t`, `p`, `z` and `c` maps each file name onto its frame; axes without a
+//! capture have length 1. Captured values need not start at 0 or be
+//! contiguous: the sorted distinct values of each axis become its indices.
+
+use crate::io::array_6d::{Array6D, Dimensions, VoxelSize};
+use crate::io::dtype::{DataType, Pixel};
+use crate::io::provenance::ProvenanceEntry;
+use anyhow::{Result, anyhow};
+use ndarray::Array2;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::ColorType;
+
+/// Capture names of the T, P, Z and C axes
+const AXES: [&str; 4] = ["t", "p", "z", "c"];
+
+/// Number of missing frames listed in the completeness error
+const MISSING_REPORT_LIMIT: usize = 10;
+
+/// Frame files of a folder, indexed by the values captured from their names
+#[derive(Debug, Clone)]
+pub struct ImageSequence {
+    /// Folder and file name pattern, recorded in the provenance log
+    dir: PathBuf,
+    pattern: String,
+    /// Distinct captured values of the T, P, Z and C axes in ascending order
+    axis_values: [Vec<usize>; 4],
+    /// Files by captured (t, p, z, c) values
+    files: BTreeMap<[usize; 4], PathBuf>,
+    pixel_size_um: f64,
+    z_step_um: Option<f64>,
+    time_interval_s: f64,
+    channel_names: Option<Vec<String>>,
+}
+
+impl ImageSequence {
+    /// Find the files in `dir` whose whole name matches `pattern`
+    ///
+    /// `pattern` is a regular expression such as
+    /// `pos(?P<p>\d+)_t(?P<t>\d+)_c(?P<c>\d+)\.tif`; files that do not match
+    /// are ignored.
+    pub fn scan<P: AsRef<Path>>(dir: P, pattern: &str) -> Result<Self> {
+        let dir = dir.as_ref();
+        let regex = Regex::new(&format!("^(?:{})$", pattern))
+            .map_err(|e| anyhow!("Invalid file name pattern: {}", e))?;
+        if let Some(name) = regex.capture_names().flatten().find(|name| !AXES.contains(name)) {
+            return Err(anyhow!("Unknown capture '{}' in file name pattern (expected t, p, z or c)", name));
+        }
+
+        let mut files = BTreeMap::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else { continue };
+            let Some(captures) = regex.captures(name) else { continue };
+            if !path.is_file() {
+                continue;
+            }
+
+            let mut key = [0; 4];
+            for (value, axis) in key.iter_mut().zip(AXES) {
+                if let Some(capture) = captures.name(axis) {
+                    *value = capture.as_str().parse()
+                        .map_err(|_| anyhow!("Capture '{}' of {} is not a number: '{}'", axis, name, capture.as_str()))?;
+                }
+            }
+            if let Some(other) = files.insert(key, path.clone()) {
+                return Err(anyhow!(
+                    "{} and {} map to the same frame",
+                    other.display(), path.display()
+                ));
+            }
+        }
+        if files.is_empty() {
+            return Err(anyhow!("No files in {} match the pattern '{}'", dir.display(), pattern));
+        }
+
+        let axis_values = std::array::from_fn(|axis| {
+            let mut values: Vec<usize> = files.keys().map(|key| key[axis]).collect();
+            values.sort_unstable();
+            values.dedup();
+            values
+        });
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            pattern: pattern.to_string(),
+            axis_values,
+            files,
+            pixel_size_um: 1.0,
+            z_step_um: None,
+            time_interval_s: 1.0,
+            channel_names: None,
+        })
+    }
+
+    /// Set pixel size of the loaded array
+    pub fn pixel_size(mut self, size_um: f64) -> Self {
+        self.pixel_size_um = size_um;
+        self
+    }
+
+    /// Set step between z-planes of the loaded array (default the pixel size)
+    pub fn z_step(mut self, step_um: f64) -> Self {
+        self.z_step_um = Some(step_um);
+        self
+    }
+
+    /// Set time interval of the loaded array
+    pub fn time_interval(mut self, interval_s: f64) -> Self {
+        self.time_interval_s = interval_s;
+        self
+    }
+
+    /// Set channel names of the loaded array (default "Channel <c>" from the captured values)
+    pub fn channel_names(mut self, names: Vec<String>) -> Self {
+        self.channel_names = Some(names);
+        self
+    }
+
+    /// Get the distinct captured values of the T, P, Z and C axes
+    pub fn axis_values(&self) -> &[Vec<usize>; 4] {
+        &self.axis_values
+    }
+
+    /// Get number of matched files
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Get the captured (t, p, z, c) values of every frame without a file
+    pub fn missing(&self) -> Vec<[usize; 4]> {
+        let [ts, ps, zs, cs] = &self.axis_values;
+        let mut missing = Vec::new();
+        for &t in ts {
+            for &p in ps {
+                for &z in zs {
+                    for &c in cs {
+                        if !self.files.contains_key(&[t, p, z, c]) {
+                            missing.push([t, p, z, c]);
+                        }
+                    }
+                }
+            }
+        }
+        missing
+    }
+
+    /// Check that every (t, p, z, c) combination of the discovered extents has a file
+    pub fn check_complete(&self) -> Result<()> {
+        let missing = self.missing();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let listed: Vec<String> = missing.iter()
+            .take(MISSING_REPORT_LIMIT)
+            .map(|[t, p, z, c]| format!("t={} p={} z={} c={}", t, p, z, c))
+            .collect();
+        let more = missing.len().saturating_sub(MISSING_REPORT_LIMIT);
+        Err(anyhow!(
+            "Image sequence is incomplete: {} of {} frames are missing ({}{})",
+            missing.len(),
+            missing.len() + self.files.len(),
+            listed.join(", "),
+            if more > 0 { format!(", and {} more", more) } else { String::new() }
+        ))
+    }
+
+    /// Read all frames into an array after checking that the sequence is complete
+    ///
+    /// Every image must have the size and sample type of the first one.
+    pub fn load<T: Pixel>(&self) -> Result<Array6D<T>> {
+        self.check_complete()?;
+
+        let (first_key, first_path) = self.files.iter().next().ok_or_else(|| anyhow!("Image sequence is empty"))?;
+        let (data_type, first) = read_image::<T>(first_path)?;
+        let (height, width) = first.dim();
+
+        let [ts, ps, zs, cs] = &self.axis_values;
+        let dimensions = Dimensions::new(ts.len(), ps.len(), zs.len(), cs.len(), height, width);
+        let channel_names = match &self.channel_names {
+            Some(names) => names.clone(),
+            None => cs.iter().map(|c| format!("Channel {}", c)).collect(),
+        };
+        let mut array = Array6D::zeros(
+            dimensions,
+            self.pixel_size_um,
+            self.time_interval_s,
+            channel_names,
+            data_type.name().to_string(),
+        )?.with_voxel_size(VoxelSize::new(
+            self.pixel_size_um,
+            self.pixel_size_um,
+            self.z_step_um.unwrap_or(self.pixel_size_um),
+        ))?;
+
+        let index = |axis: usize, value: usize| self.axis_values[axis].binary_search(&value).unwrap_or_default();
+        for (key, path) in &self.files {
+            let frame = if key == first_key {
+                first.clone()
+            } else {
+                let (frame_type, frame) = read_image::<T>(path)?;
+                if frame_type != data_type || frame.dim() != (height, width) {
+                    return Err(anyhow!(
+                        "{} is a {}x{} {} image, expected {}x{} {} like {}",
+                        path.display(), frame.dim().1, frame.dim().0, frame_type,
+                        width, height, data_type, first_path.display()
+                    ));
+                }
+                frame
+            };
+            let [t, p, z, c] = std::array::from_fn(|axis| index(axis, key[axis]));
+            array.set_frame(t, p, z, c, &frame.view())?;
+        }
+
+        array.record(ProvenanceEntry::new("import_sequence")
+            .parameter("folder", self.dir.display())
+            .parameter("pattern", &self.pattern)
+            .parameter("files", self.files.len()));
+        Ok(array)
+    }
+}
+
+/// Load a folder of per-frame images whose names match `pattern` (see [`ImageSequence::scan`])
+pub fn load_sequence<T: Pixel, P: AsRef<Path>>(dir: P, pattern: &str) -> Result<Array6D<T>> {
+    ImageSequence::scan(dir, pattern)?.load()
+}
+
+/// Read the first plane of a grayscale TIFF or PNG file
+fn read_image<T: Pixel>(path: &Path) -> Result<(DataType, Array2<T>)> {
+    let is_png = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+    let (data_type, height, width, values) = if is_png { read_png(path)? } else { read_tiff(path)? };
+    Ok((data_type, Array2::from_shape_vec((height, width), values)?))
+}
+
+/// Convert decoded samples to pixel type `T`
+fn convert<S: Pixel, T: Pixel>(values: Vec<S>) -> Vec<T> {
+    values.into_iter().map(|v| T::from_f64(v.to_f64())).collect()
+}
+
+/// Decode the first IFD of a grayscale TIFF file
+fn read_tiff<T: Pixel>(path: &Path) -> Result<(DataType, usize, usize, Vec<T>)> {
+    let mut decoder = Decoder::new(BufReader::new(File::open(path)?))
+        .map_err(|e| anyhow!("Cannot read {} as TIFF: {}", path.display(), e))?;
+    if !matches!(decoder.colortype()?, ColorType::Gray(_)) {
+        return Err(anyhow!("{} is not a grayscale image", path.display()));
+    }
+
+    let (width, height) = decoder.dimensions()?;
+    let (data_type, values) = match decoder.read_image()? {
+        DecodingResult::U8(values) => (DataType::UInt8, convert(values)),
+        DecodingResult::U16(values) => (DataType::UInt16, convert(values)),
+        DecodingResult::U32(values) => (DataType::UInt32, convert(values)),
+        DecodingResult::F32(values) => (DataType::Float32, convert(values)),
+        DecodingResult::F64(values) => (DataType::Float64, convert(values)),
+        _ => return Err(anyhow!("{} has an unsupported sample format", path.display())),
+    };
+    Ok((data_type, height as usize, width as usize, values))
+}
+
+/// Decode a grayscale PNG file (bit depths below 8 are expanded to 8)
+fn read_png<T: Pixel>(path: &Path) -> Result<(DataType, usize, usize, Vec<T>)> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info()
+        .map_err(|e| anyhow!("Cannot read {} as PNG: {}", path.display(), e))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    buffer.truncate(info.buffer_size());
+
+    if info.color_type != png::ColorType::Grayscale {
+        return Err(anyhow!("{} is not a grayscale image", path.display()));
+    }
+    let (data_type, values) = match info.bit_depth {
+        png::BitDepth::Sixteen => {
+            // PNG samples are big-endian
+            let values = buffer.chunks_exact(2).map(|b| u16::from_be_bytes([b[0], b[1]])).collect();
+            (DataType::UInt16, convert(values))
+        }
+        _ => (DataType::UInt8, convert(buffer)),
+    };
+    Ok((data_type, info.height as usize, info.width as usize, values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiff::encoder::{colortype, TiffEncoder};
+
+    /// Write a 3x2 uint16 TIFF whose pixels all equal `value`
+    fn write_tiff(path: &Path, value: u16) {
+        let mut tiff = TiffEncoder::new(File::create(path).unwrap()).unwrap();
+        tiff.write_image::<colortype::Gray16>(3, 2, &[value; 6]).unwrap();
+    }
+
+    /// Write a 3x2 grayscale PNG whose pixels all equal `value`
+    fn write_png(path: &Path, value: u16, sixteen_bit: bool) {
+        let mut encoder = png::Encoder::new(File::create(path).unwrap(), 3, 2);
+        encoder.set_color(png::ColorType::Grayscale);
+        let data: Vec<u8> = if sixteen_bit {
+            encoder.set_depth(png::BitDepth::Sixteen);
+            (0..6).flat_map(|_| value.to_be_bytes()).collect()
+        } else {
+            encoder.set_depth(png::BitDepth::Eight);
+            vec![value as u8; 6]
+        };
+        encoder.write_header().unwrap().write_image_data(&data).unwrap();
+    }
+
+    #[test]
+    fn test_sequence_import() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for p in [3, 7] {
+            for t in 0..3 {
+                for c in 1..=2 {
+                    let value = (p * 100 + t * 10 + c) as u16;
+                    write_tiff(&temp_dir.path().join(format!("pos{:02}_t{:04}_c{}.tif", p, t, c)), value);
+                }
+            }
+        }
+        std::fs::write(temp_dir.path().join("notes.txt"), "not a frame").unwrap();
+
+        let pattern = r"pos(?P<p>\d+)_t(?P<t>\d+)_c(?P<c>\d+)\.tif";
+        let sequence = ImageSequence::scan(temp_dir.path(), pattern).unwrap();
+        assert_eq!(sequence.file_count(), 12);
+        assert_eq!(sequence.axis_values(), &[vec![0, 1, 2], vec![3, 7], vec![0], vec![1, 2]]);
+        assert!(sequence.missing().is_empty());
+
+        let array = sequence.pixel_size(0.65).load::<u16>().unwrap();
+        assert_eq!(*array.dimensions(), Dimensions::new(3, 2, 1, 2, 2, 3));
+        assert_eq!(array.data_type(), "uint16");
+        assert_eq!(array.pixel_size_um(), 0.65);
+        assert_eq!(array.channel_names(), ["Channel 1", "Channel 2"]);
+        assert_eq!(array.get_frame(2, 1, 0, 0).unwrap()[[1, 2]], 721);
+        assert_eq!(array.get_frame(0, 0, 0, 1).unwrap()[[0, 0]], 302);
+    }
+
+    #[test]
+    fn test_png_sequence() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for z in 0..2 {
+            write_png(&temp_dir.path().join(format!("z{}.png", z)), 1000 + z, true);
+        }
+        let array = load_sequence::<f32, _>(temp_dir.path(), r"z(?P<z>\d)\.png").unwrap();
+        assert_eq!(*array.dimensions(), Dimensions::new(1, 1, 2, 1, 2, 3));
+        assert_eq!(array.data_type(), "uint16");
+        assert_eq!(array.get_frame(0, 0, 1, 0).unwrap()[[1, 1]], 1001.0);
+
+        // Mixed sample types are rejected
+        write_png(&temp_dir.path().join("z2.png"), 7, false);
+        let error = load_sequence::<f32, _>(temp_dir.path(), r"z(?P<z>\d)\.png").unwrap_err();
+        assert!(error.to_string().contains("uint8"), "{}", error);
+    }
+
+    #[test]
+    fn test_incomplete_sequence() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for (t, c) in [(0, 0), (0, 1), (1, 0), (2, 1)] {
+            write_tiff(&temp_dir.path().join(format!("t{}_c{}.tif", t, c)), 1);
+        }
+
+        let sequence = ImageSequence::scan(temp_dir.path(), r"t(?P<t>\d+)_c(?P<c>\d+)\.tif").unwrap();
+        assert_eq!(sequence.missing(), vec![[1, 0, 0, 1], [2, 0, 0, 0]]);
+        let error = sequence.load::<u16>().unwrap_err().to_string();
+        assert!(error.contains("2 of 6 frames are missing"), "{}", error);
+        assert!(error.contains("t=1 p=0 z=0 c=1"), "{}", error);
+
+        // Typos in capture names, unmatched patterns and ambiguous names are errors
+        assert!(ImageSequence::scan(temp_dir.path(), r"t(?P<time>\d+)_c\d+\.tif").is_err());
+        assert!(ImageSequence::scan(temp_dir.path(), r"frame\d+\.png").is_err());
+        write_tiff(&temp_dir.path().join("t00_c0.tif"), 1);
+        assert!(ImageSequence::scan(temp_dir.path(), r"t(?P<t>\d+)_c(?P<c>\d+)\.tif").is_err());
+    }
+}
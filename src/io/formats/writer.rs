@@ -0,0 +1,349 @@
+//! Streaming writer for split files of a running acquisition
+//!
+//! A [`SplitWriter`] creates a raw split file with fixed P/Z/C/Y/X extents
+//! and appends frames in TPZCYX order. Each completed time point is synced
+//! to disk before the `.meta` is replaced with one counting it, so readers
+//! (and the survivors of a crash) always see a consistent prefix of whole
+//! time points.
+
+use super::atomic::{remove_if_exists, replace_metadata, CommitWriter};
+use super::ArrayMetadata;
+use crate::io::array_6d::{Dimensions, VoxelSize};
+use crate::io::channels::ChannelMetadata;
+use crate::io::dtype::{DataType, Pixel, SampleEncoding};
+use crate::io::positions::PositionMetadata;
+use crate::io::provenance::ProvenanceEntry;
+use crate::io::verify::{ChecksumKind, FrameChecksums};
+use anyhow::{Result, anyhow};
+use ndarray::ArrayView2;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Appends time points to a raw split file while keeping it readable
+#[derive(Debug)]
+pub struct SplitWriter {
+    path: PathBuf,
+    metadata: ArrayMetadata,
+    encoding: SampleEncoding,
+    data_file: CommitWriter,
+    /// Frames written of the time point in progress
+    pending_frames: usize,
+    /// Checksums of every written frame, including the time point in progress
+    pending_checksums: Option<FrameChecksums>,
+    buffer: Vec<u8>,
+}
+
+impl SplitWriter {
+    /// Create a split file at `path` (the `.meta`) with a growing time axis
+    ///
+    /// `dimensions.time` is ignored. Until the first time point is complete
+    /// there is no `.meta`, so the dataset cannot be opened yet.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        dimensions: Dimensions,
+        pixel_size_um: f64,
+        time_interval_s: f64,
+        channel_names: Vec<String>,
+        data_type: DataType,
+    ) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let dimensions = Dimensions { time: 1, ..dimensions };
+        dimensions.validate_extents()?;
+        if channel_names.len() != dimensions.channel {
+            return Err(anyhow!(
+                "Number of channel names ({}) does not match channel dimension ({})",
+                channel_names.len(), dimensions.channel
+            ));
+        }
+
+        let mut metadata = ArrayMetadata::new(
+            Dimensions { time: 0, ..dimensions },
+            pixel_size_um,
+            time_interval_s,
+            channel_names,
+            data_type,
+        );
+        metadata.appending = true;
+        metadata.provenance.push(ProvenanceEntry::new("split_writer").parameter("data_type", data_type));
+        let encoding = metadata.encoding()?;
+
+        // A stale .meta must not describe the new .data file
+        remove_if_exists(&path)?;
+        let data_file = CommitWriter::create(&metadata.data_path(&path))?;
+
+        Ok(Self {
+            path,
+            buffer: Vec::with_capacity(metadata.frame_size()?),
+            metadata,
+            encoding,
+            data_file,
+            pending_frames: 0,
+            pending_checksums: None,
+        })
+    }
+
+    /// Store a checksum of every frame for [`verify_file`](crate::io::verify::verify_file)
+    pub fn frame_checksums(mut self, kind: ChecksumKind) -> Self {
+        self.pending_checksums = Some(FrameChecksums::new(kind));
+        self
+    }
+
+    /// Add an entry to the provenance log, e.g. the acquisition software and its settings
+    pub fn record(mut self, entry: ProvenanceEntry) -> Self {
+        self.metadata.provenance.push(entry);
+        self
+    }
+
+    /// Set separate X/Y pixel sizes and the z-step
+    pub fn voxel_size(mut self, voxel_size: VoxelSize) -> Result<Self> {
+        voxel_size.validate()?;
+        self.metadata.pixel_size_um = voxel_size.x_um;
+        self.metadata.pixel_size_y_um = Some(voxel_size.y_um);
+        self.metadata.z_step_um = Some(voxel_size.z_um);
+        Ok(self)
+    }
+
+    /// Set the acquisition settings of every channel
+    pub fn channels(mut self, channels: Vec<ChannelMetadata>) -> Result<Self> {
+        self.metadata.dimensions.check_channels(&channels)?;
+        self.metadata.channels = channels;
+        Ok(self)
+    }
+
+    /// Set the stage coordinates and wells of every position
+    pub fn positions(mut self, positions: Vec<PositionMetadata>) -> Result<Self> {
+        self.metadata.dimensions.check_positions(&positions)?;
+        self.metadata.positions = positions;
+        Ok(self)
+    }
+
+    /// Set the time of the next time point in seconds since the first
+    ///
+    /// Call before the first frame of each time point. Either every time
+    /// point gets a timestamp or none does; readers otherwise assume time
+    /// points `time_interval_s` apart.
+    pub fn timestamp_next(&mut self, seconds: f64) -> Result<()> {
+        if self.pending_frames > 0 {
+            return Err(anyhow!("Timestamps must be set before the first frame of a time point"));
+        }
+        let time = self.time_points();
+        let timestamps = match &mut self.metadata.timestamps_s {
+            Some(timestamps) => timestamps,
+            None if time == 0 => self.metadata.timestamps_s.insert(Vec::new()),
+            None => return Err(anyhow!("Earlier time points were written without timestamps")),
+        };
+        if timestamps.len() > time {
+            return Err(anyhow!("Time point {} already has a timestamp", time));
+        }
+        if !seconds.is_finite() || timestamps.last().is_some_and(|&last| seconds < last) {
+            return Err(anyhow!("Timestamp {} s is not after the previous time point", seconds));
+        }
+        timestamps.push(seconds);
+        Ok(())
+    }
+
+    /// Get number of complete time points
+    pub fn time_points(&self) -> usize {
+        self.metadata.dimensions.time
+    }
+
+    /// Get the (p, z, c) indices of the next frame to append
+    pub fn next_frame(&self) -> (usize, usize, usize) {
+        let (_, p, z, c) = self.metadata.dimensions.frame_coords(self.pending_frames);
+        (p, z, c)
+    }
+
+    /// Append the next frame in TPZCYX order
+    ///
+    /// The `.meta` is updated whenever this completes a time point.
+    pub fn append_frame<T: Pixel>(&mut self, frame: ArrayView2<T>) -> Result<()> {
+        let dims = &self.metadata.dimensions;
+        if frame.dim() != (dims.height, dims.width) {
+            return Err(anyhow!(
+                "Frame shape {:?} does not match {}x{}",
+                frame.shape(), dims.height, dims.width
+            ));
+        }
+        // Checked before writing, so a forgotten timestamp can still be set
+        let time = self.time_points();
+        if self.pending_frames == 0 && self.metadata.timestamps_s.as_ref().is_some_and(|ts| ts.len() <= time) {
+            return Err(anyhow!("Time point {} has no timestamp; set it before its first frame", time));
+        }
+
+        self.buffer.clear();
+        self.encoding.encode(frame.iter(), &mut self.buffer);
+        self.data_file.write_all(&self.buffer)?;
+        if let Some(checksums) = &mut self.pending_checksums {
+            checksums.push(&self.buffer);
+        }
+
+        self.pending_frames += 1;
+        if self.pending_frames == dims.position * dims.z * dims.channel {
+            self.pending_frames = 0;
+            self.metadata.dimensions.time += 1;
+            self.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Finish the acquisition and mark the file as complete
+    ///
+    /// Fails if a time point is only partly written; the file then keeps
+    /// the complete time points.
+    pub fn finish(mut self) -> Result<ArrayMetadata> {
+        if self.pending_frames > 0 {
+            return Err(anyhow!(
+                "Time point {} is incomplete ({} frames written)",
+                self.time_points(), self.pending_frames
+            ));
+        }
+        if self.time_points() == 0 {
+            return Err(anyhow!("No time point was written"));
+        }
+
+        self.metadata.appending = false;
+        self.commit()?;
+        Ok(self.metadata)
+    }
+
+    /// Sync the data, then atomically replace the `.meta` with the current state
+    fn commit(&mut self) -> Result<()> {
+        if let Some(timestamps) = &self.metadata.timestamps_s {
+            self.metadata.dimensions.check_timestamps(timestamps)?;
+        }
+        self.metadata.commit = Some(self.data_file.sync()?);
+        self.metadata.frame_checksums = self.pending_checksums.clone();
+
+        // Readers see either the old or the new .meta, never a partial one
+        replace_metadata(&self.path, &self.metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::formats::{load_array, open_array, read_frame, validate_file};
+    use crate::io::generators::ArrayGenerator;
+
+    fn create_writer(path: &Path) -> SplitWriter {
+        SplitWriter::create(
+            path,
+            Dimensions::new(0, 2, 1, 2, 6, 4),
+            0.5,
+            30.0,
+            vec!["Phase".to_string(), "GFP".to_string()],
+            DataType::UInt16,
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_append_and_read_while_writing() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("live.meta");
+        let source = ArrayGenerator::generate_test_array(3, 2, 1, 2, 6, 4).unwrap();
+        let mut writer = create_writer(&path);
+
+        // Nothing can be opened before the first time point is complete
+        assert_eq!(writer.next_frame(), (0, 0, 0));
+        writer.append_frame(source.get_frame(0, 0, 0, 0).unwrap().view()).unwrap();
+        assert_eq!(writer.next_frame(), (0, 0, 1));
+        assert!(validate_file(&path).is_err());
+
+        // Two and a half time points: readers see the first two
+        for t in 0..3 {
+            for p in 0..2 {
+                for c in 0..2 {
+                    if (t, p, c) == (0, 0, 0) || (t == 2 && p == 1) {
+                        continue;
+                    }
+                    writer.append_frame(source.get_frame(t, p, 0, c).unwrap().view()).unwrap();
+                }
+            }
+        }
+        assert_eq!(writer.time_points(), 2);
+
+        let metadata = validate_file(&path).unwrap();
+        assert!(metadata.appending);
+        assert_eq!(metadata.dimensions, Dimensions::new(2, 2, 1, 2, 6, 4));
+        let expected = source.get_frame(1, 1, 0, 1).unwrap().mapv(|v| v.round());
+        assert_eq!(open_array(&path).unwrap().get_frame(1, 1, 0, 1).unwrap(), expected);
+        assert_eq!(load_array(&path).unwrap().get_frame(1, 1, 0, 1).unwrap(), expected);
+        assert_eq!(read_frame(&path, 1, 1, 0, 1).unwrap(), expected);
+        assert!(read_frame(&path, 2, 0, 0, 0).is_err());
+
+        // Finishing with a partial time point fails but keeps the prefix,
+        // just like a crash would
+        assert!(writer.finish().is_err());
+        assert_eq!(load_array(&path).unwrap().dimensions().time, 2);
+    }
+
+    #[test]
+    fn test_finish() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("done.meta");
+        let source = ArrayGenerator::generate_test_array(2, 2, 1, 2, 6, 4).unwrap();
+        let mut writer = create_writer(&path).frame_checksums(ChecksumKind::Crc32);
+
+        assert!(writer.append_frame(ndarray::Array2::<u16>::zeros((4, 6)).view()).is_err());
+        for t in 0..2 {
+            for p in 0..2 {
+                for c in 0..2 {
+                    writer.append_frame(source.get_frame(t, p, 0, c).unwrap().view()).unwrap();
+                }
+            }
+        }
+        let metadata = writer.finish().unwrap();
+        assert!(!metadata.appending);
+
+        let metadata = validate_file(&path).unwrap();
+        assert!(!metadata.appending);
+        assert_eq!(metadata.dimensions.time, 2);
+        assert_eq!(metadata.channel_names, ["Phase", "GFP"]);
+        assert!(!path.with_extension("meta.tmp").exists());
+        assert!(crate::io::formats::atomic::verify_commit(&path).is_ok());
+        assert!(crate::io::verify::verify_file(&path).unwrap().is_ok());
+        assert_eq!(metadata.frame_checksums.unwrap().values.len(), 8);
+        assert_eq!(load_array(&path).unwrap().data().unwrap(), source.data().unwrap().mapv(|v| v.round()));
+    }
+
+    #[test]
+    fn test_timestamps_and_provenance() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("irregular.meta");
+        let frame = ndarray::Array2::<u16>::zeros((6, 4));
+        let mut writer = create_writer(&path)
+            .voxel_size(VoxelSize::new(0.5, 0.5, 1.2)).unwrap()
+            .record(ProvenanceEntry::new("acquire").tool("scope-control 2.1").parameter("exposure_ms", 50));
+
+        for (t, seconds) in [0.0, 30.0, 95.5].into_iter().enumerate() {
+            if t > 0 {
+                assert!(writer.append_frame(frame.view()).is_err());
+            }
+            writer.timestamp_next(seconds).unwrap();
+            assert!(writer.timestamp_next(seconds).is_err());
+            for i in 0..4 {
+                if t == 0 && i > 0 {
+                    assert!(writer.timestamp_next(1.0).is_err());
+                }
+                writer.append_frame(frame.view()).unwrap();
+            }
+        }
+        assert!(writer.timestamp_next(90.0).is_err());
+        writer.finish().unwrap();
+
+        let array = open_array(&path).unwrap();
+        assert_eq!(array.timestamps_s(), Some(&[0.0, 30.0, 95.5][..]));
+        assert_eq!(array.time_axis_s(), [0.0, 30.0, 95.5]);
+        assert_eq!(array.voxel_size().z_um, 1.2);
+        let operations: Vec<&str> = array.provenance().iter().map(|e| e.operation.as_str()).collect();
+        assert_eq!(operations, ["split_writer", "acquire"]);
+        assert_eq!(array.provenance()[1].tool, "scope-control 2.1");
+
+        // Without timestamps from the start, none can be added later
+        let mut writer = create_writer(&temp_dir.path().join("regular.meta"));
+        for _ in 0..4 {
+            writer.append_frame(frame.view()).unwrap();
+        }
+        assert!(writer.timestamp_next(10.0).is_err());
+    }
+}
@@ -3,6 +3,7 @@
 //! Provides various pattern generators for testing and demonstration purposes.
 
 use super::array_6d::{Array6D, Dimensions};
+use super::dtype::DataType;
 use ndarray::Array6;
 use anyhow::{Result, anyhow};
 
@@ -114,6 +115,8 @@ impl ArrayGenerator {
             ));
         }
         
+        let data_type: DataType = config.data_type.parse()?;
+        
         // Create data array
         let mut data = Array6::zeros(dims.shape());
         
@@ -129,6 +132,9 @@ impl ArrayGenerator {
             )?;
         }
         
+        // Values must be representable in the declared data type
+        data.mapv_inplace(|v| data_type.quantize(v));
+        
         // Extract channel names
         let channel_names: Vec<String> = config.channel_patterns
             .into_iter()
@@ -180,6 +186,7 @@ impl ArrayGenerator {
     }
     
     /// Generate pixel value based on pattern type
+    #[allow(clippy::too_many_arguments)]
     fn generate_pixel_value(
         pattern: PatternType,
         t: usize, _p: usize, _z: usize, x: usize, y: usize,
@@ -387,7 +394,7 @@ mod tests {
         for i in 0..10 {
             for j in 0..10 {
                 let value = frame[[i, j]];
-                assert!(value >= 50.0 && value <= 150.0);
+                assert!((50.0..=150.0).contains(&value));
             }
         }
         
@@ -4,13 +4,48 @@
 //! - Generating 6D arrays with specific patterns
 //! - Loading and saving 6D arrays to/from disk
 //! - Converting between different formats
+//! - Extracting and cropping sub-arrays
+//! - Concatenating datasets along T, P or C
+//! - Building multi-resolution pyramids for zoomed-out viewing
 //! - Validating array structures
 
 pub mod array_6d;
+pub mod axes;
+pub mod channels;
+pub mod chunked;
+pub mod concat;
+pub mod dtype;
 pub mod generators;
 pub mod formats;
+pub mod positions;
+pub mod provenance;
+pub mod pyramid;
+pub mod subset;
+pub mod verify;
 
 #[allow(unused_imports)] // Re-exported for external API
 pub use array_6d::Array6D;
+pub use axes::AxisSpec;
+pub use channels::{ChannelMetadata, ChannelRole, Color};
+pub use chunked::Compression;
+pub use concat::{concatenate, concatenate_files, concatenate_time_at, ConcatAxis};
+pub use dtype::{ByteOrder, DataType, Pixel, SampleEncoding};
 pub use generators::{ArrayGenerator, PatternType};
-pub use formats::{save_array, load_array, validate_file};
\ No newline at end of file
+pub use formats::{
+    save_array, save_array_in_order, save_array_with, save_array_with_checksums, load_array, load_array_as, open_array, open_array_as,
+    read_frame, read_frame_as, validate_file, SplitFormat,
+};
+pub use formats::ome_tiff::{save_tiff, PositionLayout, TiffFlavor};
+pub use formats::ome_zarr::save_zarr;
+pub use formats::npy::{load_npy, load_npz, open_npy, save_npy, save_npz};
+pub use formats::sequence::{load_sequence, ImageSequence};
+pub use formats::raw::RawImport;
+pub use formats::multi_position::{open_positions, PositionManifest};
+pub use formats::registry::{FileInfo, Format, FormatReader, FormatRegistry, FormatWriter};
+pub use formats::writer::SplitWriter;
+pub use formats::atomic::{recover_file, verify_commit, Recovery};
+pub use positions::{group_by_well, PositionMetadata};
+pub use provenance::ProvenanceEntry;
+pub use pyramid::{build_pyramid, open_level, pyramid_levels, read_frame_for_display, Downsampling};
+pub use subset::{extract_file, AxisSelection, Roi, Selection};
+pub use verify::{verify_file, ChecksumKind, VerifyReport};
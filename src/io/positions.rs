@@ -0,0 +1,120 @@
+//! Stage coordinates and plate layout of positions
+//!
+//! Each position (P index) of an array can record where the stage was and,
+//! for multi-well plates, which well and which field within the well it
+//! images. Positions of the same well can then be analysed together.
+
+use serde::{Serialize, Deserialize};
+use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
+
+/// Stage coordinates and well assignment of one position
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PositionMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stage_x_um: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stage_y_um: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stage_z_um: Option<f64>,
+    /// Well ID, e.g. `B03`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub well: Option<String>,
+    /// Field (site) number within the well
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<usize>,
+}
+
+impl PositionMetadata {
+    /// Position in `well` at `field`
+    pub fn in_well(well: &str, field: usize) -> Self {
+        Self { well: Some(well.to_string()), field: Some(field), ..Default::default() }
+    }
+
+    /// Set stage coordinates in micrometers
+    pub fn at(mut self, x_um: f64, y_um: f64, z_um: Option<f64>) -> Self {
+        self.stage_x_um = Some(x_um);
+        self.stage_y_um = Some(y_um);
+        self.stage_z_um = z_um;
+        self
+    }
+
+    /// Check that nothing was recorded
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Get a display label such as `B03 field 2`, if a well or field is known
+    pub fn label(&self) -> Option<String> {
+        match (&self.well, self.field) {
+            (Some(well), Some(field)) => Some(format!("{} field {}", well, field)),
+            (Some(well), None) => Some(well.clone()),
+            (None, Some(field)) => Some(format!("Field {}", field)),
+            (None, None) => None,
+        }
+    }
+
+    /// Check that coordinates are finite and the well ID is a row letter and column number
+    pub fn validate(&self) -> Result<()> {
+        let coordinates = [self.stage_x_um, self.stage_y_um, self.stage_z_um];
+        if coordinates.iter().flatten().any(|v| !v.is_finite()) {
+            return Err(anyhow!("Stage coordinates must be finite"));
+        }
+        if let Some(well) = &self.well {
+            let column = well.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+            if column.len() == well.len() || column.is_empty() || !column.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(anyhow!("Invalid well ID '{}', expected a row letter and column number like B03", well));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Group position indices by well, in well order
+///
+/// Positions without a well are left out.
+pub fn group_by_well(positions: &[PositionMetadata]) -> BTreeMap<String, Vec<usize>> {
+    let mut wells: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (p, position) in positions.iter().enumerate() {
+        if let Some(well) = &position.well {
+            wells.entry(well.clone()).or_default().push(p);
+        }
+    }
+    wells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position_metadata() {
+        let position = PositionMetadata::in_well("B03", 2).at(1250.5, -300.0, None);
+        assert_eq!(position.label().as_deref(), Some("B03 field 2"));
+        assert_eq!(PositionMetadata { field: Some(4), ..Default::default() }.label().as_deref(), Some("Field 4"));
+        assert_eq!(PositionMetadata::default().label(), None);
+
+        let json = serde_json::to_string(&position).unwrap();
+        assert_eq!(json, r#"{"stage_x_um":1250.5,"stage_y_um":-300.0,"well":"B03","field":2}"#);
+        assert_eq!(serde_json::from_str::<PositionMetadata>(&json).unwrap(), position);
+
+        assert!(position.validate().is_ok());
+        for well in ["03", "B", "B3x", ""] {
+            assert!(PositionMetadata::in_well(well, 1).validate().is_err(), "{}", well);
+        }
+        assert!(PositionMetadata::default().at(f64::NAN, 0.0, None).validate().is_err());
+    }
+
+    #[test]
+    fn test_group_by_well() {
+        let positions = vec![
+            PositionMetadata::in_well("B03", 1),
+            PositionMetadata::in_well("A01", 1),
+            PositionMetadata::default(),
+            PositionMetadata::in_well("B03", 2),
+        ];
+        let wells = group_by_well(&positions);
+        assert_eq!(wells.keys().collect::<Vec<_>>(), ["A01", "B03"]);
+        assert_eq!(wells["B03"], [0, 3]);
+    }
+}
@@ -0,0 +1,89 @@
+//! Creation times and the provenance log of saved arrays
+//!
+//! Every file records when it was written and an append-only log of the
+//! operations that produced it: imports, acquisitions and saves, each with
+//! the tool version and its parameters. Arrays carry the log of the file
+//! they were read from, so saving them again extends it.
+
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::SystemTime;
+
+/// Get the current time as an RFC 3339 UTC timestamp, e.g. `2024-05-01T12:30:00Z`
+pub fn now_rfc3339() -> String {
+    humantime::format_rfc3339_seconds(SystemTime::now()).to_string()
+}
+
+/// Name and version of this library as recorded in provenance entries
+pub fn tool_name() -> String {
+    format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
+
+/// One operation in the history of a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProvenanceEntry {
+    /// When the operation ran (RFC 3339, UTC)
+    pub timestamp: String,
+    /// Program that ran it, with its version
+    pub tool: String,
+    /// Operation name, e.g. `import_sequence` or `save_array`
+    pub operation: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<String, String>,
+}
+
+impl ProvenanceEntry {
+    /// Record `operation` run now by this library
+    pub fn new(operation: &str) -> Self {
+        Self {
+            timestamp: now_rfc3339(),
+            tool: tool_name(),
+            operation: operation.to_string(),
+            parameters: BTreeMap::new(),
+        }
+    }
+
+    /// Set tool that ran the operation (default this library)
+    pub fn tool(mut self, tool: &str) -> Self {
+        self.tool = tool.to_string();
+        self
+    }
+
+    /// Add a parameter of the operation
+    pub fn parameter(mut self, name: &str, value: impl ToString) -> Self {
+        self.parameters.insert(name.to_string(), value.to_string());
+        self
+    }
+}
+
+impl fmt::Display for ProvenanceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} ({})", self.timestamp, self.operation, self.tool)?;
+        for (i, (name, value)) in self.parameters.iter().enumerate() {
+            write!(f, "{}{}={}", if i == 0 { ": " } else { ", " }, name, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provenance_entry() {
+        let timestamp = now_rfc3339();
+        assert!(humantime::parse_rfc3339(&timestamp).is_ok(), "{}", timestamp);
+        assert!(timestamp.ends_with('Z'));
+
+        let entry = ProvenanceEntry::new("import_raw")
+            .parameter("offset", 512)
+            .parameter("axes", "TCYX");
+        assert!(entry.tool.starts_with("pyama-rust "));
+        assert_eq!(entry.to_string(), format!("{} import_raw ({}): axes=TCYX, offset=512", entry.timestamp, entry.tool));
+
+        let json = serde_json::to_string(&ProvenanceEntry { parameters: BTreeMap::new(), ..entry }).unwrap();
+        assert!(!json.contains("parameters"));
+    }
+}
@@ -0,0 +1,343 @@
+//! Multi-resolution pyramids for fast zoomed-out viewing
+//!
+//! A pyramid holds every frame downsampled by 2×, 4×, 8×, … next to the
+//! full-resolution data. Split files keep each level as a split file in
+//! `<name>.pyramid/<factor>x.meta` and list the factors in their `.meta`;
+//! OME-Zarr stores add the levels as further datasets of every image's
+//! multiscales. Readers pick the smallest level that still fills the
+//! size a frame is displayed at.
+
+use super::array_6d::{Array6D, Dimensions, FrameSource, VoxelSize};
+use super::dtype::{ByteOrder, Pixel, SampleEncoding};
+use super::formats::atomic::replace_metadata;
+use super::formats::ome_zarr::{is_zarr, open_zarr_level, write_zarr_level, zarr_level_factors};
+use super::formats::registry::FormatRegistry;
+use super::formats::{open_array_as, read_metadata, save_array_with, ArrayMetadata, SplitFormat};
+use super::provenance::ProvenanceEntry;
+use anyhow::{Result, anyhow};
+use ndarray::{s, Array2, ArrayView2};
+use std::borrow::Cow;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// How a block of pixels is reduced to one pixel of the next level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Downsampling {
+    /// Average of the block, for intensity images
+    #[default]
+    Mean,
+    /// Top-left pixel of the block, which keeps label values intact
+    Nearest,
+}
+
+impl Downsampling {
+    /// Get the name used in provenance logs
+    pub fn name(self) -> &'static str {
+        match self {
+            Downsampling::Mean => "mean",
+            Downsampling::Nearest => "nearest",
+        }
+    }
+}
+
+impl fmt::Display for Downsampling {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Downsample a frame by `factor` in Y and X
+///
+/// Frames whose extent is not a multiple of `factor` get a last row and
+/// column made of the partial blocks.
+pub fn downsample_frame<T: Pixel>(frame: ArrayView2<T>, factor: usize, method: Downsampling) -> Array2<T> {
+    let (height, width) = frame.dim();
+    Array2::from_shape_fn((height.div_ceil(factor), width.div_ceil(factor)), |(y, x)| {
+        let (y0, x0) = (y * factor, x * factor);
+        let block = frame.slice(s![y0..(y0 + factor).min(height), x0..(x0 + factor).min(width)]);
+        match method {
+            Downsampling::Mean => T::from_f64(block.iter().map(|v| v.to_f64()).sum::<f64>() / block.len() as f64),
+            Downsampling::Nearest => block[[0, 0]],
+        }
+    })
+}
+
+/// Downsample every frame of an array by `factor` in Y and X
+///
+/// Frames are downsampled as they are read, so the source is never
+/// loaded as a whole.
+pub fn downsample<T: Pixel>(array: &Array6D<T>, factor: usize, method: Downsampling) -> Result<Array6D<T>> {
+    if factor < 2 {
+        return Err(anyhow!("Downsampling factor must be at least 2, got {}", factor));
+    }
+    let source_dims = array.dimensions();
+    let dimensions = Dimensions {
+        height: source_dims.height.div_ceil(factor),
+        width: source_dims.width.div_ceil(factor),
+        ..*source_dims
+    };
+    let voxel_size = array.voxel_size();
+    let encoding = SampleEncoding::new(T::DATA_TYPE, ByteOrder::Little);
+    let source = DownsampledFrames { array: array.clone(), factor, method, encoding };
+
+    let mut level = Array6D::from_source(
+        Arc::new(source),
+        encoding,
+        dimensions,
+        voxel_size.x_um * factor as f64,
+        array.time_interval_s(),
+        array.channel_names().to_vec(),
+        array.data_type().to_string(),
+    )?
+    .with_voxel_size(VoxelSize::new(voxel_size.x_um * factor as f64, voxel_size.y_um * factor as f64, voxel_size.z_um))?
+    .with_channels(array.channels().to_vec())?
+    .with_positions(array.positions().to_vec())?
+    .with_provenance(array.provenance().to_vec());
+    if let Some(timestamps) = array.timestamps_s() {
+        level = level.with_timestamps(timestamps.to_vec())?;
+    }
+    level.record(ProvenanceEntry::new("downsample").parameter("factor", factor).parameter("method", method));
+    Ok(level)
+}
+
+/// Frames of an array downsampled on access
+#[derive(Debug)]
+struct DownsampledFrames<T: Pixel> {
+    array: Array6D<T>,
+    factor: usize,
+    method: Downsampling,
+    /// Encoding of the pixel type, so downsampled values pass through unchanged
+    encoding: SampleEncoding,
+}
+
+impl<T: Pixel> FrameSource for DownsampledFrames<T> {
+    fn frame_bytes(&self, index: usize) -> Result<Cow<'_, [u8]>> {
+        let (t, p, z, c) = self.array.dimensions().frame_coords(index);
+        let frame = downsample_frame(self.array.get_frame(t, p, z, c)?.view(), self.factor, self.method);
+        let mut bytes = Vec::with_capacity(frame.len() * self.encoding.size());
+        self.encoding.encode(frame.iter(), &mut bytes);
+        Ok(Cow::Owned(bytes))
+    }
+}
+
+/// Get the `.meta` file of the pyramid level of split file `path` downsampled by `factor`
+pub fn split_level_path(path: &Path, factor: usize) -> PathBuf {
+    path.with_extension("pyramid").join(format!("{}x.meta", factor))
+}
+
+/// Compute `levels` pyramid levels (2×, 4×, …) and store them with a split file or OME-Zarr store
+///
+/// Each level is computed from the previous one, frame by frame. Existing
+/// levels are replaced. The coarsest factor may not exceed the frame's
+/// height or width. Returns the downsampling factor of every level,
+/// starting with 1 for full resolution.
+pub fn build_pyramid<P: AsRef<Path>>(path: P, levels: usize, method: Downsampling) -> Result<Vec<usize>> {
+    let path = path.as_ref();
+    let full = open_array_as::<f64, _>(path)?;
+    let dims = full.dimensions();
+    let extent = dims.height.min(dims.width);
+    if levels == 0 {
+        return Err(anyhow!("A pyramid needs at least one level"));
+    }
+    if levels > extent.ilog2() as usize {
+        return Err(anyhow!(
+            "{} levels downsample {}x{} frames below one pixel; at most {} fit",
+            levels, dims.height, dims.width, extent.ilog2()
+        ));
+    }
+    let factors: Vec<usize> = (0..=levels).map(|level| 1 << level).collect();
+
+    if is_zarr(path) {
+        let mut previous = full;
+        for (index, &factor) in factors.iter().enumerate().skip(1) {
+            write_zarr_level(path, &downsample(&previous, 2, method)?, index, factor)?;
+            previous = open_zarr_level(path, index)?;
+        }
+        return Ok(factors);
+    }
+
+    let mut metadata = split_metadata(path)?
+        .ok_or_else(|| anyhow!("Pyramids can only be stored with split files and OME-Zarr stores"))?;
+    if metadata.appending {
+        return Err(anyhow!("{:?} is still being written", path));
+    }
+    // Forget the old levels before deleting them, so an interrupted rebuild
+    // leaves a file without a pyramid rather than one listing missing levels
+    if !metadata.pyramid.is_empty() {
+        metadata.pyramid.clear();
+        replace_metadata(path, &metadata)?;
+    }
+    let pyramid_dir = path.with_extension("pyramid");
+    if pyramid_dir.exists() {
+        std::fs::remove_dir_all(&pyramid_dir)?;
+    }
+    std::fs::create_dir_all(&pyramid_dir)?;
+
+    let mut previous = full;
+    for &factor in &factors[1..] {
+        let level_path = split_level_path(path, factor);
+        save_array_with(&downsample(&previous, 2, method)?, &level_path, SplitFormat::Raw)?;
+        previous = open_array_as(&level_path)?;
+    }
+
+    // Record the levels only once all of them are written
+    metadata.pyramid = factors[1..].to_vec();
+    metadata.provenance.push(ProvenanceEntry::new("build_pyramid").parameter("levels", levels).parameter("method", method));
+    replace_metadata(path, &metadata)?;
+    Ok(factors)
+}
+
+/// Get the `.meta` description of `path` if it is a split file
+fn split_metadata(path: &Path) -> Result<Option<ArrayMetadata>> {
+    if FormatRegistry::<f32>::builtin().is_split(path) {
+        read_metadata(path).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Get the downsampling factor of every stored level, starting with 1 for full resolution
+///
+/// Files without a pyramid only have the full-resolution level.
+pub fn pyramid_levels<P: AsRef<Path>>(path: P) -> Result<Vec<usize>> {
+    let path = path.as_ref();
+    if is_zarr(path) {
+        return zarr_level_factors(path);
+    }
+    let pyramid = split_metadata(path)?.map(|metadata| metadata.pyramid).unwrap_or_default();
+    Ok(std::iter::once(1).chain(pyramid).collect())
+}
+
+/// Open the level of `path` downsampled by `factor`; 1 opens the full-resolution data
+pub fn open_level<T: Pixel, P: AsRef<Path>>(path: P, factor: usize) -> Result<Array6D<T>> {
+    let path = path.as_ref();
+    if factor == 1 {
+        return open_array_as(path);
+    }
+    let levels = pyramid_levels(path)?;
+    let index = levels.iter().position(|&f| f == factor)
+        .ok_or_else(|| anyhow!("{:?} has no {}x pyramid level (levels: {:?})", path, factor, levels))?;
+    if is_zarr(path) {
+        open_zarr_level(path, index)
+    } else {
+        open_array_as(split_level_path(path, factor))
+    }
+}
+
+/// Pick the coarsest level that still fills a `width`×`height` display with frames of `dimensions`
+///
+/// Frames are assumed to be scaled to fit the display, keeping their
+/// aspect ratio; a level fills it if it has at least one pixel per
+/// displayed pixel.
+pub fn level_for_display(factors: &[usize], dimensions: &Dimensions, width: usize, height: usize) -> usize {
+    let scale = (width as f64 / dimensions.width as f64).min(height as f64 / dimensions.height as f64);
+    factors.iter()
+        .copied()
+        .filter(|&factor| factor as f64 * scale <= 1.0)
+        .max()
+        .unwrap_or(1)
+}
+
+/// Read frame (t, p, z, c) from the coarsest level that fills a `width`×`height` display
+///
+/// Returns the frame and the downsampling factor of its level, to map
+/// frame pixels back to full-resolution coordinates.
+pub fn read_frame_for_display<T: Pixel, P: AsRef<Path>>(
+    path: P,
+    (t, p, z, c): (usize, usize, usize, usize),
+    width: usize,
+    height: usize,
+) -> Result<(Array2<T>, usize)> {
+    let path = path.as_ref();
+    let dimensions = *open_array_as::<T, _>(path)?.dimensions();
+    let factor = level_for_display(&pyramid_levels(path)?, &dimensions, width, height);
+    let frame = open_level::<T, _>(path, factor)?.get_frame(t, p, z, c)?.into_owned();
+    Ok((frame, factor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::formats::{load_array, save_array, validate_file};
+    use crate::io::generators::ArrayGenerator;
+
+    #[test]
+    fn test_downsample_frame() {
+        let frame = Array2::from_shape_fn((5, 4), |(y, x)| (y * 4 + x) as u16);
+        let mean = downsample_frame(frame.view(), 2, Downsampling::Mean);
+        assert_eq!(mean.dim(), (3, 2));
+        assert_eq!(mean[[0, 0]], 3); // (0 + 1 + 4 + 5) / 4 rounded
+        assert_eq!(mean[[2, 1]], 19); // partial block (18 + 19) / 2 rounded
+        let nearest = downsample_frame(frame.view(), 4, Downsampling::Nearest);
+        assert_eq!(nearest, ndarray::arr2(&[[0], [16]]));
+
+        let factors = [1, 2, 4, 8];
+        let dims = Dimensions::new(1, 1, 1, 1, 2048, 2048);
+        assert_eq!(level_for_display(&factors, &dims, 2048, 2048), 1);
+        assert_eq!(level_for_display(&factors, &dims, 600, 300), 4);
+        assert_eq!(level_for_display(&factors, &dims, 100, 100), 8);
+    }
+
+    #[test]
+    fn test_split_pyramid() {
+        let array = ArrayGenerator::generate_test_array(2, 2, 1, 2, 32, 24).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("overview.meta");
+        save_array(&array, &path).unwrap();
+        assert_eq!(pyramid_levels(&path).unwrap(), [1]);
+
+        assert_eq!(build_pyramid(&path, 3, Downsampling::Mean).unwrap(), [1, 2, 4, 8]);
+        assert_eq!(pyramid_levels(&path).unwrap(), [1, 2, 4, 8]);
+        assert_eq!(validate_file(&path).unwrap().provenance.last().unwrap().operation, "build_pyramid");
+        assert!(split_level_path(&path, 4).is_file());
+
+        let level = open_level::<f32, _>(&path, 4).unwrap();
+        assert_eq!(*level.dimensions(), Dimensions::new(2, 2, 1, 2, 8, 6));
+        assert_eq!(level.voxel_size().x_um, 4.0 * array.voxel_size().x_um);
+        assert_eq!(level.data_type(), array.data_type());
+        // Every level is stored as uint16, so each one is rounded before the next is computed
+        let half = downsample_frame(array.get_frame(1, 1, 0, 1).unwrap().view(), 2, Downsampling::Mean).mapv(f32::round);
+        let expected = downsample_frame(half.view(), 2, Downsampling::Mean).mapv(f32::round);
+        assert_eq!(level.get_frame(1, 1, 0, 1).unwrap(), expected);
+        assert!(open_level::<f32, _>(&path, 16).is_err());
+
+        let (frame, factor) = read_frame_for_display::<f32, _>(&path, (1, 1, 0, 1), 10, 10).unwrap();
+        assert_eq!(factor, 2);
+        assert_eq!(frame.dim(), (16, 12));
+
+        // Rebuilding replaces the levels; the data itself is untouched
+        assert_eq!(build_pyramid(&path, 1, Downsampling::Nearest).unwrap(), [1, 2]);
+        assert!(!split_level_path(&path, 4).exists());
+        assert_eq!(load_array(&path).unwrap().data(), array.data());
+        assert!(build_pyramid(&path, 0, Downsampling::Mean).is_err());
+        // 24 pixels wide frames fit 16x but not 32x
+        let error = build_pyramid(&path, 5, Downsampling::Mean).unwrap_err();
+        assert!(error.to_string().contains("at most 4 fit"), "{}", error);
+        assert!(build_pyramid(&path, 64, Downsampling::Mean).is_err());
+        assert_eq!(pyramid_levels(&path).unwrap(), [1, 2]);
+    }
+
+    #[test]
+    fn test_zarr_pyramid() {
+        let array = ArrayGenerator::generate_test_array(2, 2, 2, 1, 16, 16).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("overview.zarr");
+        save_array(&array, &path).unwrap();
+        assert_eq!(pyramid_levels(&path).unwrap(), [1]);
+
+        assert_eq!(build_pyramid(&path, 2, Downsampling::Mean).unwrap(), [1, 2, 4]);
+        assert_eq!(pyramid_levels(&path).unwrap(), [1, 2, 4]);
+        assert!(path.join("1/2/.zarray").is_file());
+
+        let level = open_level::<f32, _>(&path, 2).unwrap();
+        assert_eq!(*level.dimensions(), Dimensions::new(2, 2, 2, 1, 8, 8));
+        assert_eq!(level.voxel_size().y_um, 2.0 * array.voxel_size().y_um);
+        let expected = downsample_frame(array.get_frame(1, 1, 1, 0).unwrap().view(), 2, Downsampling::Mean);
+        assert_eq!(level.get_frame(1, 1, 1, 0).unwrap(), expected.mapv(f32::round));
+        assert_eq!(load_array(&path).unwrap().data(), array.data());
+
+        let (frame, factor) = read_frame_for_display::<f32, _>(&path, (0, 0, 0, 0), 4, 4).unwrap();
+        assert_eq!((factor, frame.dim()), (4, (4, 4)));
+    }
+}
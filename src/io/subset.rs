@@ -0,0 +1,380 @@
+//! Sub-array extraction and cropping
+//!
+//! A [`Selection`] picks time points, positions, z-slices and channels by
+//! range or index list and optionally crops every frame to a region of
+//! interest. [`Array6D::select`](super::array_6d::Array6D::select) applies
+//! it to an array; [`extract_file`] streams a selection of a file on disk
+//! into a new file without loading the source.
+
+use super::array_6d::{Dimensions, FrameSource};
+use super::formats::{open_array_as, save_array};
+use super::provenance::ProvenanceEntry;
+use anyhow::{Result, anyhow};
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::{Range, RangeInclusive};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Indices picked along one axis
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum AxisSelection {
+    /// Every index
+    #[default]
+    All,
+    /// A half-open range of indices
+    Range(Range<usize>),
+    /// Indices in the order listed
+    Indices(Vec<usize>),
+}
+
+impl AxisSelection {
+    /// Get the selected indices of an axis of length `len`
+    fn resolve(&self, axis: &str, len: usize) -> Result<Vec<usize>> {
+        let indices: Vec<usize> = match self {
+            AxisSelection::All => (0..len).collect(),
+            AxisSelection::Range(range) => range.clone().collect(),
+            AxisSelection::Indices(indices) => indices.clone(),
+        };
+        if indices.is_empty() {
+            return Err(anyhow!("Selection of {} {} is empty", axis, self));
+        }
+        if let Some(&index) = indices.iter().find(|&&i| i >= len) {
+            return Err(anyhow!("{} index {} out of bounds (max: {})", axis, index, len - 1));
+        }
+        Ok(indices)
+    }
+}
+
+impl From<Range<usize>> for AxisSelection {
+    fn from(range: Range<usize>) -> Self {
+        AxisSelection::Range(range)
+    }
+}
+
+impl From<RangeInclusive<usize>> for AxisSelection {
+    fn from(range: RangeInclusive<usize>) -> Self {
+        AxisSelection::Range(*range.start()..*range.end() + 1)
+    }
+}
+
+impl From<Vec<usize>> for AxisSelection {
+    fn from(indices: Vec<usize>) -> Self {
+        AxisSelection::Indices(indices)
+    }
+}
+
+impl From<&[usize]> for AxisSelection {
+    fn from(indices: &[usize]) -> Self {
+        AxisSelection::Indices(indices.to_vec())
+    }
+}
+
+impl<const N: usize> From<[usize; N]> for AxisSelection {
+    fn from(indices: [usize; N]) -> Self {
+        AxisSelection::Indices(indices.to_vec())
+    }
+}
+
+impl From<usize> for AxisSelection {
+    fn from(index: usize) -> Self {
+        AxisSelection::Indices(vec![index])
+    }
+}
+
+impl fmt::Display for AxisSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AxisSelection::All => f.write_str("all"),
+            AxisSelection::Range(range) => write!(f, "{}..{}", range.start, range.end),
+            AxisSelection::Indices(indices) => {
+                let indices: Vec<String> = indices.iter().map(|i| i.to_string()).collect();
+                write!(f, "[{}]", indices.join(", "))
+            }
+        }
+    }
+}
+
+/// Rectangular region of interest in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Roi {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Roi {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// Check that the region is not empty and lies within a frame
+    fn check(&self, dimensions: &Dimensions) -> Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Err(anyhow!("Region of interest {} is empty", self));
+        }
+        if self.x + self.width > dimensions.width || self.y + self.height > dimensions.height {
+            return Err(anyhow!(
+                "Region of interest {} exceeds the {}x{} frame",
+                self, dimensions.width, dimensions.height
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check whether the region covers the whole frame
+    fn is_full_frame(&self, dimensions: &Dimensions) -> bool {
+        (self.width, self.height) == (dimensions.width, dimensions.height)
+    }
+}
+
+impl fmt::Display for Roi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{} at ({}, {})", self.width, self.height, self.x, self.y)
+    }
+}
+
+/// Time points, positions, z-slices and channels to extract, and an optional crop
+///
+/// Axes that are not set keep every index.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selection {
+    pub time: AxisSelection,
+    pub position: AxisSelection,
+    pub z: AxisSelection,
+    pub channel: AxisSelection,
+    /// Region of each frame to keep; `None` keeps the whole frame
+    pub roi: Option<Roi>,
+}
+
+impl Selection {
+    /// Select the whole array
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Select time points, which must be listed in increasing order
+    pub fn time(mut self, time: impl Into<AxisSelection>) -> Self {
+        self.time = time.into();
+        self
+    }
+
+    /// Select positions
+    pub fn positions(mut self, positions: impl Into<AxisSelection>) -> Self {
+        self.position = positions.into();
+        self
+    }
+
+    /// Select z-slices
+    pub fn z(mut self, z: impl Into<AxisSelection>) -> Self {
+        self.z = z.into();
+        self
+    }
+
+    /// Select channels
+    pub fn channels(mut self, channels: impl Into<AxisSelection>) -> Self {
+        self.channel = channels.into();
+        self
+    }
+
+    /// Crop every frame to `roi`
+    pub fn crop(mut self, roi: Roi) -> Self {
+        self.roi = Some(roi);
+        self
+    }
+
+    /// Get the dimensions of the selection from an array of `dimensions`
+    pub fn dimensions(&self, dimensions: &Dimensions) -> Result<Dimensions> {
+        Ok(self.resolve(dimensions)?.dimensions)
+    }
+
+    /// Resolve the selection against the dimensions of the source array
+    pub(crate) fn resolve(&self, dimensions: &Dimensions) -> Result<Subset> {
+        let roi = self.roi.unwrap_or(Roi::new(0, 0, dimensions.width, dimensions.height));
+        roi.check(dimensions)?;
+        let time = self.time.resolve("Time", dimensions.time)?;
+        // Time points keep their order so the selection stays a time series
+        if let Some(w) = time.windows(2).find(|w| w[1] <= w[0]) {
+            return Err(anyhow!("Time indices must be increasing, got {} after {}", w[1], w[0]));
+        }
+        let position = self.position.resolve("Position", dimensions.position)?;
+        let z = self.z.resolve("Z", dimensions.z)?;
+        let channel = self.channel.resolve("Channel", dimensions.channel)?;
+        let selected = Dimensions::new(time.len(), position.len(), z.len(), channel.len(), roi.height, roi.width);
+        Ok(Subset { time, position, z, channel, roi, source: *dimensions, dimensions: selected })
+    }
+
+    /// Describe the selection for the provenance log
+    pub(crate) fn provenance(&self) -> ProvenanceEntry {
+        let mut entry = ProvenanceEntry::new("select");
+        let axes = [("time", &self.time), ("position", &self.position), ("z", &self.z), ("channel", &self.channel)];
+        for (name, axis) in axes {
+            if *axis != AxisSelection::All {
+                entry = entry.parameter(name, axis);
+            }
+        }
+        if let Some(roi) = self.roi {
+            entry = entry.parameter("roi", roi);
+        }
+        entry
+    }
+}
+
+/// Selection resolved to indices of a source array
+#[derive(Debug, Clone)]
+pub(crate) struct Subset {
+    pub time: Vec<usize>,
+    pub position: Vec<usize>,
+    pub z: Vec<usize>,
+    pub channel: Vec<usize>,
+    pub roi: Roi,
+    /// Dimensions of the source array
+    pub source: Dimensions,
+    /// Dimensions of the selection
+    pub dimensions: Dimensions,
+}
+
+impl Subset {
+    /// Get the source coordinates of frame `index` of the selection
+    pub fn source_coords(&self, index: usize) -> (usize, usize, usize, usize) {
+        let (t, p, z, c) = self.dimensions.frame_coords(index);
+        (self.time[t], self.position[p], self.z[z], self.channel[c])
+    }
+}
+
+/// Frames of `source` picked and cropped by a selection, read on demand
+#[derive(Debug)]
+pub(crate) struct SelectedFrames {
+    source: Arc<dyn FrameSource>,
+    subset: Subset,
+    /// Size of one encoded sample in bytes
+    sample_size: usize,
+}
+
+impl SelectedFrames {
+    pub fn new(source: Arc<dyn FrameSource>, subset: Subset, sample_size: usize) -> Self {
+        Self { source, subset, sample_size }
+    }
+}
+
+impl FrameSource for SelectedFrames {
+    fn frame_bytes(&self, index: usize) -> Result<Cow<'_, [u8]>> {
+        let (t, p, z, c) = self.subset.source_coords(index);
+        let bytes = self.source.frame_bytes(self.subset.source.frame_index(t, p, z, c))?;
+        let roi = self.subset.roi;
+        if roi.is_full_frame(&self.subset.source) {
+            return Ok(bytes);
+        }
+
+        let row_size = roi.width * self.sample_size;
+        let mut cropped = Vec::with_capacity(roi.height * row_size);
+        for y in roi.y..roi.y + roi.height {
+            let start = (y * self.subset.source.width + roi.x) * self.sample_size;
+            cropped.extend_from_slice(&bytes[start..start + row_size]);
+        }
+        Ok(Cow::Owned(cropped))
+    }
+}
+
+/// Extract a selection of a file into a new file, e.g. to share part of a dataset
+///
+/// The source is opened without loading it, so only the selected frames
+/// are read, one at a time. Samples keep their data type; f64 pixels hold
+/// every type exactly.
+pub fn extract_file<P: AsRef<Path>, Q: AsRef<Path>>(source: P, output: Q, selection: &Selection) -> Result<()> {
+    let array = open_array_as::<f64, _>(source)?.select(selection)?;
+    save_array(&array, output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::array_6d::Array6D;
+    use crate::io::formats::{load_array, open_array, validate_file};
+    use crate::io::generators::ArrayGenerator;
+    use crate::io::positions::PositionMetadata;
+
+    #[test]
+    fn test_resolve() {
+        let dims = Dimensions::new(10, 8, 1, 3, 16, 12);
+        let selection = Selection::all()
+            .time(2..=5)
+            .positions(3..8)
+            .channels([0, 2])
+            .crop(Roi::new(4, 2, 8, 10));
+        assert_eq!(selection.dimensions(&dims).unwrap(), Dimensions::new(4, 5, 1, 2, 10, 8));
+        let subset = selection.resolve(&dims).unwrap();
+        assert_eq!(subset.source_coords(0), (2, 3, 0, 0));
+        assert_eq!(subset.source_coords(subset.dimensions.frame_count() - 1), (5, 7, 0, 2));
+
+        let entry = selection.provenance();
+        assert_eq!(entry.parameters["time"], "2..6");
+        assert_eq!(entry.parameters["channel"], "[0, 2]");
+        assert_eq!(entry.parameters["roi"], "8x10 at (4, 2)");
+        assert!(!entry.parameters.contains_key("z"));
+
+        assert!(Selection::all().time(5..5).dimensions(&dims).is_err());
+        assert!(Selection::all().channels(3).dimensions(&dims).is_err());
+        assert!(Selection::all().crop(Roi::new(8, 0, 8, 16)).dimensions(&dims).is_err());
+        assert!(Selection::all().crop(Roi::new(0, 0, 0, 16)).dimensions(&dims).is_err());
+    }
+
+    #[test]
+    fn test_select() {
+        let array = ArrayGenerator::generate_test_array(4, 3, 1, 2, 8, 6).unwrap()
+            .with_timestamps(vec![0.0, 10.0, 25.0, 40.0]).unwrap()
+            .with_positions((0..3).map(|p| PositionMetadata::in_well("A01", p + 1)).collect()).unwrap();
+        let selection = Selection::all().time([1, 3]).positions(1..3).channels(1).crop(Roi::new(2, 1, 3, 4));
+        let selected = array.select(&selection).unwrap();
+
+        assert_eq!(*selected.dimensions(), Dimensions::new(2, 2, 1, 1, 4, 3));
+        assert_eq!(selected.channel_names(), &array.channel_names()[1..]);
+        assert_eq!(selected.timestamps_s(), Some(&[0.0, 30.0][..]));
+        assert_eq!(selected.positions()[0].field, Some(2));
+        assert_eq!(selected.provenance().last().unwrap().operation, "select");
+        let source = array.get_frame(3, 2, 0, 1).unwrap();
+        assert_eq!(selected.get_frame(1, 1, 0, 0).unwrap(), source.slice(ndarray::s![1..5, 2..5]));
+
+        // Evenly spaced time points of regular acquisitions keep a time interval
+        let regular = ArrayGenerator::generate_test_array(6, 1, 1, 1, 4, 4).unwrap();
+        let every_other = regular.select(&Selection::all().time([1, 3, 5])).unwrap();
+        assert_eq!(every_other.timestamps_s(), None);
+        assert_eq!(every_other.time_interval_s(), 2.0 * regular.time_interval_s());
+        let irregular = regular.select(&Selection::all().time([0, 1, 4])).unwrap();
+        assert_eq!(irregular.timestamps_s().unwrap().len(), 3);
+        assert!(regular.select(&Selection::all().time([3, 1])).is_err());
+        assert!(regular.select(&Selection::all().time([1, 1])).is_err());
+
+        // Without a time interval, time points are still picked in order only
+        let dims = Dimensions::new(4, 1, 1, 1, 4, 4);
+        let snapshot = Array6D::<u16>::new(
+            ndarray::Array6::zeros(dims.shape()), dims, 0.5, 0.0, vec!["DAPI".to_string()], "uint16".to_string(),
+        ).unwrap();
+        let error = snapshot.select(&Selection::all().time([3, 1])).unwrap_err();
+        assert!(error.to_string().contains("increasing"), "{}", error);
+        assert_eq!(snapshot.select(&Selection::all().time([0, 2])).unwrap().dimensions().time, 2);
+    }
+
+    #[test]
+    fn test_extract_file() {
+        let array = ArrayGenerator::generate_test_array(5, 3, 2, 2, 12, 10).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source_path = temp_dir.path().join("source.meta");
+        crate::io::formats::save_array(&array, &source_path).unwrap();
+
+        let selection = Selection::all().time(1..4).positions([2, 0]).z(1).crop(Roi::new(3, 2, 5, 6));
+        let opened = open_array(&source_path).unwrap().select(&selection).unwrap();
+        assert!(opened.is_lazy());
+        let expected = array.select(&selection).unwrap();
+        assert_eq!(opened.materialize().unwrap().data(), expected.data());
+
+        let output = temp_dir.path().join("extract.meta");
+        extract_file(&source_path, &output, &selection).unwrap();
+        assert_eq!(validate_file(&output).unwrap().data_type, array.data_type());
+        let extracted = load_array(&output).unwrap();
+        assert_eq!(extracted.data(), expected.data());
+        let operations: Vec<&str> = extracted.provenance().iter().map(|e| e.operation.as_str()).collect();
+        assert_eq!(operations, ["save_array", "select", "save_array"]);
+    }
+}
@@ -0,0 +1,333 @@
+//! Per-frame checksums and integrity checks of split files
+//!
+//! Files saved with [`save_array_with_checksums`](super::formats::save_array_with_checksums)
+//! (or a [`SplitWriter`](super::formats::writer::SplitWriter) with checksums
+//! enabled) store one checksum per (t, p, z, c) frame in the `.meta`. The
+//! checksum covers the encoded, uncompressed frame bytes, so it does not
+//! depend on the chunk compression. [`verify_file`] reads every frame and
+//! names the ones that no longer match.
+
+use super::formats::{read_metadata, ArrayMetadata};
+use anyhow::{Result, anyhow};
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Hash function of per-frame checksums
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChecksumKind {
+    /// CRC32 (IEEE), as used by zip and PNG
+    #[serde(rename = "crc32")]
+    Crc32,
+    /// 64-bit xxHash with seed 0, faster on large frames
+    #[serde(rename = "xxh64")]
+    XxHash64,
+}
+
+impl ChecksumKind {
+    /// Get the name used in `.meta` files
+    pub fn name(self) -> &'static str {
+        match self {
+            ChecksumKind::Crc32 => "crc32",
+            ChecksumKind::XxHash64 => "xxh64",
+        }
+    }
+
+    /// Compute the checksum of one encoded frame
+    pub fn checksum(self, frame: &[u8]) -> u64 {
+        match self {
+            ChecksumKind::Crc32 => crc32fast::hash(frame) as u64,
+            ChecksumKind::XxHash64 => xxhash_rust::xxh64::xxh64(frame, 0),
+        }
+    }
+}
+
+impl FromStr for ChecksumKind {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "crc32" => Ok(ChecksumKind::Crc32),
+            "xxh64" | "xxhash" | "xxhash64" => Ok(ChecksumKind::XxHash64),
+            _ => Err(anyhow!("Unknown checksum '{}' (expected crc32 or xxh64)", name)),
+        }
+    }
+}
+
+impl fmt::Display for ChecksumKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Checksums of every frame of a split file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrameChecksums {
+    pub algorithm: ChecksumKind,
+    /// One checksum per frame in the order frames are stored in the `.data` file
+    pub values: Vec<u64>,
+}
+
+impl FrameChecksums {
+    /// Create an empty list of checksums
+    pub fn new(algorithm: ChecksumKind) -> Self {
+        Self { algorithm, values: Vec::new() }
+    }
+
+    /// Add the checksum of the next encoded frame
+    pub fn push(&mut self, frame: &[u8]) {
+        self.values.push(self.algorithm.checksum(frame));
+    }
+
+    /// Check that there is one checksum per frame
+    pub fn validate(&self, frame_count: usize) -> Result<()> {
+        if self.values.len() != frame_count {
+            return Err(anyhow!(
+                "Expected {} frame checksums, got {}",
+                frame_count, self.values.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A frame that failed verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorruptFrame {
+    pub t: usize,
+    pub p: usize,
+    pub z: usize,
+    pub c: usize,
+    pub reason: String,
+}
+
+/// Outcome of [`verify_file`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Checksum stored per frame, if any
+    pub checksum: Option<ChecksumKind>,
+    /// Number of frames read
+    pub frames_checked: usize,
+    /// Frames that could not be read or whose checksum differs
+    pub corrupt_frames: Vec<CorruptFrame>,
+    /// Problems not tied to a single frame, e.g. a wrong file size
+    pub file_errors: Vec<String>,
+}
+
+impl VerifyReport {
+    /// Check whether no problem was found
+    pub fn is_ok(&self) -> bool {
+        self.corrupt_frames.is_empty() && self.file_errors.is_empty()
+    }
+}
+
+impl fmt::Display for VerifyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let checksum = self.checksum.map_or("no per-frame checksums".to_string(), |kind| format!("{} checksums", kind));
+        writeln!(f, "{} frames checked ({})", self.frames_checked, checksum)?;
+        for error in &self.file_errors {
+            writeln!(f, "  file: {}", error)?;
+        }
+        for frame in &self.corrupt_frames {
+            writeln!(f, "  t={} p={} z={} c={}: {}", frame.t, frame.p, frame.z, frame.c, frame.reason)?;
+        }
+        if self.is_ok() {
+            writeln!(f, "  OK")?;
+        }
+        Ok(())
+    }
+}
+
+/// Read every frame of a split file and report the corrupt ones
+///
+/// Frames are checked against their stored checksums; compressed frames
+/// that fail to decompress count as corrupt as well. Files without
+/// per-frame checksums still get the whole-file commit checksum, which
+/// detects corruption without locating it. Errors are returned only when
+/// the `.meta` or `.data` file cannot be opened at all.
+pub fn verify_file<P: AsRef<Path>>(path: P) -> Result<VerifyReport> {
+    let path = path.as_ref();
+    let metadata = read_metadata(path)?;
+    let data_path = metadata.data_path(path);
+    let file = File::open(&data_path)?;
+    let file_size = file.metadata()?.len();
+
+    let mut report = VerifyReport {
+        checksum: metadata.frame_checksums.as_ref().map(|c| c.algorithm),
+        ..Default::default()
+    };
+    if let Err(e) = metadata.check_commit_size(&data_path, file_size) {
+        report.file_errors.push(e.to_string());
+    }
+    // Frames are located through the chunk index, so a damaged one leaves
+    // nothing to check; a truncated file is still checked frame by frame
+    if let Some(Err(e)) = metadata.chunks.as_ref().map(|chunks| chunks.check_offsets(metadata.dimensions.frame_count())) {
+        report.file_errors.push(e.to_string());
+        return Ok(report);
+    }
+    // read_metadata has checked that there is one checksum per frame
+    let checksums = metadata.frame_checksums.as_ref();
+
+    let mut reader = BufReader::new(file);
+    let mut commit_hasher = crc32fast::Hasher::new();
+    let mut all_read = true;
+    for index in 0..metadata.dimensions.frame_count() {
+        report.frames_checked += 1;
+        let problem = match read_stored_frame(&metadata, &mut reader, index) {
+            Ok(stored) => {
+                commit_hasher.update(&stored);
+                decode_stored_frame(&metadata, stored).and_then(|frame| match checksums {
+                    Some(checksums) if checksums.algorithm.checksum(&frame) != checksums.values[index] => {
+                        Err(anyhow!("{} checksum differs", checksums.algorithm))
+                    }
+                    _ => Ok(()),
+                }).err()
+            }
+            Err(e) => {
+                all_read = false;
+                Some(e)
+            }
+        };
+
+        if let Some(problem) = problem {
+            let (t, p, z, c) = metadata.stored_order().stored_frame_coords(&metadata.dimensions, index);
+            report.corrupt_frames.push(CorruptFrame { t, p, z, c, reason: problem.to_string() });
+        }
+    }
+
+    // Without per-frame checksums only the commit can tell that something changed
+    if let (Some(commit), true, None) = (metadata.commit, all_read, checksums) {
+        let crc32 = commit_hasher.finalize();
+        if crc32 != commit.crc32 {
+            report.file_errors.push(format!(
+                "checksum {:08x} differs from the committed {:08x}; the file has no per-frame checksums to locate the corrupt frames",
+                crc32, commit.crc32
+            ));
+        }
+    }
+    Ok(report)
+}
+
+/// Read the bytes of frame `index` as stored in the `.data` file
+fn read_stored_frame(metadata: &ArrayMetadata, reader: &mut BufReader<File>, index: usize) -> Result<Vec<u8>> {
+    let (start, size) = match &metadata.chunks {
+        Some(chunks) => {
+            let range = chunks.chunk_range(index);
+            (range.start as u64, range.len())
+        }
+        None => {
+            let frame_size = metadata.frame_size()?;
+            ((index * frame_size) as u64, frame_size)
+        }
+    };
+
+    let mut stored = vec![0u8; size];
+    reader.seek(SeekFrom::Start(metadata.data_offset + start))?;
+    reader.read_exact(&mut stored).map_err(|_| anyhow!("frame is missing from the truncated data file"))?;
+    Ok(stored)
+}
+
+/// Turn stored frame bytes into encoded frame bytes
+fn decode_stored_frame(metadata: &ArrayMetadata, stored: Vec<u8>) -> Result<Vec<u8>> {
+    match &metadata.chunks {
+        Some(chunks) => chunks.compression.decompress(&stored, metadata.frame_size()?),
+        None => Ok(stored),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::formats::{save_array, save_array_with_checksums, validate_file, SplitFormat};
+    use crate::io::generators::ArrayGenerator;
+    use crate::io::Compression;
+    use std::io::{Seek, Write};
+
+    /// Overwrite one byte of the data file at `offset`
+    fn flip_byte(path: &Path, offset: u64) {
+        let mut file = std::fs::OpenOptions::new().read(true).write(true).open(path).unwrap();
+        let mut byte = [0u8];
+        file.seek(SeekFrom::Start(offset)).unwrap();
+        file.read_exact(&mut byte).unwrap();
+        file.seek(SeekFrom::Start(offset)).unwrap();
+        file.write_all(&[byte[0] ^ 0xFF]).unwrap();
+    }
+
+    #[test]
+    fn test_checksum_kind() {
+        assert_eq!("CRC32".parse::<ChecksumKind>().unwrap(), ChecksumKind::Crc32);
+        assert_eq!("xxhash".parse::<ChecksumKind>().unwrap(), ChecksumKind::XxHash64);
+        assert!("md5".parse::<ChecksumKind>().is_err());
+        assert_eq!(serde_json::to_string(&ChecksumKind::XxHash64).unwrap(), "\"xxh64\"");
+        assert_eq!(ChecksumKind::Crc32.checksum(b"123456789"), 0xCBF43926);
+    }
+
+    #[test]
+    fn test_locate_corrupt_frame() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let array = ArrayGenerator::generate_test_array(2, 2, 1, 2, 8, 8).unwrap();
+        for (name, kind) in [("crc.meta", ChecksumKind::Crc32), ("xxh.meta", ChecksumKind::XxHash64)] {
+            let path = temp_dir.path().join(name);
+            save_array_with_checksums(&array, &path, SplitFormat::Raw, kind).unwrap();
+            let report = verify_file(&path).unwrap();
+            assert!(report.is_ok(), "{}", report);
+            assert_eq!(report.checksum, Some(kind));
+            assert_eq!(report.frames_checked, 8);
+
+            // Frame 5 is (t=1, p=0, z=0, c=1)
+            let frame_size = validate_file(&path).unwrap().frame_size().unwrap() as u64;
+            flip_byte(&path.with_extension("data"), 5 * frame_size + 3);
+            let report = verify_file(&path).unwrap();
+            assert_eq!(report.corrupt_frames.len(), 1, "{}", report);
+            let frame = &report.corrupt_frames[0];
+            assert_eq!((frame.t, frame.p, frame.z, frame.c), (1, 0, 0, 1));
+            assert!(report.file_errors.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_verify_chunked_and_unchecked() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let array = ArrayGenerator::generate_test_array(2, 1, 1, 2, 8, 8).unwrap();
+
+        // Compressed frames are checked after decompression
+        let path = temp_dir.path().join("lz4.meta");
+        save_array_with_checksums(&array, &path, SplitFormat::Chunked(Compression::Lz4), ChecksumKind::Crc32).unwrap();
+        assert!(verify_file(&path).unwrap().is_ok());
+        let chunks = validate_file(&path).unwrap().chunks.unwrap();
+        flip_byte(&path.with_extension("data"), chunks.offsets[2] + 1);
+        let report = verify_file(&path).unwrap();
+        assert_eq!(report.corrupt_frames.len(), 1, "{}", report);
+        assert_eq!(report.corrupt_frames[0].c, 0);
+        assert_eq!(report.corrupt_frames[0].t, 1);
+
+        // A chunk index that misses frames is reported, not followed
+        let meta: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let mut damaged = meta.clone();
+        damaged["chunks"]["offsets"].as_array_mut().unwrap().truncate(2);
+        std::fs::write(&path, damaged.to_string()).unwrap();
+        let report = verify_file(&path).unwrap();
+        assert_eq!(report.frames_checked, 0);
+        assert!(report.file_errors[0].contains("Chunk index has 2 offsets"), "{}", report);
+        std::fs::write(&path, meta.to_string()).unwrap();
+
+        // Without per-frame checksums the commit still notices the change
+        let path = temp_dir.path().join("plain.meta");
+        save_array(&array, &path).unwrap();
+        assert!(verify_file(&path).unwrap().is_ok());
+        flip_byte(&path.with_extension("data"), 100);
+        let report = verify_file(&path).unwrap();
+        assert!(report.corrupt_frames.is_empty());
+        assert_eq!(report.file_errors.len(), 1, "{}", report);
+
+        // Truncation marks the missing frames
+        let data = std::fs::read(path.with_extension("data")).unwrap();
+        std::fs::write(path.with_extension("data"), &data[..data.len() / 2]).unwrap();
+        let report = verify_file(&path).unwrap();
+        assert_eq!(report.corrupt_frames.len(), 2, "{}", report);
+        assert!(!report.file_errors.is_empty());
+    }
+}
@@ -7,7 +7,7 @@ pub mod ui;
 pub mod routes;
 
 // Re-export commonly used items
-pub use io::{Array6D, ArrayGenerator, PatternType, save_array, load_array};
+pub use io::{Array6D, ArrayGenerator, PatternType, save_array, load_array, open_array};
 pub use utils::*;
 pub use services::*;
 pub use ui::*;
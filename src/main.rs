@@ -1,12 +1,6 @@
 use dioxus::prelude::*;
 // Views are used by the Router component
-use routes::Route;
-
-mod ui;
-mod services;
-mod io;
-mod utils;
-mod routes;
+use pyama_rust::routes::Route;
 
 const FAVICON: Asset = asset!("/assets/favicon.ico");
 
@@ -21,4 +15,3 @@ fn App() -> Element {
         Router::<Route> {}
     }
 }
-
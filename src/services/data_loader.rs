@@ -1,8 +1,10 @@
 //! Data loading service for 6D microscopy arrays
 
-use crate::io::{load_array, validate_file};
-use crate::io::array_6d::{Array6D, Dimensions, FrameStats};
+use crate::io::{load_array, read_frame, read_frame_for_display, validate_file, DataType, FormatRegistry, Pixel};
+use crate::io::array_6d::{Array6D, Dimensions, FrameStats, VoxelSize};
+use crate::io::{ChannelMetadata, PositionMetadata, ProvenanceEntry};
 use anyhow::Result;
+use ndarray::Array2;
 use std::path::Path;
 
 /// Metadata structure for microscopy data
@@ -10,43 +12,96 @@ use std::path::Path;
 pub struct MicroscopyMetadata {
     pub file_path: String,
     pub dimensions: Dimensions,
-    pub pixel_size_um: f64,
+    /// X/Y pixel size and z-step
+    pub voxel_size: VoxelSize,
     pub time_interval_s: f64,
     pub channel_names: Vec<String>,
     pub data_type: String,
     pub memory_usage_mb: usize,
+    /// Whether a writer is still appending time points to the file
+    pub appending: bool,
+    /// When the file was written, for split format files
+    pub created_at: Option<String>,
+    /// Seconds since the first time point, for irregular acquisitions
+    pub timestamps_s: Option<Vec<f64>>,
+    /// Operations that produced the file, oldest first
+    pub provenance: Vec<ProvenanceEntry>,
+    /// Acquisition settings of every channel; empty if unknown
+    pub channels: Vec<ChannelMetadata>,
+    /// Stage coordinates and wells of every position; empty if unknown
+    pub positions: Vec<PositionMetadata>,
 }
 
-impl From<&Array6D> for MicroscopyMetadata {
-    fn from(array: &Array6D) -> Self {
+impl MicroscopyMetadata {
+    /// Get the time of every time point in seconds, for trace time axes
+    pub fn time_axis_s(&self) -> Vec<f64> {
+        match &self.timestamps_s {
+            Some(timestamps) => timestamps.clone(),
+            None => (0..self.dimensions.time).map(|t| t as f64 * self.time_interval_s).collect(),
+        }
+    }
+    
+    /// Get a label for every position, e.g. `B03 field 2`, or `Position 4` (1-based) if none is known
+    pub fn position_labels(&self) -> Vec<String> {
+        (0..self.dimensions.position)
+            .map(|p| {
+                self.positions.get(p)
+                    .and_then(PositionMetadata::label)
+                    .unwrap_or_else(|| format!("Position {}", p + 1))
+            })
+            .collect()
+    }
+}
+
+impl<T: Pixel> From<&Array6D<T>> for MicroscopyMetadata {
+    fn from(array: &Array6D<T>) -> Self {
         Self {
             file_path: "".to_string(), // Will be set by the loader
             dimensions: *array.dimensions(),
-            pixel_size_um: array.pixel_size_um(),
+            voxel_size: array.voxel_size(),
             time_interval_s: array.time_interval_s(),
             channel_names: array.channel_names().to_vec(),
             data_type: array.data_type().to_string(),
-            memory_usage_mb: array.memory_usage() / (1024 * 1024),
+            memory_usage_mb: array.memory_usage().div_ceil(1024 * 1024),
+            appending: false,
+            created_at: None,
+            timestamps_s: array.timestamps_s().map(<[f64]>::to_vec),
+            provenance: array.provenance().to_vec(),
+            channels: array.channels().to_vec(),
+            positions: array.positions().to_vec(),
         }
     }
 }
 
 /// Load array file metadata only (lightweight operation)
+///
+/// Accepts every format of the [`FormatRegistry`]: split format `.meta` files,
+/// OME-TIFF files, OME-Zarr stores, NumPy files and directories (or JSON
+/// manifests) of per-position split files.
 pub async fn load_array_file<P: AsRef<Path>>(file_path: P) -> Result<MicroscopyMetadata> {
     let path = file_path.as_ref();
     let path_str = path.to_string_lossy().to_string();
+    let registry = FormatRegistry::<f32>::builtin();
+    let reader = registry.reader_for(path)?;
     
-    // First validate the file without loading all data
-    let metadata = validate_file(path)?;
+    // Split files are described from their `.meta` without touching the data;
+    // the others are opened lazily, which only parses their headers
+    let info = reader.info(path)?;
     
     Ok(MicroscopyMetadata {
         file_path: path_str,
-        dimensions: metadata.dimensions,
-        pixel_size_um: metadata.pixel_size_um,
-        time_interval_s: metadata.time_interval_s,
-        channel_names: metadata.channel_names,
-        data_type: metadata.data_type,
-        memory_usage_mb: (metadata.dimensions.total_elements() * 4) / (1024 * 1024),
+        dimensions: info.dimensions,
+        voxel_size: info.voxel_size,
+        time_interval_s: info.time_interval_s,
+        channel_names: info.channel_names,
+        data_type: info.data_type.to_string(),
+        memory_usage_mb: (info.dimensions.total_elements() * info.data_type.size()).div_ceil(1024 * 1024),
+        appending: info.appending,
+        created_at: info.created_at,
+        timestamps_s: info.timestamps_s,
+        provenance: info.provenance,
+        channels: info.channels,
+        positions: info.positions,
     })
 }
 
@@ -61,8 +116,36 @@ pub async fn load_full_array<P: AsRef<Path>>(file_path: P) -> Result<Array6D> {
     Ok(array)
 }
 
+/// Load a single frame without loading the full array
+#[allow(dead_code)] // Future use for frame display
+pub async fn load_frame<P: AsRef<Path>>(
+    file_path: P,
+    t: usize,
+    p: usize,
+    z: usize,
+    c: usize,
+) -> Result<Array2<f32>> {
+    read_frame(file_path, t, p, z, c)
+}
+
+/// Load a frame from the coarsest pyramid level that fills a `width`×`height` display
+///
+/// Returns the frame and its downsampling factor; files without a
+/// pyramid return the full-resolution frame with factor 1.
+#[allow(dead_code)] // Future use for zoomed-out frame display
+pub async fn load_display_frame<P: AsRef<Path>>(
+    file_path: P,
+    (t, p, z, c): (usize, usize, usize, usize),
+    width: usize,
+    height: usize,
+) -> Result<(Array2<f32>, usize)> {
+    read_frame_for_display(file_path, (t, p, z, c), width, height)
+}
+
 /// Get frame statistics for a specific frame without loading the full array
-#[allow(dead_code)] // Future use for detailed frame analysis
+///
+/// The frame is read and measured on a blocking thread, so callers on the
+/// UI executor are not held up by the disk.
 pub async fn get_frame_statistics<P: AsRef<Path>>(
     file_path: P,
     t: usize,
@@ -71,14 +154,18 @@ pub async fn get_frame_statistics<P: AsRef<Path>>(
     c: usize,
     saturation_threshold: f64,
 ) -> Result<FrameStats> {
-    let path = file_path.as_ref();
-    
-    // For now, load the full array to get stats
-    // In the future, this could be optimized to load only the specific frame
-    let array = load_array(path)?;
-    let stats = array.get_frame_stats(t, p, z, c, saturation_threshold)?;
-    
-    Ok(stats)
+    let path = file_path.as_ref().to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let frame = read_frame(path, t, p, z, c)?;
+        Ok(FrameStats::from_frame(frame.view(), saturation_threshold))
+    }).await?
+}
+
+/// Get the saturation threshold for a data type name
+pub fn saturation_threshold(data_type: &str) -> f64 {
+    data_type.parse::<DataType>()
+        .map(DataType::max_value)
+        .unwrap_or(f32::MAX as f64)
 }
 
 /// Check if a file exists and is a valid 6D file
@@ -91,14 +178,11 @@ pub fn is_valid_6d_file<P: AsRef<Path>>(file_path: P) -> bool {
         return false;
     }
     
-    // Check if corresponding .data file exists
-    let data_path = path.with_extension("data");
-    if !data_path.exists() {
-        return false;
-    }
-    
-    // Try to validate the file format
-    validate_file(path).is_ok()
+    // Detect the format and let it check the file, e.g. the .data file of split files
+    FormatRegistry::<f32>::builtin()
+        .reader_for(path)
+        .and_then(|reader| reader.validate(path))
+        .is_ok()
 }
 
 /// Get file size information
@@ -107,7 +191,7 @@ pub fn get_file_info<P: AsRef<Path>>(file_path: P) -> Result<(u64, u64)> {
     let path = file_path.as_ref();
     
     let meta_size = std::fs::metadata(path)?.len();
-    let data_path = path.with_extension("data");
+    let data_path = validate_file(path)?.data_path(path);
     let data_size = std::fs::metadata(data_path)?.len();
     
     Ok((meta_size, data_size))
@@ -152,11 +236,56 @@ mod tests {
         assert_eq!(array.channel_names().len(), 2);
     }
     
+    #[tokio::test]
+    async fn test_get_frame_statistics() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test.meta");
+        generate_small_test_file(&file_path).unwrap();
+        
+        let array = load_full_array(&file_path).await.unwrap();
+        let expected = array.get_frame_stats(2, 0, 1, 1, 1000.0).unwrap();
+        let stats = get_frame_statistics(&file_path, 2, 0, 1, 1, 1000.0).await.unwrap();
+        assert_eq!(stats, expected);
+        
+        let frame = load_frame(&file_path, 2, 0, 1, 1).await.unwrap();
+        assert_eq!(frame.dim(), (32, 32));
+        assert!(load_frame(&file_path, 3, 0, 0, 0).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_load_position_directory() {
+        let temp_dir = tempdir().unwrap();
+        let array = crate::io::ArrayGenerator::generate_test_array(2, 3, 1, 2, 8, 8).unwrap();
+        for p in 0..3 {
+            let position = array.select(&crate::io::Selection::all().positions(p)).unwrap()
+                .with_positions(vec![PositionMetadata::in_well("A02", p + 1)]).unwrap();
+            crate::io::save_array(&position, temp_dir.path().join(format!("pos{}.meta", p))).unwrap();
+        }
+        
+        let metadata = load_array_file(temp_dir.path()).await.unwrap();
+        assert_eq!(metadata.dimensions, *array.dimensions());
+        assert_eq!(metadata.position_labels(), ["A02 field 1", "A02 field 2", "A02 field 3"]);
+        let frame = load_frame(temp_dir.path(), 1, 2, 0, 1).await.unwrap();
+        assert_eq!(frame, array.get_frame(1, 2, 0, 1).unwrap());
+        assert!(is_valid_6d_file(temp_dir.path()));
+    }
+    
     #[test]
     fn test_is_valid_6d_file() {
         // Test with non-existent file
         assert!(!is_valid_6d_file("nonexistent.meta"));
         
-        // Could add more tests with actual files
+        // Every registered format is accepted
+        let temp_dir = tempdir().unwrap();
+        let meta_path = temp_dir.path().join("test.meta");
+        generate_small_test_file(&meta_path).unwrap();
+        assert!(is_valid_6d_file(&meta_path));
+        let tiff_path = temp_dir.path().join("test.ome.tif");
+        crate::io::save_array(&load_array(&meta_path).unwrap(), &tiff_path).unwrap();
+        assert!(is_valid_6d_file(&tiff_path));
+        
+        // A .meta file without its .data file is not
+        std::fs::remove_file(meta_path.with_extension("data")).unwrap();
+        assert!(!is_valid_6d_file(&meta_path));
     }
 }
\ No newline at end of file
@@ -1,12 +1,18 @@
 //! File dialog service for selecting 6D data files
 
+use crate::io::FormatRegistry;
 use anyhow::Result;
 use std::path::PathBuf;
 
-/// Open a file dialog to select a 6D data file (.meta)
+/// Add dialog filters built by the format registry
+fn with_filters(dialog: rfd::AsyncFileDialog, filters: Vec<(String, Vec<&'static str>)>) -> rfd::AsyncFileDialog {
+    filters.into_iter().fold(dialog, |dialog, (name, extensions)| dialog.add_filter(name, &extensions))
+}
+
+/// Open a file dialog to select a 6D data file of any registered format
 pub async fn select_6d_file() -> Result<Option<PathBuf>> {
-    let file = rfd::AsyncFileDialog::new()
-        .add_filter("6D Data Files", &["meta"])
+    let filters = FormatRegistry::<f32>::builtin().open_filters();
+    let file = with_filters(rfd::AsyncFileDialog::new(), filters)
         .add_filter("All Files", &["*"])
         .set_title("Select 6D Data File")
         .set_directory(".")
@@ -19,8 +25,35 @@ pub async fn select_6d_file() -> Result<Option<PathBuf>> {
     }
 }
 
+/// Open a file dialog to select several 6D data files, e.g. to merge them
+pub async fn select_6d_files() -> Result<Vec<PathBuf>> {
+    let filters = FormatRegistry::<f32>::builtin().open_filters();
+    let files = with_filters(rfd::AsyncFileDialog::new(), filters)
+        .add_filter("All Files", &["*"])
+        .set_title("Select 6D Data Files")
+        .set_directory(".")
+        .pick_files()
+        .await;
+    
+    // An empty list means the user cancelled
+    Ok(files.unwrap_or_default().iter().map(|handle| handle.path().to_path_buf()).collect())
+}
+
+/// Open a folder dialog to select a directory of per-position split files
+pub async fn select_6d_folder() -> Result<Option<PathBuf>> {
+    let folder = rfd::AsyncFileDialog::new()
+        .set_title("Select Folder of Position Files")
+        .set_directory(".")
+        .pick_folder()
+        .await;
+    
+    match folder {
+        Some(handle) => Ok(Some(handle.path().to_path_buf())),
+        None => Ok(None), // User cancelled
+    }
+}
+
 /// Open a file dialog to select any file
-#[allow(dead_code)] // Alternative file selection method
 pub async fn select_any_file() -> Result<Option<PathBuf>> {
     let file = rfd::AsyncFileDialog::new()
         .add_filter("All Files", &["*"])
@@ -35,11 +68,10 @@ pub async fn select_any_file() -> Result<Option<PathBuf>> {
     }
 }
 
-/// Open a file dialog to save a 6D data file
-#[allow(dead_code)] // Future use for saving functionality
+/// Open a file dialog to save a 6D data file in any registered format
 pub async fn save_6d_file() -> Result<Option<PathBuf>> {
-    let file = rfd::AsyncFileDialog::new()
-        .add_filter("6D Data Files", &["meta"])
+    let filters = FormatRegistry::<f32>::builtin().save_filters();
+    let file = with_filters(rfd::AsyncFileDialog::new(), filters)
         .set_title("Save 6D Data File")
         .set_directory(".")
         .set_file_name("data.meta")
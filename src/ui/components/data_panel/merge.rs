@@ -0,0 +1,131 @@
+use dioxus::prelude::*;
+use std::path::PathBuf;
+use crate::ui::components::{Button, Dropdown};
+use crate::io::{concatenate, concatenate_files, open_array, ConcatAxis};
+
+/// Check that the files can be merged and describe the result
+fn summary(paths: &[PathBuf], axis: ConcatAxis) -> Result<String, String> {
+    if paths.len() < 2 {
+        return Err("Add at least two files to merge".to_string());
+    }
+    let arrays = paths.iter()
+        .map(|path| open_array(path).map_err(|e| format!("{}: {}", path.display(), e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let merged = concatenate(&arrays, axis).map_err(|e| e.to_string())?;
+    let dims = merged.dimensions();
+    Ok(format!(
+        "TPZCYX {}×{}×{}×{}×{}×{}, channels: {}",
+        dims.time, dims.position, dims.z, dims.channel, dims.height, dims.width,
+        merged.channel_names().join(", ")
+    ))
+}
+
+#[derive(Props, Clone, PartialEq)]
+pub struct MergeWizardProps {
+    /// Called with the merged file to load
+    pub on_merged: EventHandler<String>,
+    pub on_cancel: EventHandler<()>,
+}
+
+/// Form that concatenates datasets along T, P or C into a new file
+#[component]
+pub fn MergeWizard(props: MergeWizardProps) -> Element {
+    let mut paths = use_signal(Vec::<PathBuf>::new);
+    let mut axis = use_signal(|| ConcatAxis::Time);
+    let mut error = use_signal(|| None::<String>);
+
+    // Opening every file is not free, so only redo it when the inputs change
+    let status = use_memo(move || summary(&paths.read(), axis()));
+    let axis_options: Vec<(String, String)> = ConcatAxis::ALL.iter()
+        .map(|axis| (axis.name().to_string(), format!("Along {}", axis.name())))
+        .collect();
+
+    let on_merged = props.on_merged;
+    let merge_files = move |output: PathBuf| async move {
+        let (inputs, selected_axis, target) = (paths(), axis(), output.clone());
+        // Every frame of the inputs is copied, so keep it off the UI thread
+        let merged = tokio::task::spawn_blocking(move || concatenate_files(&inputs, &target, selected_axis)).await;
+        match merged.map_err(anyhow::Error::from).and_then(|result| result) {
+            Ok(()) => {
+                error.set(None);
+                on_merged.call(output.to_string_lossy().to_string());
+            }
+            Err(e) => error.set(Some(e.to_string())),
+        }
+    };
+
+    rsx! {
+        div { class: "data-section merge",
+            div { class: "section-title", "Merge Datasets" }
+            for (i, path) in paths.read().iter().enumerate() {
+                div { class: "data-item",
+                    span { class: "data-label", "{i + 1}." }
+                    span { class: "data-value", "{path.display()}" }
+                }
+            }
+            div { class: "data-header",
+                Button {
+                    variant: "outline".to_string(),
+                    class: "load-button".to_string(),
+                    onclick: move |_| {
+                        spawn(async move {
+                            match crate::services::select_6d_files().await {
+                                Ok(selected) => paths.write().extend(selected),
+                                Err(e) => error.set(Some(format!("Error opening file dialog: {}", e))),
+                            }
+                        });
+                    },
+                    "Add Files..."
+                }
+                Button {
+                    variant: "outline".to_string(),
+                    class: "load-button".to_string(),
+                    onclick: move |_| paths.write().clear(),
+                    "Clear"
+                }
+                Dropdown {
+                    options: axis_options,
+                    selected: Some(axis().name().to_string()),
+                    onchange: move |value: String| {
+                        if let Ok(value) = value.parse() {
+                            axis.set(value);
+                        }
+                    },
+                }
+            }
+
+            match &*status.read() {
+                Ok(text) => rsx! { div { class: "data-item", "{text}" } },
+                Err(text) => rsx! { div { class: "data-item error-state", "{text}" } },
+            }
+            if let Some(ref message) = error() {
+                div { class: "data-item error-state", "{message}" }
+            }
+
+            div { class: "data-header",
+                if status.read().is_ok() {
+                    Button {
+                        variant: "default".to_string(),
+                        class: "load-button".to_string(),
+                        onclick: move |_| {
+                            spawn(async move {
+                                match crate::services::save_6d_file().await {
+                                    Ok(Some(output)) => merge_files(output).await,
+                                    Ok(None) => {}
+                                    Err(e) => error.set(Some(format!("Error opening file dialog: {}", e))),
+                                }
+                            });
+                        },
+                        "Merge..."
+                    }
+                }
+                Button {
+                    variant: "outline".to_string(),
+                    class: "load-button".to_string(),
+                    onclick: move |_| props.on_cancel.call(()),
+                    "Cancel"
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,12 @@
 use dioxus::prelude::*;
 use crate::ui::components::{Separator, Button};
-use crate::io::array_6d::FrameStats;
+use crate::io::array_6d::{FrameStats, VoxelSize};
+use crate::io::ChannelMetadata;
+
+mod merge;
+mod raw_import;
+use merge::MergeWizard;
+use raw_import::RawImportWizard;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum DataLoadingState {
@@ -40,10 +46,56 @@ pub struct IntensityStats {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ChannelInfo {
-    pub wavelength: String,
-    pub exposure_time: f64,
-    pub gain: f64,
-    pub filter: String,
+    pub name: String,
+    /// Excitation/emission wavelengths, e.g. "488/525 nm"
+    pub wavelength: Option<String>,
+    /// Exposure time in ms
+    pub exposure_time: Option<f64>,
+    pub gain: Option<f64>,
+    pub camera_offset: Option<f64>,
+    pub filter: Option<String>,
+    /// Display colour as `#RRGGBB`
+    pub color: Option<String>,
+    pub role: Option<String>,
+}
+
+impl ChannelInfo {
+    /// Describe channel `name` with the settings recorded in its file
+    pub fn new(name: &str, settings: &ChannelMetadata) -> Self {
+        let wavelength = match (settings.excitation_nm, settings.emission_nm) {
+            (Some(ex), Some(em)) => Some(format!("{ex:.0}/{em:.0} nm")),
+            (Some(ex), None) => Some(format!("{ex:.0} nm excitation")),
+            (None, Some(em)) => Some(format!("{em:.0} nm emission")),
+            (None, None) => None,
+        };
+        Self {
+            name: name.to_string(),
+            wavelength,
+            exposure_time: settings.exposure_ms,
+            gain: settings.gain,
+            camera_offset: settings.camera_offset,
+            filter: settings.filter.clone(),
+            color: settings.color.map(|color| color.to_string()),
+            role: settings.role.map(|role| role.to_string()),
+        }
+    }
+}
+
+/// Describe every channel of a file; channels without recorded settings only get their name
+pub fn channel_infos(names: &[String], settings: &[ChannelMetadata]) -> Vec<ChannelInfo> {
+    let unknown = ChannelMetadata::default();
+    names.iter()
+        .enumerate()
+        .map(|(c, name)| ChannelInfo::new(name, settings.get(c).unwrap_or(&unknown)))
+        .collect()
+}
+
+/// Format an optional channel setting, or "—" if it was not recorded
+fn setting<T: std::fmt::Display>(value: &Option<T>, unit: &str) -> String {
+    match value {
+        Some(value) => format!("{value}{unit}"),
+        None => "—".to_string(),
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -101,16 +153,30 @@ pub struct ArrayDimensions {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ArrayMetadata {
-    pub pixel_size_um: f64,
+    /// X/Y pixel size and z-step
+    pub voxel_size: VoxelSize,
     pub time_interval_s: f64,
     pub channel_names: Vec<String>,
+    /// Name and recorded settings of every channel
+    pub channels: Vec<ChannelInfo>,
     pub data_type: String,
+    /// The file is still being written by an acquisition
+    pub appending: bool,
+    /// When the file was written, if recorded
+    pub created_at: Option<String>,
+    /// Time of every time point in seconds
+    pub time_axis_s: Vec<f64>,
+    /// Whether the time points have recorded timestamps rather than a fixed interval
+    pub irregular_time: bool,
+    /// Operations that produced the file, oldest first
+    pub provenance: Vec<String>,
 }
 
 #[derive(Props, Clone, PartialEq)]
 pub struct DataPanelProps {
     pub channel: crate::ui::components::Channel,
     pub position: i32,
+    /// 1-based frame (time point) shown in the viewer
     pub frame: i32,
     #[props(default = DataLoadingState::NotLoaded)]
     pub loading_state: DataLoadingState,
@@ -124,7 +190,9 @@ pub struct DataPanelProps {
 
 #[component]
 pub fn DataPanel(props: DataPanelProps) -> Element {
-    let channel = props.channel.clone();
+    let channel = props.channel;
+    let mut show_raw_import = use_signal(|| false);
+    let mut show_merge = use_signal(|| false);
     
     // Mock data generation for demonstration
     let _generate_mock_data = move |channel: &crate::ui::components::Channel, position: i32, frame: i32| -> ChannelData {
@@ -157,10 +225,14 @@ pub fn DataPanel(props: DataPanelProps) -> Element {
                         saturated_pixels: 1247,
                     },
                     channel_info: ChannelInfo {
-                        wavelength: "488nm".to_string(),
-                        exposure_time: 100.0,
-                        gain: 2.5,
-                        filter: "FITC".to_string(),
+                        name: "GFP".to_string(),
+                        wavelength: Some("488nm".to_string()),
+                        exposure_time: Some(100.0),
+                        gain: Some(2.5),
+                        camera_offset: None,
+                        filter: Some("FITC".to_string()),
+                        color: None,
+                        role: None,
                     },
                 })
             },
@@ -299,7 +371,7 @@ pub fn DataPanel(props: DataPanelProps) -> Element {
                         variant: "default".to_string(),
                         class: "load-button".to_string(),
                         onclick: move |_| {
-                            let handler = props.on_load_file.clone();
+                            let handler = props.on_load_file;
                             spawn(async move {
                                 if let Some(handler) = handler {
                                     match crate::services::select_6d_file().await {
@@ -318,15 +390,76 @@ pub fn DataPanel(props: DataPanelProps) -> Element {
                         },
                         "Load 6D Data"
                     }
+                    Button {
+                        variant: "outline".to_string(),
+                        class: "load-button".to_string(),
+                        onclick: move |_| {
+                            let handler = props.on_load_file;
+                            spawn(async move {
+                                if let Some(handler) = handler {
+                                    match crate::services::select_6d_folder().await {
+                                        Ok(Some(path)) => handler.call(path.to_string_lossy().to_string()),
+                                        Ok(None) => {}
+                                        Err(e) => println!("Error opening folder dialog: {}", e),
+                                    }
+                                }
+                            });
+                        },
+                        "Load Folder..."
+                    }
+                    Button {
+                        variant: "outline".to_string(),
+                        class: "load-button".to_string(),
+                        onclick: move |_| show_raw_import.set(true),
+                        "Import Raw..."
+                    }
+                    Button {
+                        variant: "outline".to_string(),
+                        class: "load-button".to_string(),
+                        onclick: move |_| show_merge.set(true),
+                        "Merge..."
+                    }
                 }
             }
             
             match props.loading_state {
+                DataLoadingState::NotLoaded if show_raw_import() => rsx! {
+                    div { class: "data-content",
+                        RawImportWizard {
+                            on_imported: move |path: String| {
+                                show_raw_import.set(false);
+                                if let Some(handler) = props.on_load_file {
+                                    handler.call(path);
+                                }
+                            },
+                            on_cancel: move |_| show_raw_import.set(false),
+                        }
+                    }
+                },
+                DataLoadingState::NotLoaded if show_merge() => rsx! {
+                    div { class: "data-content",
+                        MergeWizard {
+                            on_merged: move |path: String| {
+                                show_merge.set(false);
+                                if let Some(handler) = props.on_load_file {
+                                    handler.call(path);
+                                }
+                            },
+                            on_cancel: move |_| show_merge.set(false),
+                        }
+                    }
+                },
                 DataLoadingState::NotLoaded => rsx! {
                     div { class: "loading-state",
                         "Click 'Load 6D Data' to browse and select a 6D microscopy file"
                         br {}
-                        "Supported formats: .meta files with corresponding .data files"
+                        "Supported formats: .meta files with corresponding .data files, OME-TIFF (.ome.tif), NumPy (.npy, .npz)"
+                        br {}
+                        "'Load Folder...' opens a folder of per-position .meta files as one dataset; a .json manifest listing them works too"
+                        br {}
+                        "Headerless binary files can be described with 'Import Raw...'"
+                        br {}
+                        "Datasets split across restarts or runs can be combined with 'Merge...'"
                     }
                 },
                 DataLoadingState::Loading => rsx! {
@@ -395,21 +528,25 @@ pub fn DataPanel(props: DataPanelProps) -> Element {
                                     div { class: "data-section",
                                         div { class: "section-title", "Channel Settings" }
                                         div { class: "data-grid",
+                                            div { class: "data-item",
+                                                span { class: "data-label", "Channel:" }
+                                                span { class: "data-value", "{fluor_data.channel_info.name}" }
+                                            }
                                             div { class: "data-item",
                                                 span { class: "data-label", "Wavelength:" }
-                                                span { class: "data-value", "{fluor_data.channel_info.wavelength}" }
+                                                span { class: "data-value", {setting(&fluor_data.channel_info.wavelength, "")} }
                                             }
                                             div { class: "data-item",
                                                 span { class: "data-label", "Filter:" }
-                                                span { class: "data-value", "{fluor_data.channel_info.filter}" }
+                                                span { class: "data-value", {setting(&fluor_data.channel_info.filter, "")} }
                                             }
                                             div { class: "data-item",
                                                 span { class: "data-label", "Exposure:" }
-                                                span { class: "data-value", "{fluor_data.channel_info.exposure_time:.1} ms" }
+                                                span { class: "data-value", {setting(&fluor_data.channel_info.exposure_time, " ms")} }
                                             }
                                             div { class: "data-item",
                                                 span { class: "data-label", "Gain:" }
-                                                span { class: "data-value", "{fluor_data.channel_info.gain:.1}x" }
+                                                span { class: "data-value", {setting(&fluor_data.channel_info.gain, "x")} }
                                             }
                                         }
                                     }
@@ -510,12 +647,37 @@ pub fn DataPanel(props: DataPanelProps) -> Element {
                                                 span { class: "data-value", "{array_data.metadata.data_type}" }
                                             }
                                             div { class: "data-item",
-                                                span { class: "data-label", "Pixel Size:" }
-                                                span { class: "data-value", "{array_data.metadata.pixel_size_um:.3} μm" }
+                                                span { class: "data-label", "Voxel Size (X × Y × Z):" }
+                                                span { class: "data-value", "{array_data.metadata.voxel_size}" }
                                             }
-                                            div { class: "data-item",
-                                                span { class: "data-label", "Time Interval:" }
-                                                span { class: "data-value", "{array_data.metadata.time_interval_s:.1} s" }
+                                            if array_data.metadata.irregular_time {
+                                                div { class: "data-item",
+                                                    span { class: "data-label", "Time Interval:" }
+                                                    span { class: "data-value", "Irregular (recorded timestamps)" }
+                                                }
+                                            } else {
+                                                div { class: "data-item",
+                                                    span { class: "data-label", "Time Interval:" }
+                                                    span { class: "data-value", "{array_data.metadata.time_interval_s:.1} s" }
+                                                }
+                                            }
+                                            if let Some(seconds) = usize::try_from(props.frame - 1).ok().and_then(|t| array_data.metadata.time_axis_s.get(t)) {
+                                                div { class: "data-item",
+                                                    span { class: "data-label", "Frame Time:" }
+                                                    span { class: "data-value", "{seconds:.1} s" }
+                                                }
+                                            }
+                                            if let Some(ref created_at) = array_data.metadata.created_at {
+                                                div { class: "data-item",
+                                                    span { class: "data-label", "Created:" }
+                                                    span { class: "data-value", "{created_at}" }
+                                                }
+                                            }
+                                            if array_data.metadata.appending {
+                                                div { class: "data-item",
+                                                    span { class: "data-label", "Acquisition:" }
+                                                    span { class: "data-value", "In progress ({array_data.dimensions.time} time points so far)" }
+                                                }
                                             }
                                         }
                                     }
@@ -525,13 +687,50 @@ pub fn DataPanel(props: DataPanelProps) -> Element {
                                         horizontal: true 
                                     }
                                     
+                                    if !array_data.metadata.provenance.is_empty() {
+                                        div { class: "data-section",
+                                            div { class: "section-title", "Provenance" }
+                                            div { class: "region-list",
+                                                for entry in array_data.metadata.provenance.iter() {
+                                                    div { class: "region-item", "{entry}" }
+                                                }
+                                            }
+                                        }
+                                        
+                                        Separator { 
+                                            style: "margin: 1rem 0; width: 100%;".to_string(),
+                                            horizontal: true 
+                                        }
+                                    }
+                                    
                                     div { class: "data-section",
                                         div { class: "section-title", "Available Channels" }
                                         div { class: "data-grid",
-                                            for (i, channel_name) in array_data.metadata.channel_names.iter().enumerate() {
+                                            for (i, channel_info) in array_data.metadata.channels.iter().enumerate() {
                                                 div { class: "data-item",
                                                     span { class: "data-label", "Channel {i}:" }
-                                                    span { class: "data-value", "{channel_name}" }
+                                                    span { class: "data-value",
+                                                        style: channel_info.color.as_ref().map(|color| format!("color: {color};")),
+                                                        "{channel_info.name}"
+                                                    }
+                                                }
+                                                div { class: "data-item",
+                                                    span { class: "data-label", {setting(&channel_info.role, "")} }
+                                                    span { class: "data-value", {setting(&channel_info.wavelength, "")} }
+                                                }
+                                                if channel_info.exposure_time.is_some() || channel_info.gain.is_some() {
+                                                    div { class: "data-item",
+                                                        span { class: "data-label", "Exposure / Gain:" }
+                                                        span { class: "data-value",
+                                                            {format!("{} / {}", setting(&channel_info.exposure_time, " ms"), setting(&channel_info.gain, "x"))}
+                                                        }
+                                                    }
+                                                    div { class: "data-item",
+                                                        span { class: "data-label", "Offset / Filter:" }
+                                                        span { class: "data-value",
+                                                            {format!("{} / {}", setting(&channel_info.camera_offset, ""), setting(&channel_info.filter, ""))}
+                                                        }
+                                                    }
                                                 }
                                             }
                                         }
@@ -0,0 +1,241 @@
+use dioxus::prelude::*;
+use std::path::{Path, PathBuf};
+use crate::ui::components::{Button, Dropdown, Input};
+use crate::io::{AxisSpec, ByteOrder, DataType, RawImport, SampleEncoding};
+
+/// Text fields of the raw import form
+#[derive(Debug, Clone, PartialEq)]
+struct RawImportForm {
+    offset: String,
+    data_type: String,
+    byte_order: String,
+    axes: String,
+    shape: String,
+    pixel_size_um: String,
+    time_interval_s: String,
+}
+
+impl Default for RawImportForm {
+    fn default() -> Self {
+        Self {
+            offset: "0".to_string(),
+            data_type: DataType::UInt16.name().to_string(),
+            byte_order: "little".to_string(),
+            axes: "TYX".to_string(),
+            shape: String::new(),
+            pixel_size_um: "1.0".to_string(),
+            time_interval_s: "1.0".to_string(),
+        }
+    }
+}
+
+impl RawImportForm {
+    /// Parse the form into a raw import description
+    fn to_import(&self) -> anyhow::Result<RawImport> {
+        let number = |name: &str, value: &str| -> anyhow::Result<f64> {
+            value.trim().parse().map_err(|_| anyhow::anyhow!("Invalid {}: '{}'", name, value))
+        };
+        let byte_order = if self.byte_order == "big" { ByteOrder::Big } else { ByteOrder::Little };
+        let encoding = SampleEncoding::new(self.data_type.parse()?, byte_order);
+        let axes: AxisSpec = self.axes.parse()?;
+        let shape = self.shape.split([',', 'x', '×', ' '])
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse().map_err(|_| anyhow::anyhow!("Invalid extent '{}' in shape", s)))
+            .collect::<anyhow::Result<Vec<usize>>>()?;
+        let offset = self.offset.trim().parse().map_err(|_| anyhow::anyhow!("Invalid header offset: '{}'", self.offset))?;
+
+        Ok(RawImport::new(encoding, axes, shape)?
+            .offset(offset)
+            .pixel_size(number("pixel size", &self.pixel_size_um)?)
+            .time_interval(number("time interval", &self.time_interval_s)?))
+    }
+}
+
+/// Describe the form state against the selected file
+fn summary(form: &RawImportForm, raw_path: Option<&Path>) -> Result<String, String> {
+    let import = form.to_import().map_err(|e| e.to_string())?;
+    let dims = import.dimensions();
+    let mut text = format!(
+        "TPZCYX {}×{}×{}×{}×{}×{}",
+        dims.time, dims.position, dims.z, dims.channel, dims.height, dims.width
+    );
+    if let Some(path) = raw_path {
+        import.check_file(path).map_err(|e| e.to_string())?;
+        text.push_str(", file size matches");
+    }
+    if !import.axes().has_contiguous_frames() {
+        text.push_str("; frames are not contiguous, convert for faster reading");
+    }
+    Ok(text)
+}
+
+#[derive(Props, Clone, PartialEq)]
+pub struct RawImportWizardProps {
+    /// Called with the `.meta` (or converted) file to load
+    pub on_imported: EventHandler<String>,
+    pub on_cancel: EventHandler<()>,
+}
+
+/// Form that turns a headerless binary file into a split-format dataset
+#[component]
+pub fn RawImportWizard(props: RawImportWizardProps) -> Element {
+    let mut raw_path = use_signal(|| None::<PathBuf>);
+    let mut form = use_signal(RawImportForm::default);
+    let mut error = use_signal(|| None::<String>);
+
+    let status = summary(&form.read(), raw_path.read().as_deref());
+    let can_describe = form.read().to_import().is_ok();
+    let data_type_options: Vec<(String, String)> = DataType::ALL.iter()
+        .map(|dtype| (dtype.name().to_string(), dtype.name().to_string()))
+        .collect();
+    let byte_order_options = vec![
+        ("little".to_string(), "Little-endian".to_string()),
+        ("big".to_string(), "Big-endian".to_string()),
+    ];
+
+    let on_imported = props.on_imported;
+    let import_file = move |output: Option<PathBuf>| async move {
+        let Some(path) = raw_path() else {
+            error.set(Some("Select a raw file first".to_string()));
+            return;
+        };
+        let import = form.read().to_import();
+        // Converting copies the whole file, so keep it off the UI thread
+        let result = tokio::task::spawn_blocking(move || {
+            import.and_then(|import| match output {
+                Some(output) => import.convert(&path, &output).map(|_| output),
+                None => import.write_meta(&path),
+            })
+        }).await;
+        match result.map_err(anyhow::Error::from).and_then(|result| result) {
+            Ok(loaded) => {
+                error.set(None);
+                on_imported.call(loaded.to_string_lossy().to_string());
+            }
+            Err(e) => error.set(Some(e.to_string())),
+        }
+    };
+
+    rsx! {
+        div { class: "data-section raw-import",
+            div { class: "section-title", "Import Raw Binary File" }
+            div { class: "data-item",
+                span { class: "data-label", "File:" }
+                span { class: "data-value",
+                    {raw_path.read().as_ref().map(|p| p.display().to_string()).unwrap_or_else(|| "None selected".to_string())}
+                }
+            }
+            Button {
+                variant: "outline".to_string(),
+                class: "load-button".to_string(),
+                onclick: move |_| {
+                    spawn(async move {
+                        match crate::services::select_any_file().await {
+                            Ok(Some(path)) => raw_path.set(Some(path)),
+                            Ok(None) => {}
+                            Err(e) => error.set(Some(format!("Error opening file dialog: {}", e))),
+                        }
+                    });
+                },
+                "Browse..."
+            }
+
+            div { class: "data-grid",
+                div { class: "data-item",
+                    span { class: "data-label", "Header (bytes):" }
+                    Input {
+                        value: form.read().offset.clone(),
+                        oninput: move |evt: FormEvent| form.write().offset = evt.value(),
+                    }
+                }
+                div { class: "data-item",
+                    span { class: "data-label", "Data type:" }
+                    Dropdown {
+                        options: data_type_options,
+                        selected: Some(form.read().data_type.clone()),
+                        onchange: move |value: String| form.write().data_type = value,
+                    }
+                }
+                div { class: "data-item",
+                    span { class: "data-label", "Byte order:" }
+                    Dropdown {
+                        options: byte_order_options,
+                        selected: Some(form.read().byte_order.clone()),
+                        onchange: move |value: String| form.write().byte_order = value,
+                    }
+                }
+                div { class: "data-item",
+                    span { class: "data-label", "Axis order:" }
+                    Input {
+                        placeholder: "e.g. TCYX".to_string(),
+                        value: form.read().axes.clone(),
+                        oninput: move |evt: FormEvent| form.write().axes = evt.value(),
+                    }
+                }
+                div { class: "data-item",
+                    span { class: "data-label", "Extents:" }
+                    Input {
+                        placeholder: "e.g. 10, 2, 512, 512".to_string(),
+                        value: form.read().shape.clone(),
+                        oninput: move |evt: FormEvent| form.write().shape = evt.value(),
+                    }
+                }
+                div { class: "data-item",
+                    span { class: "data-label", "Pixel size (μm):" }
+                    Input {
+                        value: form.read().pixel_size_um.clone(),
+                        oninput: move |evt: FormEvent| form.write().pixel_size_um = evt.value(),
+                    }
+                }
+                div { class: "data-item",
+                    span { class: "data-label", "Time interval (s):" }
+                    Input {
+                        value: form.read().time_interval_s.clone(),
+                        oninput: move |evt: FormEvent| form.write().time_interval_s = evt.value(),
+                    }
+                }
+            }
+
+            match status {
+                Ok(ref text) => rsx! { div { class: "data-item", "{text}" } },
+                Err(ref text) => rsx! { div { class: "data-item error-state", "{text}" } },
+            }
+            if let Some(ref message) = error() {
+                div { class: "data-item error-state", "{message}" }
+            }
+
+            div { class: "data-header",
+                if can_describe {
+                    Button {
+                        variant: "default".to_string(),
+                        class: "load-button".to_string(),
+                        onclick: move |_| {
+                            spawn(import_file(None));
+                        },
+                        "Write .meta"
+                    }
+                }
+                Button {
+                    variant: "default".to_string(),
+                    class: "load-button".to_string(),
+                    onclick: move |_| {
+                        spawn(async move {
+                            match crate::services::save_6d_file().await {
+                                Ok(Some(output)) => import_file(Some(output)).await,
+                                Ok(None) => {}
+                                Err(e) => error.set(Some(format!("Error opening file dialog: {}", e))),
+                            }
+                        });
+                    },
+                    "Convert..."
+                }
+                Button {
+                    variant: "outline".to_string(),
+                    class: "load-button".to_string(),
+                    onclick: move |_| props.on_cancel.call(()),
+                    "Cancel"
+                }
+            }
+        }
+    }
+}
@@ -62,7 +62,7 @@ pub fn Dropdown(props: DropdownProps) -> Element {
                                 if props.selected.as_ref() == Some(value) { "selected" } else { "" }),
                             onclick: {
                                 let value = value.clone();
-                                let onchange = props.onchange.clone();
+                                let onchange = props.onchange;
                                 move |_| {
                                     if let Some(handler) = onchange.as_ref() {
                                         handler.call(value.clone());
@@ -8,15 +8,18 @@ pub enum Channel {
     Segmentation,
 }
 
-impl Channel {
-    pub fn to_string(&self) -> String {
-        match self {
-            Channel::PhaseContrast => "Phase Contrast".to_string(),
-            Channel::Fluorescence => "Fluorescence".to_string(),
-            Channel::Segmentation => "Segmentation".to_string(),
-        }
+impl std::fmt::Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Channel::PhaseContrast => "Phase Contrast",
+            Channel::Fluorescence => "Fluorescence",
+            Channel::Segmentation => "Segmentation",
+        };
+        f.write_str(name)
     }
-    
+}
+
+impl Channel {
     pub fn from_string(s: &str) -> Self {
         match s {
             "phase_contrast" => Channel::PhaseContrast,
@@ -26,7 +29,7 @@ impl Channel {
         }
     }
     
-    pub fn to_channel_index(&self) -> usize {
+    pub fn to_channel_index(self) -> usize {
         match self {
             Channel::PhaseContrast => 0,
             Channel::Fluorescence => 1,
@@ -43,6 +46,9 @@ pub struct ViewerPanelProps {
     pub frame: i32,
     #[props(default = Channel::PhaseContrast)]
     pub channel: Channel,
+    /// Label of every position, e.g. "B03 field 2"; positions are numbered if empty
+    #[props(default = Vec::new())]
+    pub position_labels: Vec<String>,
     #[props(default = None)]
     pub on_position_change: Option<EventHandler<i32>>,
     #[props(default = None)]
@@ -62,6 +68,15 @@ pub fn ViewerPanel(props: ViewerPanelProps) -> Element {
         ("segmentation".to_string(), "Segmentation".to_string()),
     ];
     
+    let position_options: Vec<(String, String)> = props.position_labels.iter()
+        .enumerate()
+        .map(|(p, label)| ((p + 1).to_string(), label.clone()))
+        .collect();
+    let position_label = usize::try_from(props.position - 1).ok()
+        .and_then(|p| props.position_labels.get(p))
+        .cloned()
+        .unwrap_or_else(|| props.position.to_string());
+    
     let selected_channel = match props.channel {
         Channel::PhaseContrast => "phase_contrast",
         Channel::Fluorescence => "fluorescence", 
@@ -138,12 +153,22 @@ pub fn ViewerPanel(props: ViewerPanelProps) -> Element {
             div { class: "control-bar",
                 div { class: "control-group",
                     label { class: "control-label", "Position:" }
-                    Input {
-                        class: "control-input".to_string(),
-                        input_type: "number".to_string(),
-                        value: position_input().to_string(),
-                        oninput: move |evt: FormEvent| position_input.set(evt.value()),
-                        placeholder: "1".to_string(),
+                    if position_options.is_empty() {
+                        Input {
+                            class: "control-input".to_string(),
+                            input_type: "number".to_string(),
+                            value: position_input().to_string(),
+                            oninput: move |evt: FormEvent| position_input.set(evt.value()),
+                            placeholder: "1".to_string(),
+                        }
+                    } else {
+                        Dropdown {
+                            class: "control-dropdown".to_string(),
+                            options: position_options,
+                            selected: position_input(),
+                            placeholder: "Select position...".to_string(),
+                            onchange: move |value: String| position_input.set(value),
+                        }
                     }
                 }
                 
@@ -206,7 +231,7 @@ pub fn ViewerPanel(props: ViewerPanelProps) -> Element {
                     
                     div { class: "current-settings",
                         p { "Current Settings:" }
-                        p { "Position: {props.position} | Frame: {props.frame}" }
+                        p { "Position: {position_label} | Frame: {props.frame}" }
                         p { "Channel: {props.channel.to_string()}" }
                     }
                 }
@@ -1,6 +1,6 @@
 use dioxus::prelude::*;
 use crate::ui::components::{Separator, ViewerPanel, Channel, DataPanel, ChannelData, DataLoadingState};
-use crate::services::{MicroscopyMetadata, load_array_file};
+use crate::services::{MicroscopyMetadata, load_array_file, get_frame_statistics, saturation_threshold};
 use crate::routes::Route;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -51,12 +51,23 @@ pub fn MainApp() -> Element {
                         total_pixels: 4194304,
                         saturated_pixels: 1247 + (frame as u32 * 3),
                     },
-                    channel_info: ChannelInfo {
-                        wavelength: "488nm".to_string(),
-                        exposure_time: 100.0,
-                        gain: 2.5,
-                        filter: "FITC".to_string(),
-                    },
+                    // Settings of the loaded file's channel, mock values otherwise
+                    channel_info: microscopy_metadata.peek().as_ref()
+                        .and_then(|metadata| {
+                            channel_infos(&metadata.channel_names, &metadata.channels)
+                                .into_iter()
+                                .nth(channel.to_channel_index())
+                        })
+                        .unwrap_or_else(|| ChannelInfo {
+                            name: "GFP".to_string(),
+                            wavelength: Some("488nm".to_string()),
+                            exposure_time: Some(100.0),
+                            gain: Some(2.5),
+                            camera_offset: None,
+                            filter: Some("FITC".to_string()),
+                            color: None,
+                            role: None,
+                        }),
                 })
             },
             Channel::Segmentation => {
@@ -119,9 +130,9 @@ pub fn MainApp() -> Element {
             println!("Starting array file metadata load: {}", file_path);
             data_loading_state.set(DataLoadingState::Loading);
             
-            let mut microscopy_metadata_signal = microscopy_metadata.clone();
-            let mut current_data_signal = current_data.clone();
-            let mut data_loading_state_signal = data_loading_state.clone();
+            let mut microscopy_metadata_signal = microscopy_metadata;
+            let mut current_data_signal = current_data;
+            let mut data_loading_state_signal = data_loading_state;
             
             spawn(async move {
                 match load_array_file(file_path.clone()).await {
@@ -137,12 +148,18 @@ pub fn MainApp() -> Element {
                                 width: metadata.dimensions.width,
                             },
                             metadata: crate::ui::components::data_panel::ArrayMetadata {
-                                pixel_size_um: metadata.pixel_size_um,
+                                voxel_size: metadata.voxel_size,
                                 time_interval_s: metadata.time_interval_s,
                                 channel_names: metadata.channel_names.clone(),
+                                channels: crate::ui::components::data_panel::channel_infos(&metadata.channel_names, &metadata.channels),
                                 data_type: metadata.data_type.clone(),
+                                appending: metadata.appending,
+                                created_at: metadata.created_at.clone(),
+                                time_axis_s: metadata.time_axis_s(),
+                                irregular_time: metadata.timestamps_s.is_some(),
+                                provenance: metadata.provenance.iter().map(|entry| entry.to_string()).collect(),
                             },
-                            current_frame_stats: None, // No heavy data processing
+                            current_frame_stats: None, // Filled in per frame by the stats effect
                             current_frame_image: None, // No image generation
                         };
                         
@@ -160,6 +177,36 @@ pub fn MainApp() -> Element {
         }
     );
 
+    // Refresh statistics of the selected frame whenever the viewer moves.
+    // Only that plane is read from disk, so scrubbing stays cheap.
+    use_effect(move || {
+        let Some(metadata) = microscopy_metadata() else {
+            return;
+        };
+        let t = usize::try_from(frame() - 1).ok();
+        let p = usize::try_from(position() - 1).ok();
+        let c = channel().to_channel_index();
+        
+        let mut current_data_signal = current_data;
+        spawn(async move {
+            let stats = match (t, p) {
+                (Some(t), Some(p)) => get_frame_statistics(
+                    &metadata.file_path, t, p, 0, c,
+                    saturation_threshold(&metadata.data_type),
+                ).await.ok(),
+                _ => None,
+            };
+            
+            current_data_signal.with_mut(|data| {
+                if let Some(ChannelData::MicroscopyArray(array_data)) = data {
+                    if array_data.file_path == metadata.file_path {
+                        array_data.current_frame_stats = stats;
+                    }
+                }
+            });
+        });
+    });
+
     rsx! {
         document::Link {
             rel: "stylesheet",
@@ -395,6 +442,7 @@ pub fn MainApp() -> Element {
                                 position: position(),
                                 frame: frame(),
                                 channel: channel(),
+                                position_labels: microscopy_metadata().map(|metadata| metadata.position_labels()).unwrap_or_default(),
                                 on_position_change: move |new_pos| position.set(new_pos),
                                 on_frame_change: move |new_frame| frame.set(new_frame),
                                 on_channel_change: move |new_channel| channel.set(new_channel),
@@ -1,9 +1,15 @@
 //! Utility functions for working with 6D arrays
 
-use crate::io::{load_array};
+use crate::io::{open_array, save_array, ArrayGenerator};
 use anyhow::Result;
 use std::path::Path;
 
+/// Generate a small test file (3×1×2×2×32×32) for development and tests
+#[allow(dead_code)] // Used in tests and examples
+pub fn generate_small_test_file<P: AsRef<Path>>(file_path: P) -> Result<()> {
+    let array = ArrayGenerator::generate_test_array(3, 1, 2, 2, 32, 32)?;
+    save_array(&array, file_path)
+}
 
 /// Load and display basic information about a 6D file
 #[allow(dead_code)] // Used in examples
@@ -11,8 +17,8 @@ pub fn load_and_inspect_6d_file<P: AsRef<Path>>(file_path: P) -> Result<()> {
     let path = file_path.as_ref();
     println!("Loading 6D file: {:?}", path);
     
-    // Load the array
-    let array = load_array(path)?;
+    // Open the array lazily; only the inspected frames are read
+    let array = open_array(path)?;
     
     // Display basic information
     let dims = array.dimensions();
@@ -20,14 +26,41 @@ pub fn load_and_inspect_6d_file<P: AsRef<Path>>(file_path: P) -> Result<()> {
              dims.time, dims.position, dims.z, dims.channel, dims.height, dims.width);
     println!("Total elements: {}", dims.total_elements());
     println!("Memory usage: {} MB", array.memory_usage() / (1024 * 1024));
-    println!("Pixel size: {:.3} μm", array.pixel_size_um());
-    println!("Time interval: {:.1} s", array.time_interval_s());
+    println!("Voxel size (X × Y × Z): {}", array.voxel_size());
+    match array.timestamps_s() {
+        Some(timestamps) => println!("Time points: irregular, {:.1} s to {:.1} s",
+                                     timestamps[0], timestamps[timestamps.len() - 1]),
+        None => println!("Time interval: {:.1} s", array.time_interval_s()),
+    }
     println!("Data type: {}", array.data_type());
     
+    // Display the operations that produced the file
+    if !array.provenance().is_empty() {
+        println!("\nProvenance:");
+        for entry in array.provenance() {
+            println!("  {}", entry);
+        }
+    }
+    
     // Display channel information
     println!("\nChannels:");
     for (i, name) in array.channel_names().iter().enumerate() {
-        println!("  {}: {}", i, name);
+        match array.channels().get(i).filter(|settings| !settings.is_empty()) {
+            Some(settings) => println!("  {}: {} ({})", i, name, settings),
+            None => println!("  {}: {}", i, name),
+        }
+    }
+    
+    // Display stage positions and wells
+    if !array.positions().is_empty() {
+        println!("\nPositions:");
+        for (p, position) in array.positions().iter().enumerate() {
+            let label = position.label().unwrap_or_else(|| format!("Position {}", p));
+            match (position.stage_x_um, position.stage_y_um) {
+                (Some(x), Some(y)) => println!("  {}: {} at ({:.1}, {:.1}) μm", p, label, x, y),
+                _ => println!("  {}: {}", p, label),
+            }
+        }
     }
     
     // Display some frame statistics
@@ -67,4 +100,17 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
     
+    #[test]
+    fn test_generate_small_test_file() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("small.meta");
+        
+        generate_small_test_file(&file_path).unwrap();
+        
+        let array = open_array(&file_path).unwrap();
+        assert_eq!(array.dimensions().time, 3);
+        assert_eq!(array.dimensions().z, 2);
+        assert_eq!(array.dimensions().channel, 2);
+        assert_eq!(array.dimensions().height, 32);
+    }
 }
\ No newline at end of file